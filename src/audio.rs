@@ -0,0 +1,12 @@
+//! Anything that processes audio after it's been decoded.
+
+pub mod fade;
+#[cfg(unix)]
+pub mod fifo;
+pub mod filters;
+pub mod normalize;
+pub mod position;
+pub mod record;
+pub mod speed;
+pub mod tap;
+pub mod tee;
@@ -0,0 +1,153 @@
+//! Fades tracks in & out around pausing, resuming & skipping, instead of cutting them off.
+//!
+//! This is separate from the [rodio::Sink]'s volume, so that the volume
+//! which is shown & saved never changes in the middle of a fade. The same
+//! goes for [ducking](Fader::duck), which fades to a lower level instead.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Source};
+
+/// Whether tracks should be heard, which is shared between the
+/// [Player](crate::player::Player) and every [Faded] source.
+#[derive(Debug)]
+pub struct Fader {
+    /// How long it takes to fade all the way in or out.
+    duration: Duration,
+
+    /// Whether the sources are fading in, rather than out.
+    audible: AtomicBool,
+
+    /// The level that the sources fade in to, which is below `1.0` while ducked.
+    /// This is stored as the bits of an [f32].
+    level: AtomicU32,
+}
+
+impl Fader {
+    /// Creates a fader that takes `duration` to fade, where [Duration::ZERO] turns fading off.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            audible: AtomicBool::new(true),
+            level: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    /// How long it takes to fade all the way in or out.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Starts fading in.
+    pub fn fade_in(&self) {
+        self.audible.store(true, Ordering::Relaxed);
+    }
+
+    /// Starts fading out, which finishes after [Fader::duration].
+    pub fn fade_out(&self) {
+        self.audible.store(false, Ordering::Relaxed);
+    }
+
+    /// Turns the sources down to `level`, where `1.0` is back to normal, while
+    /// something else is making noise. This fades just like pausing does.
+    pub fn duck(&self, level: f32) {
+        self.level
+            .store(level.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Whether the sources are turned down, see [Fader::duck].
+    pub fn ducked(&self) -> bool {
+        f32::from_bits(self.level.load(Ordering::Relaxed)) < 1.0
+    }
+}
+
+/// A [Source] which follows the [Fader].
+pub struct Faded<S: Source<Item = f32>> {
+    /// The source that's being faded.
+    inner: S,
+
+    /// Whether to fade in or out.
+    fader: Arc<Fader>,
+
+    /// The gain that's currently applied, which is [None] until the first sample, since
+    /// a track that's queued up shouldn't start fading until it's actually playing.
+    gain: Option<f32>,
+}
+
+impl<S: Source<Item = f32>> Faded<S> {
+    /// Makes a source fade along with `fader`.
+    pub fn new(inner: S, fader: Arc<Fader>) -> Self {
+        Self {
+            inner,
+            fader,
+            gain: None,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Faded<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let target = if self.fader.audible.load(Ordering::Relaxed) {
+            f32::from_bits(self.fader.level.load(Ordering::Relaxed))
+        } else {
+            0.0
+        };
+
+        let gain = match self.gain {
+            Some(gain) if gain != target => {
+                let samples = self.fader.duration.as_secs_f32()
+                    * self.inner.sample_rate() as f32
+                    * f32::from(self.inner.channels());
+
+                // A step that's bigger than the gap just jumps straight there, which is
+                // also what happens when fading is turned off.
+                let step = 1.0 / samples;
+                if (target - gain).abs() <= step {
+                    target
+                } else {
+                    gain + step.copysign(target - gain)
+                }
+            }
+            Some(gain) => gain,
+            None => target,
+        };
+
+        self.gain = Some(gain);
+        Some(sample * gain)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Faded<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(position)
+    }
+}
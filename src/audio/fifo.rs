@@ -0,0 +1,96 @@
+//! Copies the samples on their way to the speakers into a named pipe, for
+//! visualizers like [cava](https://github.com/karlstav/cava) to read.
+//!
+//! Whatever's in the pipe is always 16 bit little endian stereo at [RATE](super::tee::RATE), which
+//! is what cava expects by default, no matter what the tracks themselves are.
+//! If nothing is reading from the pipe, or the reader falls behind, then the
+//! samples are just thrown away.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+    time::Duration,
+};
+
+use eyre::{bail, eyre};
+use tracing::{debug, warn};
+
+use super::tee::{convert, Batch, Destination};
+
+/// How many batches can be waiting to be written before new ones are thrown away.
+const CAPACITY: usize = 16;
+
+/// The named pipe that the samples go to.
+pub struct Fifo {
+    /// Where batches are sent to be written, by a thread of their own.
+    tx: SyncSender<Batch>,
+}
+
+impl Fifo {
+    /// Opens the pipe at `path`, making it first if it doesn't exist yet.
+    ///
+    /// Opening a pipe waits until something starts reading from it,
+    /// so the actual writing happens on another thread.
+    pub fn open(path: PathBuf) -> eyre::Result<Self> {
+        if !path.exists() {
+            let status = Command::new("mkfifo")
+                .arg(&path)
+                .status()
+                .map_err(|error| eyre!("unable to run mkfifo: {}", error))?;
+            if !status.success() {
+                bail!("unable to make a pipe at {}", path.display());
+            }
+        }
+
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        thread::Builder::new()
+            .name("fifo".to_owned())
+            .spawn(move || write(&path, &rx))?;
+
+        Ok(Self { tx })
+    }
+}
+
+impl Destination for Fifo {
+    fn send(&self, batch: &Batch) {
+        let _ = self.tx.try_send(batch.clone());
+    }
+}
+
+/// Writes batches to the pipe until the [Fifo] is dropped, opening it again whenever the reader goes away.
+fn write(path: &Path, rx: &Receiver<Batch>) {
+    let mut position = 0.0;
+
+    loop {
+        // This blocks until something starts reading.
+        let mut pipe = match OpenOptions::new().write(true).open(path) {
+            Ok(pipe) => pipe,
+            Err(error) => {
+                warn!("unable to open {}: {}", path.display(), error);
+                return;
+            }
+        };
+        debug!("started writing to {}", path.display());
+
+        // Anything that came in while waiting is stale by now.
+        while rx.try_recv().is_ok() {}
+
+        loop {
+            let Ok(batch) = rx.recv() else {
+                return;
+            };
+
+            if pipe.write_all(&convert(&batch, &mut position)).is_err() {
+                debug!("stopped writing to {}", path.display());
+                break;
+            }
+        }
+
+        // Stops the loop from spinning if the pipe was swapped for something that can't be written to.
+        thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,263 @@
+//! Filters which are applied to tracks between decoding & playback,
+//! which for now is just a simple bass & treble equalizer.
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicI8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Source};
+
+/// The highest that the bass or treble can be boosted or cut, in decibels.
+pub const MAX_GAIN: i8 = 12;
+
+/// The frequency below which the bass shelf applies.
+const BASS_FREQUENCY: f32 = 200.0;
+
+/// The frequency above which the treble shelf applies.
+const TREBLE_FREQUENCY: f32 = 3000.0;
+
+/// How many frames to play before checking if the gains have changed.
+const CHECK_INTERVAL: usize = 1024;
+
+/// The equalizer's settings, which are shared between the [Player](crate::player::Player)
+/// and every [Equalized] source, so that they can be changed while a track is playing.
+#[derive(Debug, Default)]
+pub struct Equalizer {
+    /// The bass gain in decibels.
+    bass: AtomicI8,
+
+    /// The treble gain in decibels.
+    treble: AtomicI8,
+}
+
+impl Equalizer {
+    /// Creates a new equalizer, clamping both gains to [MAX_GAIN].
+    pub fn new(bass: i8, treble: i8) -> Self {
+        Self {
+            bass: AtomicI8::new(bass.clamp(-MAX_GAIN, MAX_GAIN)),
+            treble: AtomicI8::new(treble.clamp(-MAX_GAIN, MAX_GAIN)),
+        }
+    }
+
+    /// The bass gain in decibels.
+    pub fn bass(&self) -> i8 {
+        self.bass.load(Ordering::Relaxed)
+    }
+
+    /// The treble gain in decibels.
+    pub fn treble(&self) -> i8 {
+        self.treble.load(Ordering::Relaxed)
+    }
+
+    /// Changes the bass gain by `change` decibels.
+    pub fn change_bass(&self, change: i8) {
+        Self::change(&self.bass, change);
+    }
+
+    /// Changes the treble gain by `change` decibels.
+    pub fn change_treble(&self, change: i8) {
+        Self::change(&self.treble, change);
+    }
+
+    /// Adds `change` to one of the gains, without going past [MAX_GAIN].
+    fn change(gain: &AtomicI8, change: i8) {
+        let _ = gain.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+            Some(x.saturating_add(change).clamp(-MAX_GAIN, MAX_GAIN))
+        });
+    }
+}
+
+/// Whether a [Biquad] boosts or cuts frequencies below or above its cutoff.
+#[derive(Clone, Copy)]
+enum Shelf {
+    Low,
+    High,
+}
+
+/// A biquad shelving filter, using the formulas from the
+/// [Audio EQ Cookbook](https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html).
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    /// The normalized coefficients, as `[b0, b1, b2, a1, a2]`.
+    coefficients: [f32; 5],
+}
+
+impl Biquad {
+    /// Calculates the coefficients for a shelf with a slope of 1.
+    fn new(shelf: Shelf, frequency: f32, gain: i8, sample_rate: u32) -> Self {
+        let a = 10f32.powf(f32::from(gain) / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / 2.0 * 2f32.sqrt();
+        let beta = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match shelf {
+            Shelf::Low => (
+                a * ((a + 1.0) - (a - 1.0) * cos + beta),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - beta),
+                (a + 1.0) + (a - 1.0) * cos + beta,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - beta,
+            ),
+            Shelf::High => (
+                a * ((a + 1.0) + (a - 1.0) * cos + beta),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - beta),
+                (a + 1.0) - (a - 1.0) * cos + beta,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - beta,
+            ),
+        };
+
+        Self {
+            coefficients: [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0],
+        }
+    }
+
+    /// Filters a single sample, where `state` is the last two inputs & outputs of its channel.
+    fn process(&self, sample: f32, state: &mut [f32; 4]) -> f32 {
+        let [b0, b1, b2, a1, a2] = self.coefficients;
+        let [x1, x2, y1, y2] = *state;
+
+        let output = b0 * sample + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+        *state = [sample, x1, output, y1];
+
+        output
+    }
+}
+
+/// A [Source] with the [Equalizer] applied to it.
+pub struct Equalized<S: Source<Item = f32>> {
+    /// The source that's being filtered.
+    inner: S,
+
+    /// The shared settings.
+    equalizer: Arc<Equalizer>,
+
+    /// The gains & sample rate that the filters were made for.
+    current: (i8, i8, u32),
+
+    /// The bass & treble filters.
+    filters: [Biquad; 2],
+
+    /// The state of both filters, for each channel.
+    states: Vec<[[f32; 4]; 2]>,
+
+    /// The channel that the next sample belongs to.
+    channel: usize,
+
+    /// How many frames have been played since the last time the gains were checked.
+    frames: usize,
+}
+
+impl<S: Source<Item = f32>> Equalized<S> {
+    /// Applies the equalizer to a source.
+    pub fn new(inner: S, equalizer: Arc<Equalizer>) -> Self {
+        let mut equalized = Self {
+            states: vec![[[0.0; 4]; 2]; usize::from(inner.channels())],
+            inner,
+            equalizer,
+            current: (0, 0, 0),
+            filters: [Biquad::default(); 2],
+            channel: 0,
+            frames: 0,
+        };
+
+        equalized.update();
+        equalized
+    }
+
+    /// Recalculates the filters if the gains or sample rate have changed.
+    fn update(&mut self) {
+        let current = (
+            self.equalizer.bass(),
+            self.equalizer.treble(),
+            self.inner.sample_rate(),
+        );
+
+        if current != self.current {
+            let (bass, treble, rate) = current;
+            self.filters = [
+                Biquad::new(Shelf::Low, BASS_FREQUENCY, bass, rate),
+                Biquad::new(Shelf::High, TREBLE_FREQUENCY, treble, rate),
+            ];
+            self.current = current;
+        }
+
+        let channels = usize::from(self.inner.channels());
+        if self.states.len() != channels {
+            self.states = vec![[[0.0; 4]; 2]; channels];
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Equalized<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        let channels = self.states.len();
+        if channels == 0 {
+            return Some(sample);
+        }
+
+        let channel = self.channel;
+        self.channel = (channel + 1) % channels;
+        if self.channel == 0 {
+            self.frames += 1;
+            if self.frames == CHECK_INTERVAL {
+                self.frames = 0;
+                self.update();
+            }
+        }
+
+        // With both gains at zero the filters don't do anything.
+        if self.current.0 == 0 && self.current.1 == 0 {
+            return Some(sample);
+        }
+
+        let [bass, treble] = &mut self.states[channel];
+        let sample = self.filters[0].process(sample, bass);
+        let sample = self.filters[1].process(sample, treble);
+
+        Some(sample.clamp(-1.0, 1.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Equalized<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(position)?;
+
+        // The old state would cause a small pop after jumping.
+        self.states.iter_mut().for_each(|x| *x = [[0.0; 4]; 2]);
+        self.channel = 0;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Evens out the loudness of tracks, since tracks from different sources
+//! can be mastered at very different levels.
+//!
+//! Tracks with a ReplayGain tag just have it applied. Everything else goes
+//! through a running estimate of its loudness instead, which slowly moves the
+//! gain towards whatever brings the track to the same level.
+
+use std::time::Duration;
+
+use rodio::{source::SeekError, Source};
+
+/// The level that tracks are brought to, as an RMS amplitude.
+///
+/// This is -18 dBFS, which is roughly the reference level of ReplayGain,
+/// so that tagged & untagged tracks end up around the same loudness.
+const TARGET: f32 = 0.126;
+
+/// How many seconds of audio the loudness estimate is averaged over.
+const WINDOW: f32 = 3.0;
+
+/// How many samples to play before the gain is worked out again.
+const UPDATE_INTERVAL: usize = 1024;
+
+/// How far the gain moves towards where it should be on every update,
+/// which stops it from pumping along with the music.
+const SMOOTHING: f32 = 0.05;
+
+/// The quietest that a track can be turned down, which is -12 dB.
+const MIN_GAIN: f32 = 0.25;
+
+/// The loudest that a track can be turned up, which is +6 dB, so that
+/// quiet intros & fades don't get blown up.
+const MAX_GAIN: f32 = 2.0;
+
+/// Below this mean square, which is -60 dBFS, the track is treated as silent
+/// and the gain is left alone.
+const SILENCE: f32 = 1e-6;
+
+/// A [Source] whose loudness is normalized, either with its ReplayGain or an estimate.
+pub struct Normalized<S: Source<Item = f32>> {
+    /// The source that's being normalized.
+    inner: S,
+
+    /// The gain from the track's ReplayGain tag, which is used as is if it has one.
+    replay_gain: Option<f32>,
+
+    /// The running mean square of the samples.
+    power: f32,
+
+    /// How many samples have gone into `power`, up to the length of the [WINDOW].
+    seen: usize,
+
+    /// The gain that's currently being applied.
+    gain: f32,
+
+    /// How many samples have been played since the gain was last updated.
+    count: usize,
+}
+
+impl<S: Source<Item = f32>> Normalized<S> {
+    /// Normalizes a source, using `replay_gain` if it's known.
+    pub fn new(inner: S, replay_gain: Option<f32>) -> Self {
+        Self {
+            inner,
+            replay_gain,
+            power: 0.0,
+            seen: 0,
+            gain: 1.0,
+            count: 0,
+        }
+    }
+
+    /// Moves the gain towards whatever would bring the estimate to [TARGET].
+    fn update(&mut self) {
+        if self.power < SILENCE {
+            return;
+        }
+
+        let target = (TARGET / self.power.sqrt()).clamp(MIN_GAIN, MAX_GAIN);
+        self.gain += (target - self.gain) * SMOOTHING;
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Normalized<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        if let Some(gain) = self.replay_gain {
+            return Some((sample * gain).clamp(-1.0, 1.0));
+        }
+
+        // Until a whole window has been played, this is a plain average, so
+        // that the estimate is already usable a fraction of a second in.
+        let window = WINDOW * self.inner.sample_rate() as f32 * f32::from(self.inner.channels());
+        self.seen = (self.seen + 1).min(window as usize);
+        self.power += (sample * sample - self.power) / self.seen as f32;
+
+        self.count += 1;
+        if self.count >= UPDATE_INTERVAL {
+            self.count = 0;
+            self.update();
+        }
+
+        // Boosting a quiet track can push its peaks past full scale, so they're clipped here.
+        Some((sample * self.gain).clamp(-1.0, 1.0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Normalized<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        // The loudness of the track is the same wherever it's at, so the estimate is kept.
+        self.inner.try_seek(position)
+    }
+}
@@ -0,0 +1,48 @@
+//! Keeps track of how far into the current track playback is, by counting the
+//! frames that have been read from it, rather than going by [rodio::Sink::get_pos].
+//!
+//! The sink's position is in real time, so it drifts once the speed changes, and
+//! it's only as good as the decoder's guess after seeking through a variable
+//! bitrate file. Counting frames on the way out of the source is exact, and
+//! doesn't move at all while paused, since nothing is read then.
+
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The position within the track that's playing, which is shared between
+/// the [Player](crate::player::Player) & the source that's playing.
+#[derive(Debug, Default)]
+pub struct Position {
+    /// How many frames into the track playback is.
+    frames: AtomicU64,
+
+    /// The sample rate of the track, which is what `frames` is counted in.
+    rate: AtomicU32,
+}
+
+impl Position {
+    /// How far into the track playback is.
+    pub fn elapsed(&self) -> Duration {
+        let rate = self.rate.load(Ordering::Relaxed);
+        if rate == 0 {
+            return Duration::ZERO;
+        }
+
+        let frames = self.frames.load(Ordering::Relaxed);
+        Duration::from_secs(frames / u64::from(rate))
+            + Duration::from_nanos(frames % u64::from(rate) * 1_000_000_000 / u64::from(rate))
+    }
+
+    /// Stores how many frames into the track playback is, at a sample rate of `rate`.
+    pub fn store(&self, frames: u64, rate: u32) {
+        self.rate.store(rate, Ordering::Relaxed);
+        self.frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// Resets the position back to the start, for when the sink is stopped.
+    pub fn reset(&self) {
+        self.frames.store(0, Ordering::Relaxed);
+    }
+}
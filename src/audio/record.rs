@@ -0,0 +1,258 @@
+//! Records everything that's played into a single file, like `--record session.mp3`,
+//! with a chapter for every track.
+//!
+//! The encoding is done by [ffmpeg](https://ffmpeg.org), which has to be installed
+//! separately. The audio is piped into it as it's played, so pausing doesn't leave
+//! any silence, & once lowfi stops, the chapters are added in a second pass which
+//! only copies the audio, rather than encoding it all over again.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use eyre::{bail, eyre};
+use tracing::{debug, warn};
+
+use super::tee::{convert, Batch, Destination, RATE};
+
+/// The name of the ffmpeg binary, which has to be somewhere in the `PATH`.
+const BINARY: &str = "ffmpeg";
+
+/// How many batches can be waiting to be encoded, which is a few seconds worth,
+/// since unlike with a pipe, anything that's thrown away is a gap in the recording.
+const CAPACITY: usize = 256;
+
+/// A track in the recording.
+struct Chapter {
+    /// How many frames into the recording the track starts.
+    start: u64,
+
+    /// The name of the track.
+    title: String,
+}
+
+/// What the writer thread ends up with, which is every chapter & how long the recording is in frames.
+type Written = eyre::Result<(Vec<Chapter>, u64)>;
+
+/// A recording that's in progress.
+pub struct Recorder {
+    /// Where the recording ends up.
+    path: PathBuf,
+
+    /// Where the recording is encoded to, before the chapters are added.
+    partial: PathBuf,
+
+    /// Where batches are sent to be encoded, which is taken once the recording is finished.
+    tx: Mutex<Option<SyncSender<Batch>>>,
+
+    /// The thread that's feeding ffmpeg.
+    writer: Mutex<Option<JoinHandle<Written>>>,
+}
+
+impl Recorder {
+    /// Gets the arguments that tell ffmpeg what to encode with, going by the extension of `path`.
+    fn codec(path: &Path) -> eyre::Result<&'static [&'static str]> {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(str::to_lowercase);
+
+        Ok(match extension.as_deref() {
+            Some("mp3") => &["-c:a", "libmp3lame", "-q:a", "2"],
+            Some("ogg") => &["-c:a", "libvorbis", "-q:a", "5"],
+            _ => bail!("recordings have to be either .mp3 or .ogg"),
+        })
+    }
+
+    /// Starts recording to `path`.
+    pub fn start(path: PathBuf) -> eyre::Result<Self> {
+        let codec = Self::codec(&path)?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| eyre!("{} isn't a file", path.display()))?
+            .to_string_lossy();
+        let partial = path.with_file_name(format!(".partial.{}", name));
+
+        let mut child = Command::new(BINARY)
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args([
+                "-f",
+                "s16le",
+                "-ar",
+                &RATE.to_string(),
+                "-ac",
+                "2",
+                "-i",
+                "pipe:0",
+            ])
+            .args(codec)
+            .arg(&partial)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => {
+                    eyre!("{} isn't installed, see https://ffmpeg.org", BINARY)
+                }
+                _ => eyre!("unable to run {}: {}", BINARY, error),
+            })?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| eyre!("{} doesn't have any input", BINARY))?;
+
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        let writer = thread::Builder::new()
+            .name("record".to_owned())
+            .spawn(move || write(child, stdin, &rx))?;
+
+        Ok(Self {
+            path,
+            partial,
+            tx: Mutex::new(Some(tx)),
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    /// Stops recording & adds the chapters, returning where the recording was saved.
+    ///
+    /// This should only be called once the music has stopped, since anything else is left out.
+    pub fn finish(&self) -> eyre::Result<&Path> {
+        // Dropping the sender is what tells the writer that there's nothing left.
+        self.tx.lock().unwrap_or_else(|x| x.into_inner()).take();
+        let Some(writer) = self.writer.lock().unwrap_or_else(|x| x.into_inner()).take() else {
+            bail!("the recording was already finished");
+        };
+
+        let (chapters, length) = writer
+            .join()
+            .map_err(|_| eyre!("the recording stopped unexpectedly"))??;
+
+        if length == 0 {
+            let _ = fs::remove_file(&self.partial);
+            bail!("nothing was played, so there's nothing to save");
+        }
+
+        // If the chapters can't be added, a recording without them is still better than nothing.
+        if let Err(error) = self.chapters(&chapters, length) {
+            warn!("unable to add the chapters to the recording: {:#}", error);
+            fs::rename(&self.partial, &self.path)?;
+        }
+
+        Ok(&self.path)
+    }
+
+    /// Copies the partial recording over to where it's meant to go, with `chapters` added to it.
+    fn chapters(&self, chapters: &[Chapter], length: u64) -> eyre::Result<()> {
+        let mut metadata = String::from(";FFMETADATA1\ntitle=lowfi\n");
+        for (i, chapter) in chapters.iter().enumerate() {
+            let end = chapters.get(i + 1).map_or(length, |x| x.start);
+            metadata.push_str(&format!(
+                "\n[CHAPTER]\nTIMEBASE=1/{}\nSTART={}\nEND={}\ntitle={}\n",
+                RATE,
+                chapter.start,
+                end,
+                escape(&chapter.title)
+            ));
+        }
+
+        let file = self.partial.with_extension("txt");
+        fs::write(&file, metadata)?;
+
+        let output = Command::new(BINARY)
+            .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+            .arg(&self.partial)
+            .arg("-i")
+            .arg(&file)
+            .args(["-map_metadata", "1", "-map_chapters", "1", "-c", "copy"])
+            .arg(&self.path)
+            .stdin(Stdio::null())
+            .output();
+        let _ = fs::remove_file(&file);
+
+        let output = output?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        fs::remove_file(&self.partial)?;
+        Ok(())
+    }
+}
+
+impl Destination for Recorder {
+    fn send(&self, batch: &Batch) {
+        let tx = self.tx.lock().unwrap_or_else(|x| x.into_inner());
+        if let Some(Err(mpsc::TrySendError::Full(_))) =
+            tx.as_ref().map(|x| x.try_send(batch.clone()))
+        {
+            debug!("the recording fell behind, so some of it was left out");
+        }
+    }
+}
+
+/// Escapes the characters that mean something in ffmpeg's metadata files.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if matches!(character, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+/// Feeds batches to ffmpeg until the [Recorder] is finished, keeping track of where each track starts.
+fn write(child: Child, mut stdin: impl Write, rx: &Receiver<Batch>) -> Written {
+    let mut chapters = Vec::new();
+    let mut position = 0.0;
+    let mut length = 0;
+
+    while let Ok(batch) = rx.recv() {
+        if let Some(title) = &batch.track {
+            chapters.push(Chapter {
+                start: length,
+                title: title.clone(),
+            });
+        }
+
+        let data = convert(&batch, &mut position);
+        length += data.len() as u64 / 4;
+        if let Err(error) = stdin.write_all(&data) {
+            drop(stdin);
+            let output = child.wait_with_output()?;
+            bail!(
+                "{} stopped, {}",
+                BINARY,
+                match String::from_utf8_lossy(&output.stderr).trim() {
+                    "" => error.to_string(),
+                    stderr => stderr.to_owned(),
+                }
+            );
+        }
+    }
+
+    // Closing the input is what tells ffmpeg to finish up.
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "{} failed, {}",
+            BINARY,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok((chapters, length))
+}
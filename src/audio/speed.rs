@@ -0,0 +1,313 @@
+//! Changes the playback speed of tracks, optionally without changing their pitch.
+//!
+//! Without pitch correction, the speed is just passed on to [rodio::Sink::set_speed],
+//! which resamples the audio. With it, tracks are time stretched with a simple
+//! version of [WSOLA](https://www.surina.net/article/time-and-pitch-scaling.html)
+//! instead, which keeps the pitch the same.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Source};
+
+use super::position::Position;
+
+/// The slowest that tracks can be played.
+pub const MIN_SPEED: f32 = 0.5;
+
+/// The fastest that tracks can be played.
+pub const MAX_SPEED: f32 = 2.0;
+
+/// The length of each windowed segment in frames.
+const SEGMENT: usize = 2048;
+
+/// How many frames are output for each segment, which is half of it so the windows overlap.
+const HOP: usize = SEGMENT / 2;
+
+/// How far each segment can be moved from where it'd normally be, in order to line it up
+/// with the previous one, which is what stops the stretched audio from sounding warbly.
+const SEARCH: usize = 128;
+
+/// The speed settings, which are shared between the [Player](crate::player::Player)
+/// and every [Stretched] source.
+#[derive(Debug)]
+pub struct Speed {
+    /// The speed as the bits of an [f32], where `1.0` is normal.
+    factor: AtomicU32,
+
+    /// Whether to keep the pitch the same when the speed changes.
+    pitch_correction: bool,
+}
+
+impl Speed {
+    /// Creates the speed settings, clamping `factor` between [MIN_SPEED] and [MAX_SPEED].
+    pub fn new(factor: f32, pitch_correction: bool) -> Self {
+        Self {
+            factor: AtomicU32::new(factor.clamp(MIN_SPEED, MAX_SPEED).to_bits()),
+            pitch_correction,
+        }
+    }
+
+    /// The current speed, where `1.0` is normal.
+    pub fn factor(&self) -> f32 {
+        f32::from_bits(self.factor.load(Ordering::Relaxed))
+    }
+
+    /// Whether the pitch is kept the same when the speed changes.
+    pub fn pitch_correction(&self) -> bool {
+        self.pitch_correction
+    }
+
+    /// Changes the speed by `change`, returning the new speed.
+    pub fn change(&self, change: f32) -> f32 {
+        // Rounding stops floating point errors from piling up after a few changes.
+        let factor = ((self.factor() + change) * 100.0).round() / 100.0;
+        let factor = factor.clamp(MIN_SPEED, MAX_SPEED);
+        self.factor.store(factor.to_bits(), Ordering::Relaxed);
+
+        factor
+    }
+}
+
+/// A [Source] which keeps track of its [Position], and time stretches
+/// itself with pitch correction once the [Speed] isn't normal anymore.
+///
+/// The position is counted in frames of the track itself, so it stays
+/// accurate no matter how fast it's being played.
+pub struct Stretched<S: Source<Item = f32>> {
+    /// The source that's being stretched.
+    inner: S,
+
+    /// The shared settings.
+    speed: Arc<Speed>,
+
+    /// Where the position within the track is kept.
+    position: Arc<Position>,
+
+    /// Whether the time stretching has kicked in, which stays on until seeking
+    /// so that nothing is skipped when going back to normal speed.
+    stretching: bool,
+
+    /// The frames read from `inner` which might still be needed, interleaved.
+    input: VecDeque<f32>,
+
+    /// The index of the first frame in `input`, counting from the start of the track.
+    start: usize,
+
+    /// Where the next segment would start without any adjustment, counting from the start of the track.
+    nominal: f64,
+
+    /// Where the continuation of the last segment starts, which the next one is lined up with.
+    natural: Option<usize>,
+
+    /// The output that's still being overlapped & added to.
+    overlap: Vec<f32>,
+
+    /// The output that's ready to be played.
+    output: VecDeque<f32>,
+
+    /// How many frames of the track each frame of `output` covers, which is the speed it was stretched at.
+    step: f64,
+
+    /// Whether `inner` has run out.
+    finished: bool,
+
+    /// How many samples have been passed through before stretching kicked in.
+    samples: usize,
+}
+
+impl<S: Source<Item = f32>> Stretched<S> {
+    /// Wraps a source, which starts out just passing through the audio.
+    pub fn new(inner: S, speed: Arc<Speed>, position: Arc<Position>) -> Self {
+        Self {
+            inner,
+            speed,
+            position,
+            stretching: false,
+            input: VecDeque::new(),
+            start: 0,
+            nominal: 0.0,
+            natural: None,
+            overlap: Vec::new(),
+            output: VecDeque::new(),
+            step: 1.0,
+            finished: false,
+            samples: 0,
+        }
+    }
+
+    /// Stores the current position, which is `frames` into the track.
+    fn store_position(&self, frames: f64) {
+        self.position
+            .store(frames.max(0.0) as u64, self.inner.sample_rate());
+    }
+
+    /// Gets a sample from `input`, where `frame` counts from the start of the track.
+    /// Anything past the end of the track is silent.
+    fn sample(&self, frame: usize, channel: usize, channels: usize) -> f32 {
+        let index = (frame - self.start) * channels + channel;
+        self.input.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Reads from `inner` until `input` reaches `frame`, or the track ends.
+    fn fill(&mut self, frame: usize, channels: usize) {
+        while !self.finished && self.start * channels + self.input.len() < frame * channels {
+            match self.inner.next() {
+                Some(sample) => self.input.push_back(sample),
+                None => self.finished = true,
+            }
+        }
+    }
+
+    /// Stretches a single segment, adding [HOP] frames to `output`.
+    /// Returns false if the track has ended.
+    fn stretch(&mut self) -> bool {
+        let channels = usize::from(self.inner.channels().max(1));
+        let nominal = self.nominal as usize;
+
+        self.fill(nominal + SEARCH + SEGMENT, channels);
+        let end = self.start + self.input.len() / channels;
+        if self.finished && nominal >= end {
+            return false;
+        }
+
+        // Finds the offset where the segment lines up best with the continuation of the last one.
+        let earliest = nominal.saturating_sub(SEARCH).max(self.start);
+        let position = match self.natural {
+            Some(natural) => {
+                let correlation = |start: usize| -> f32 {
+                    (0..HOP)
+                        .step_by(4)
+                        .map(|i| {
+                            self.sample(start + i, 0, channels)
+                                * self.sample(natural + i, 0, channels)
+                        })
+                        .sum()
+                };
+
+                (earliest..=nominal + SEARCH)
+                    .map(|start| (start, correlation(start)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map_or(nominal, |(start, _)| start)
+            }
+            None => nominal,
+        };
+
+        self.overlap.resize(SEGMENT * channels, 0.0);
+        for i in 0..SEGMENT {
+            let window = 0.5 - 0.5 * (2.0 * PI * i as f32 / SEGMENT as f32).cos();
+            for channel in 0..channels {
+                self.overlap[i * channels + channel] +=
+                    self.sample(position + i, channel, channels) * window;
+            }
+        }
+
+        self.output.extend(self.overlap.drain(..HOP * channels));
+        self.natural = Some(position + HOP);
+        self.step = f64::from(self.speed.factor());
+        self.nominal += HOP as f64 * self.step;
+
+        // Anything before the earliest frame the next segment could use isn't needed anymore.
+        let needed = (self.nominal as usize)
+            .saturating_sub(SEARCH)
+            .min(position + HOP);
+        while self.start < needed && self.input.len() >= channels {
+            self.input.drain(..channels);
+            self.start += 1;
+        }
+
+        true
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Stretched<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = usize::from(self.inner.channels().max(1));
+
+        // Stretching only kicks in between frames, so that the channels don't get mixed up.
+        if !self.stretching
+            && self.samples.is_multiple_of(channels)
+            && self.speed.pitch_correction
+            && self.speed.factor() != 1.0
+        {
+            self.stretching = true;
+            self.start = self.samples / channels;
+            self.nominal = self.start as f64;
+        }
+
+        if self.stretching {
+            while self.output.is_empty() {
+                if !self.stretch() {
+                    return None;
+                }
+            }
+
+            // The position is where the frame that's about to be played came from,
+            // rather than where the segment that's being stretched is up to.
+            let sample = self.output.pop_front();
+            if self.output.len().is_multiple_of(channels) {
+                let remaining = (self.output.len() / channels) as f64;
+                self.store_position(self.nominal - remaining * self.step);
+            }
+
+            return sample;
+        }
+
+        let sample = self.inner.next()?;
+        self.samples += 1;
+        if self.samples.is_multiple_of(channels) {
+            self.store_position((self.samples / channels) as f64);
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Stretched<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.stretching {
+            None
+        } else {
+            self.inner.current_frame_len()
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(position)?;
+
+        let frame = (position.as_secs_f64() * f64::from(self.inner.sample_rate())) as usize;
+        self.stretching = false;
+        self.input.clear();
+        self.overlap.clear();
+        self.output.clear();
+        self.natural = None;
+        self.finished = false;
+        self.samples = frame * usize::from(self.inner.channels().max(1));
+        self.start = frame;
+        self.nominal = frame as f64;
+        self.store_position(self.nominal);
+
+        Ok(())
+    }
+}
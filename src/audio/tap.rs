@@ -0,0 +1,247 @@
+//! Taps the samples on their way to the speakers, so that the UI can draw a visualizer of them.
+//!
+//! Nothing is collected unless the tap is active, since it'd otherwise
+//! just be extra work for the audio thread that nobody sees.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Source};
+
+/// How many samples the spectrum is worked out from, which has to be a power of two.
+const WINDOW: usize = 1024;
+
+/// How many frames are collected before they're handed over,
+/// so that the lock isn't taken for every single sample.
+const BATCH: usize = 256;
+
+/// The quietest that a band can be in decibels, which is drawn as an empty bar.
+const FLOOR: f32 = -60.0;
+
+/// The lowest frequency that the spectrum covers.
+const LOWEST: f32 = 40.0;
+
+/// The highest frequency that the spectrum covers.
+const HIGHEST: f32 = 16000.0;
+
+/// The samples that were played most recently, shared between every [Tapped] source & the UI.
+#[derive(Debug, Default)]
+pub struct Tap {
+    /// Whether anything is using the samples.
+    active: AtomicBool,
+
+    /// The last [WINDOW] samples, mixed down to mono, along with their sample rate.
+    samples: Mutex<(VecDeque<f32>, u32)>,
+}
+
+impl Tap {
+    /// Starts or stops collecting samples.
+    pub fn set_active(&self, active: bool) {
+        if !self.active.swap(active, Ordering::Relaxed) || active {
+            return;
+        }
+
+        // Old samples would make the visualizer jump when it's opened again.
+        self.lock().0.clear();
+    }
+
+    /// Gets the samples, even if the lock was poisoned.
+    fn lock(&self) -> std::sync::MutexGuard<'_, (VecDeque<f32>, u32)> {
+        self.samples.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Adds a batch of samples, dropping the oldest ones to stay at [WINDOW].
+    fn push(&self, batch: &[f32], rate: u32) {
+        let mut samples = self.lock();
+        samples.0.extend(batch);
+        samples.1 = rate;
+
+        let extra = samples.0.len().saturating_sub(WINDOW);
+        samples.0.drain(..extra);
+    }
+
+    /// Works out how loud each of `bands` frequency bands are, from 0 to 1.
+    ///
+    /// The bands are spaced logarithmically, since that's roughly how pitch is heard.
+    /// Until a whole window has been played, they're all 0.
+    pub fn spectrum(&self, bands: usize) -> Vec<f32> {
+        let (mut real, rate) = {
+            let samples = self.lock();
+            if samples.0.len() < WINDOW {
+                return vec![0.0; bands];
+            }
+
+            (samples.0.iter().copied().collect::<Vec<f32>>(), samples.1)
+        };
+
+        // A Hann window stops the edges of the window from smearing across every band.
+        for (i, sample) in real.iter_mut().enumerate() {
+            *sample *= 0.5 - 0.5 * (2.0 * PI * i as f32 / (WINDOW - 1) as f32).cos();
+        }
+
+        let mut imaginary = vec![0.0; WINDOW];
+        fft(&mut real, &mut imaginary);
+
+        // The window halves the amplitude on average, which is made up for here.
+        let magnitudes: Vec<f32> = (0..WINDOW / 2)
+            .map(|i| real[i].hypot(imaginary[i]) * 4.0 / WINDOW as f32)
+            .collect();
+
+        let resolution = rate as f32 / WINDOW as f32;
+        let frequency = |band: usize| LOWEST * (HIGHEST / LOWEST).powf(band as f32 / bands as f32);
+
+        (0..bands)
+            .map(|band| {
+                let low = (frequency(band) / resolution) as usize;
+                let high = (frequency(band + 1) / resolution) as usize;
+
+                // The lowest bands can be narrower than a single bin, in which case they share it.
+                let magnitude = magnitudes[low.max(1).min(magnitudes.len() - 1)..]
+                    .iter()
+                    .take((high - low).max(1))
+                    .fold(0.0f32, |x, y| x.max(*y));
+
+                let decibels = 20.0 * magnitude.max(f32::EPSILON).log10();
+                ((decibels - FLOOR) / -FLOOR).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+/// An in-place radix-2 fast Fourier transform, where both slices have to be the same power of two long.
+fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+    let n = real.len();
+
+    // The samples are put in bit-reversed order first, so that every step can be done in place.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + length / 2);
+
+                let re = real[b] * cos - imaginary[b] * sin;
+                let im = real[b] * sin + imaginary[b] * cos;
+
+                real[b] = real[a] - re;
+                imaginary[b] = imaginary[a] - im;
+                real[a] += re;
+                imaginary[a] += im;
+            }
+        }
+
+        length <<= 1;
+    }
+}
+
+/// A [Source] which hands a copy of its samples over to the [Tap], mixed down to mono.
+pub struct Tapped<S: Source<Item = f32>> {
+    /// The source that's being tapped.
+    inner: S,
+
+    /// Where the samples go.
+    tap: Arc<Tap>,
+
+    /// The samples that haven't been handed over yet.
+    batch: Vec<f32>,
+
+    /// The sum of the current frame so far.
+    frame: f32,
+
+    /// The channel that the next sample belongs to.
+    channel: usize,
+}
+
+impl<S: Source<Item = f32>> Tapped<S> {
+    /// Taps a source.
+    pub fn new(inner: S, tap: Arc<Tap>) -> Self {
+        Self {
+            inner,
+            tap,
+            batch: Vec::with_capacity(BATCH),
+            frame: 0.0,
+            channel: 0,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Tapped<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.inner.next()?;
+        if !self.tap.active.load(Ordering::Relaxed) {
+            return Some(sample);
+        }
+
+        let channels = usize::from(self.inner.channels()).max(1);
+        self.frame += sample;
+        self.channel += 1;
+
+        if self.channel >= channels {
+            self.batch.push(self.frame / channels as f32);
+            self.frame = 0.0;
+            self.channel = 0;
+
+            if self.batch.len() == BATCH {
+                self.tap.push(&self.batch, self.inner.sample_rate());
+                self.batch.clear();
+            }
+        }
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Tapped<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(position)?;
+        self.frame = 0.0;
+        self.channel = 0;
+
+        Ok(())
+    }
+}
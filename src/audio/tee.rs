@@ -0,0 +1,161 @@
+//! Copies the samples on their way to the speakers to somewhere else, like a
+//! [named pipe](super::fifo) or a [recording](super::record).
+//!
+//! The audio thread never waits on any of them, so samples are handed over in
+//! batches through a bounded channel, & thrown away if there's no room left.
+
+use std::{sync::Arc, time::Duration};
+
+use rodio::{source::SeekError, Source};
+
+/// The sample rate that the samples are converted to, see [convert].
+pub const RATE: u32 = 44100;
+
+/// How many frames are collected before they're handed over.
+const BATCH: usize = 512;
+
+/// A batch of samples from a single source, before it's been converted.
+#[derive(Clone)]
+pub struct Batch {
+    /// The interleaved samples.
+    pub samples: Vec<f32>,
+
+    /// How many channels the samples have.
+    pub channels: u16,
+
+    /// The sample rate of the samples.
+    pub rate: u32,
+
+    /// The name of the track, which is only there for the first batch of each one.
+    pub track: Option<String>,
+}
+
+/// Somewhere that a [Teed] source copies its samples to.
+pub trait Destination: Send + Sync {
+    /// Takes a batch, which mustn't ever block, since this is called from the audio thread.
+    fn send(&self, batch: &Batch);
+}
+
+/// Converts a batch to 16 bit little endian stereo at [RATE], picking whichever frame is closest for each one.
+///
+/// `position` is how far into the batch the next frame is, in frames of the batch,
+/// which is carried over so that there aren't any gaps between batches.
+pub fn convert(batch: &Batch, position: &mut f64) -> Vec<u8> {
+    let channels = usize::from(batch.channels).max(1);
+    let frames: Vec<&[f32]> = batch.samples.chunks_exact(channels).collect();
+    let step = f64::from(batch.rate) / f64::from(RATE);
+
+    let mut data = Vec::with_capacity((frames.len() as f64 / step) as usize * 4 + 4);
+    while let Some(frame) = frames.get(*position as usize) {
+        let left = frame[0];
+        let right = frame.get(1).copied().unwrap_or(left);
+
+        for sample in [left, right] {
+            let sample = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        *position += step;
+    }
+
+    *position -= frames.len() as f64;
+    data
+}
+
+/// Copies the samples of a source to every one of its [Destination]s.
+pub struct Teed<S: Source<Item = f32>> {
+    /// The source that's being copied.
+    inner: S,
+
+    /// Where the samples go, which might be nowhere.
+    destinations: Vec<Arc<dyn Destination>>,
+
+    /// The samples that haven't been handed over yet.
+    batch: Vec<f32>,
+
+    /// The name of the track, until the first batch has been handed over.
+    track: Option<String>,
+}
+
+impl<S: Source<Item = f32>> Teed<S> {
+    /// Copies a source, which is the track called `track`, to `destinations`.
+    pub fn new(inner: S, destinations: Vec<Arc<dyn Destination>>, track: String) -> Self {
+        Self {
+            inner,
+            destinations,
+            batch: Vec::new(),
+            track: Some(track),
+        }
+    }
+
+    /// Hands over whatever samples have built up to every destination.
+    fn flush(&mut self) {
+        let batch = Batch {
+            samples: std::mem::take(&mut self.batch),
+            channels: self.inner.channels().max(1),
+            rate: self.inner.sample_rate(),
+            track: self.track.take(),
+        };
+
+        for destination in &self.destinations {
+            destination.send(&batch);
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for Teed<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let Some(sample) = self.inner.next() else {
+            // The end of the track rarely lines up with a whole batch, so the rest is sent as is.
+            if !self.batch.is_empty() {
+                self.flush();
+            }
+
+            return None;
+        };
+
+        if self.destinations.is_empty() {
+            return Some(sample);
+        }
+
+        self.batch.push(sample);
+        if self.batch.len() >= BATCH * usize::from(self.inner.channels().max(1)) {
+            self.flush();
+        }
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Teed<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(position)?;
+
+        // A batch which is split between two places would have a frame that's half & half.
+        self.batch.clear();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! Has all of the functions for the `check` command, which looks for
+//! tracks in the track list that are missing, broken, or in it twice.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use bytes::{Bytes, BytesMut};
+use futures::{stream, StreamExt};
+use reqwest::{Method, StatusCode};
+
+use lowfi::{
+    config::Config,
+    tracks::{download::Network, hls, list::Entry, local, source, DecodedTrack, Track, TrackInfo},
+};
+
+use crate::play;
+
+/// How much of each track is downloaded, which is enough to get past
+/// any tags at the start and decode the first bit of the audio.
+const PROBE_SIZE: usize = 512 * 1024;
+
+/// How many tracks are checked at once.
+const CONCURRENCY: usize = 8;
+
+/// Checks that a track can be decoded, going by just the start of it.
+fn decode(entry: &Entry, data: Bytes) -> eyre::Result<()> {
+    let track = Track {
+        name: entry
+            .name
+            .clone()
+            .unwrap_or_else(|| TrackInfo::format_name(&entry.url)),
+        url: entry.url.clone(),
+        data,
+        stream: None,
+    };
+
+    DecodedTrack::new(track).map(|_| ())
+}
+
+/// Downloads the start of a track, with a `HEAD` first so that a dead
+/// link is caught without downloading anything.
+async fn probe(url: &str, network: &Network) -> eyre::Result<Bytes> {
+    // Plenty of servers don't bother with `HEAD`, so only an actual error counts.
+    let head = network.request(Method::HEAD, url).send().await?;
+    if !matches!(
+        head.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        head.error_for_status()?;
+    }
+
+    let mut response = network
+        .request(Method::GET, url)
+        .header("Range", format!("bytes=0-{}", PROBE_SIZE - 1))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    // Servers which ignore the range send everything, so stop once there's enough.
+    let mut data = BytesMut::new();
+    while let Some(chunk) = response.chunk().await? {
+        data.extend_from_slice(&chunk);
+        if data.len() >= PROBE_SIZE {
+            break;
+        }
+    }
+
+    Ok(data.freeze())
+}
+
+/// Checks a single track, returning what's wrong with it if anything is.
+async fn check(entry: &Entry, network: &Network) -> Option<String> {
+    if hls::is_stream(&entry.url) {
+        return Track::fetch(entry, network, None, None)
+            .await
+            .err()
+            .map(|error| format!("doesn't play: {:#}", error));
+    }
+
+    let data = if local::is_local(&entry.url) {
+        local::read(&entry.url).await
+    } else {
+        match source::locate(&entry.url, network).await {
+            Ok(location) => probe(location.as_deref().unwrap_or(&entry.url), network).await,
+            Err(error) => Err(error),
+        }
+    };
+
+    match data {
+        Ok(data) => decode(entry, data)
+            .err()
+            .map(|error| format!("doesn't decode: {:#}", error)),
+        Err(error) => Some(format!("dead: {:#}", error)),
+    }
+}
+
+/// Checks every track in the track list, which is the same one that lowfi would
+/// play with the same arguments, and reports any problems.
+///
+/// If there's an `output`, then a cleaned up list with only the working tracks,
+/// and without any duplicates, is written to it.
+pub async fn run(
+    config: Config,
+    tracks: Option<PathBuf>,
+    output: Option<PathBuf>,
+) -> eyre::Result<()> {
+    let network = Network::new(&config.network)?;
+    let (list, _) = play::load(&config, tracks, &network).await?;
+    let entries = list.entries();
+
+    // Duplicates are the same URL, even if they've been given different names.
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut unique = Vec::new();
+    for entry in entries {
+        let count = counts.entry(&entry.url).or_default();
+        *count += 1;
+        if *count == 1 {
+            unique.push(entry);
+        }
+    }
+
+    eprintln!("checking {} tracks...", unique.len());
+    let results: Vec<(&Entry, Option<String>)> = stream::iter(unique)
+        .map(|entry| {
+            let network = &network;
+            async move { (entry, check(entry, network).await) }
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut broken = 0;
+    let mut working = Vec::new();
+    for (entry, problem) in results {
+        match problem {
+            Some(problem) => {
+                broken += 1;
+                println!("{}\n  {}", entry.url, problem);
+            }
+            None => working.push(entry),
+        }
+    }
+
+    let mut duplicates = 0;
+    for entry in entries {
+        let count = counts.remove(entry.url.as_str()).unwrap_or_default();
+        if count > 1 {
+            duplicates += 1;
+            println!("{}\n  in the list {} times", entry.url, count);
+        }
+    }
+
+    println!(
+        "{} tracks, {} broken, {} duplicated",
+        entries.len(),
+        broken,
+        duplicates
+    );
+
+    if let Some(output) = output {
+        let text: String = working
+            .into_iter()
+            .map(|entry| match &entry.name {
+                Some(name) => format!("{}\t{}\n", entry.url, name),
+                None => format!("{}\n", entry.url),
+            })
+            .collect();
+
+        tokio::fs::write(&output, text)
+            .await
+            .map_err(|error| eyre::eyre!("unable to write {}: {}", output.display(), error))?;
+        println!("wrote the working tracks to {}", output.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,949 @@
+//! Contains the [Config] struct, which is read from lowfi's config file.
+
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, time::Duration};
+
+use crossterm::style::Color;
+use eyre::{bail, eyre, Context};
+use serde::Deserialize;
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    player::{
+        keymap::Binding,
+        pomodoro::{Break, Intervals},
+    },
+    tracks::{download::Bandwidth, template::Template},
+};
+
+/// The smallest width that the UI can be, since anything smaller
+/// won't fit all of the controls.
+pub const MIN_WIDTH: usize = 43;
+
+/// The keys that trigger each action in the UI.
+///
+/// Each of these can either be a single key, or a list of keys, see [Key](crate::player::keymap::Key).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybinds {
+    /// Skips to the next track.
+    pub skip: Binding,
+
+    /// Pauses & unpauses playback.
+    pub pause: Binding,
+
+    /// Quits lowfi.
+    pub quit: Binding,
+
+    /// Saves the current track to the favorites.
+    pub favorite: Binding,
+
+    /// Raises the volume by 10%.
+    pub volume_up: Binding,
+
+    /// Lowers the volume by 10%.
+    pub volume_down: Binding,
+
+    /// Raises the volume by 1%.
+    pub volume_up_fine: Binding,
+
+    /// Lowers the volume by 1%.
+    pub volume_down_fine: Binding,
+
+    /// Seeks forwards within the current track.
+    pub seek_forward: Binding,
+
+    /// Seeks backwards within the current track.
+    pub seek_backward: Binding,
+
+    /// Cycles the sleep timer between 15, 30 & 60 minutes, and then off.
+    pub timer: Binding,
+
+    /// Goes back to the previously played track.
+    pub previous: Binding,
+
+    /// Likes the current track, or unlikes it if it's already liked.
+    pub like: Binding,
+
+    /// Blocks the current track so that it's never played again, and skips it.
+    pub block: Binding,
+
+    /// Turns repeating the current track on & off.
+    pub repeat: Binding,
+
+    /// Moves playback over to the next audio device.
+    pub device: Binding,
+
+    /// Opens & closes the history pane.
+    pub history: Binding,
+
+    /// Opens & closes the station picker.
+    pub stations: Binding,
+
+    /// Opens & closes the visualizer.
+    pub visualizer: Binding,
+
+    /// Opens & closes the queue of upcoming tracks.
+    pub queue: Binding,
+
+    /// Speeds up playback.
+    pub speed_up: Binding,
+
+    /// Slows down playback.
+    pub speed_down: Binding,
+
+    /// Boosts the bass.
+    pub bass_up: Binding,
+
+    /// Cuts the bass.
+    pub bass_down: Binding,
+
+    /// Boosts the treble.
+    pub treble_up: Binding,
+
+    /// Cuts the treble.
+    pub treble_down: Binding,
+
+    /// Opens an input for typing in an exact volume.
+    pub set_volume: Binding,
+
+    /// Mutes & unmutes playback.
+    pub mute: Binding,
+
+    /// Copies the URL of the current track, which needs the `clipboard` feature.
+    pub copy: Binding,
+
+    /// Opens the URL of the current track in the browser.
+    pub open: Binding,
+
+    /// Saves the current track to the downloads directory.
+    pub download: Binding,
+
+    /// Cycles the volume of the selected ambient sound.
+    pub ambience: Binding,
+
+    /// Selects the next ambient sound, for `ambience` to change the volume of.
+    pub ambience_select: Binding,
+
+    /// Shows every keybind & a summary of the config.
+    pub help: Binding,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self {
+            skip: Binding::of(&["s"]),
+            pause: Binding::of(&["p"]),
+            quit: Binding::of(&["q"]),
+            favorite: Binding::of(&["f"]),
+            volume_up: Binding::of(&["+", "=", "up"]),
+            volume_down: Binding::of(&["-", "_", "down"]),
+            volume_up_fine: Binding::of(&[">", "."]),
+            volume_down_fine: Binding::of(&["<", ","]),
+            seek_forward: Binding::of(&["]", "right"]),
+            seek_backward: Binding::of(&["[", "left"]),
+            timer: Binding::of(&["t"]),
+            previous: Binding::of(&["b"]),
+            like: Binding::of(&["l"]),
+            block: Binding::of(&["x"]),
+            repeat: Binding::of(&["r"]),
+            device: Binding::of(&["d"]),
+            history: Binding::of(&["h"]),
+            stations: Binding::of(&["o"]),
+            visualizer: Binding::of(&["v"]),
+            queue: Binding::of(&["n"]),
+            speed_up: Binding::of(&["}"]),
+            speed_down: Binding::of(&["{"]),
+            bass_up: Binding::of(&["u"]),
+            bass_down: Binding::of(&["j"]),
+            treble_up: Binding::of(&["i"]),
+            treble_down: Binding::of(&["k"]),
+            set_volume: Binding::of(&["V"]),
+            mute: Binding::of(&["m"]),
+            copy: Binding::of(&["y"]),
+            open: Binding::of(&["w"]),
+            download: Binding::of(&["D"]),
+            ambience: Binding::of(&["R"]),
+            ambience_select: Binding::of(&["A"]),
+            help: Binding::of(&["?"]),
+        }
+    }
+}
+
+/// Credentials for the scrobbling services.
+///
+/// Any of these can also be set with environment variables, which take priority.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scrobble {
+    /// The ListenBrainz user token.
+    pub listenbrainz_token: Option<String>,
+
+    /// The Last.fm API key.
+    pub lastfm_api_key: Option<String>,
+
+    /// The Last.fm shared secret.
+    pub lastfm_secret: Option<String>,
+
+    /// The Last.fm session key.
+    pub lastfm_session_key: Option<String>,
+}
+
+/// Settings for the on-disk track cache.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cache {
+    /// Where to store cached tracks, which is usually `~/.cache/lowfi`.
+    pub dir: Option<PathBuf>,
+
+    /// The maximum size of the cache in megabytes, where `0` disables it.
+    pub size: u64,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            size: 256,
+        }
+    }
+}
+
+/// Settings for downloading tracks.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+    /// How many seconds a download can go without receiving anything before it's given up on.
+    pub timeout: u64,
+
+    /// How many times to try downloading a track before skipping it for another one.
+    pub attempts: u32,
+
+    /// The fastest that tracks can be downloaded, like `256k`, which is unlimited by default.
+    pub max_bandwidth: Option<Bandwidth>,
+
+    /// The proxy to send every request through, like `http://host:port`.
+    pub proxy: Option<String>,
+
+    /// How many seconds of a stream to buffer before playing it, which trades
+    /// a slower start for less stuttering on slow connections.
+    pub buffer_size: f32,
+
+    /// How many tracks to keep downloaded ahead of time, including the ones that are still downloading.
+    pub prefetch: usize,
+
+    /// How many tracks can be downloading at once.
+    pub concurrency: usize,
+
+    /// How many seconds a track can take to start playing before another one is played instead,
+    /// where `0` waits for as long as it takes.
+    pub skip_timeout: u64,
+
+    /// A PEM file with extra certificate authorities to trust, for networks
+    /// which intercept HTTPS traffic.
+    pub ca_certificates: Option<PathBuf>,
+
+    /// The User-Agent to send instead of lowfi's own, for servers which block it.
+    pub user_agent: Option<String>,
+
+    /// Extra headers to send with every request, like `Referer`.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            timeout: 8,
+            attempts: 3,
+            max_bandwidth: None,
+            proxy: None,
+            buffer_size: 2.0,
+            prefetch: 5,
+            concurrency: 2,
+            skip_timeout: 10,
+            ca_certificates: None,
+            user_agent: None,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+/// Settings for how tracks are shuffled, see [crate::tracks::selector].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Shuffle {
+    /// How many of the most recently played tracks to avoid, where `0` allows anything.
+    pub no_repeat: usize,
+
+    /// How much more likely favorite tracks are to be played,
+    /// where `2.0` is twice as likely & `1.0` is the same as any other track.
+    pub favorite_weight: f32,
+
+    /// How much more likely liked tracks are to be played, just like `favorite_weight`.
+    pub like_weight: f32,
+}
+
+impl Default for Shuffle {
+    fn default() -> Self {
+        Self {
+            no_repeat: 20,
+            favorite_weight: 1.0,
+            like_weight: 2.0,
+        }
+    }
+}
+
+/// Settings for the equalizer, see [crate::audio::filters].
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Equalizer {
+    /// How much to boost or cut the bass, in decibels from -12 to 12.
+    pub bass: i8,
+
+    /// How much to boost or cut the treble, in decibels from -12 to 12.
+    pub treble: i8,
+
+    /// Whether to save the equalizer when lowfi quits, and restore it next time.
+    ///
+    /// If this is enabled, `bass` & `treble` are only used
+    /// until lowfi has saved an equalizer for the first time.
+    pub persist: bool,
+}
+
+/// Settings for scrolling names that are too long for the UI back & forth, instead of cutting them off.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Marquee {
+    /// Whether long names scroll at all. `wrap_names` takes priority over this.
+    pub enabled: bool,
+
+    /// How many columns the name moves by every second.
+    pub speed: f32,
+
+    /// How many seconds the name stays still once it gets to either end.
+    pub pause: f32,
+}
+
+impl Default for Marquee {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 4.0,
+            pause: 2.0,
+        }
+    }
+}
+
+/// Settings for saving power while a laptop is running on its battery, see [crate::player::power].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Power {
+    /// Whether to check the battery at all.
+    pub enabled: bool,
+
+    /// The charge as a percentage, below which lowfi starts saving power.
+    pub threshold: u8,
+
+    /// How many frames the visualizer draws per second while saving power,
+    /// if it's lower than the usual `fps`.
+    pub fps: f32,
+
+    /// Whether the visualizer keeps going while saving power, rather than being paused.
+    pub visualizer: bool,
+
+    /// How many tracks can be downloading at once while saving power,
+    /// if it's lower than the usual `concurrency`.
+    pub concurrency: usize,
+}
+
+impl Default for Power {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 20,
+            fps: 4.0,
+            visualizer: false,
+            concurrency: 1,
+        }
+    }
+}
+
+/// A source of tracks, which is one of the `[[sources]]` in the config, see [crate::tracks::source].
+///
+/// Exactly one of `station`, `list`, `dir`, `archive`, `subsonic`,
+/// `webdav`, `s3`, `youtube` or `stream` has to be set.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Source {
+    /// The name of a station.
+    pub station: Option<String>,
+
+    /// The path to a track list or a playlist, or the URL of a track list.
+    pub list: Option<String>,
+
+    /// A directory of audio files.
+    pub dir: Option<PathBuf>,
+
+    /// The identifier of a collection on archive.org, see [crate::tracks::source::archive].
+    #[cfg(feature = "sources")]
+    pub archive: Option<String>,
+
+    /// The URL of a playlist on SoundCloud, see [crate::tracks::source::soundcloud].
+    #[cfg(feature = "soundcloud")]
+    pub soundcloud: Option<String>,
+
+    /// The client ID to use SoundCloud's API with.
+    #[cfg(feature = "soundcloud")]
+    pub client_id: Option<String>,
+
+    /// The URL of a Subsonic server, see [crate::tracks::source::subsonic].
+    #[cfg(feature = "sources")]
+    pub subsonic: Option<String>,
+
+    /// The name or ID of the playlist to play from the Subsonic server, instead of random songs.
+    #[cfg(feature = "sources")]
+    pub playlist: Option<String>,
+
+    /// The URL of a directory on a WebDAV share, see [crate::tracks::source::webdav].
+    #[cfg(feature = "sources")]
+    pub webdav: Option<String>,
+
+    /// The user to log in to the Subsonic server or the WebDAV share as.
+    #[cfg(feature = "sources")]
+    pub user: Option<String>,
+
+    /// The password of the user.
+    #[cfg(feature = "sources")]
+    pub password: Option<String>,
+
+    /// The URL of an S3 bucket, along with an optional prefix, see [crate::tracks::source::s3].
+    #[cfg(feature = "sources")]
+    pub s3: Option<String>,
+
+    /// The region of the S3 bucket, which is otherwise worked out from the URL.
+    #[cfg(feature = "sources")]
+    pub region: Option<String>,
+
+    /// The ID of the access key for the S3 bucket.
+    #[cfg(feature = "sources")]
+    pub access_key: Option<String>,
+
+    /// The secret access key for the S3 bucket.
+    #[cfg(feature = "sources")]
+    pub secret_key: Option<String>,
+
+    /// The URL of a YouTube playlist or channel, see [crate::tracks::source::youtube].
+    #[cfg(feature = "sources")]
+    pub youtube: Option<String>,
+
+    /// The URL of a single stream.
+    pub stream: Option<String>,
+
+    /// The name to show for a stream, instead of formatting it from the URL.
+    pub name: Option<String>,
+
+    /// How often tracks come from this source compared to the others, where `1.0` is normal.
+    pub weight: f32,
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self {
+            station: None,
+            list: None,
+            dir: None,
+            #[cfg(feature = "sources")]
+            archive: None,
+            #[cfg(feature = "soundcloud")]
+            soundcloud: None,
+            #[cfg(feature = "soundcloud")]
+            client_id: None,
+            #[cfg(feature = "sources")]
+            subsonic: None,
+            #[cfg(feature = "sources")]
+            playlist: None,
+            #[cfg(feature = "sources")]
+            webdav: None,
+            #[cfg(feature = "sources")]
+            user: None,
+            #[cfg(feature = "sources")]
+            password: None,
+            #[cfg(feature = "sources")]
+            s3: None,
+            #[cfg(feature = "sources")]
+            region: None,
+            #[cfg(feature = "sources")]
+            access_key: None,
+            #[cfg(feature = "sources")]
+            secret_key: None,
+            #[cfg(feature = "sources")]
+            youtube: None,
+            stream: None,
+            name: None,
+            weight: 1.0,
+        }
+    }
+}
+
+/// Settings for the ambient sounds, see [crate::player::ambience].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ambience {
+    /// The track list of the sounds, which are all looped at once.
+    pub tracks: Option<PathBuf>,
+
+    /// The volume of the sounds as a percentage, which is separate from the music's.
+    pub volume: u8,
+
+    /// The volumes of particular sounds by name, like `rain = 30`, which are used instead of `volume`.
+    pub volumes: BTreeMap<String, u8>,
+}
+
+impl Default for Ambience {
+    fn default() -> Self {
+        Self {
+            tracks: None,
+            volume: 50,
+            volumes: BTreeMap::new(),
+        }
+    }
+}
+
+/// Settings for turning the music down while something else is playing, which needs the `duck` feature.
+#[cfg(feature = "duck")]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Duck {
+    /// Whether to duck at all.
+    pub enabled: bool,
+
+    /// How loud the music is while ducked, where `1.0` is the usual volume.
+    pub volume: f32,
+
+    /// The `media.role`s of the streams that the music is ducked for, like `event` for
+    /// notification sounds, where an empty list means any other application.
+    pub roles: Vec<String>,
+}
+
+#[cfg(feature = "duck")]
+impl Default for Duck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.3,
+            roles: vec!["event".to_owned(), "phone".to_owned()],
+        }
+    }
+}
+
+/// Which audio system lowfi plays through.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Whatever the system normally uses, like ALSA, CoreAudio or WASAPI.
+    Default,
+
+    /// JACK, or PipeWire through pipewire-jack, where lowfi shows up as its own client.
+    #[cfg(feature = "jack")]
+    Jack,
+}
+
+/// The different frontends that the UI can be drawn with.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Frontend {
+    /// The usual box with the track, the progress bar & the controls.
+    Boxed,
+
+    /// A single line, for tmux panes & narrow terminals.
+    Minimal,
+
+    /// A line of JSON on stdout every time something changes, for wrappers & status bars.
+    Json,
+}
+
+/// Which characters the borders of the UI are drawn with.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Borders {
+    /// Box drawing characters with square corners, like `┌─┐`.
+    Square,
+
+    /// Box drawing characters with rounded corners, like `╭─╮`.
+    Rounded,
+
+    /// Plain ASCII, like `+-+`, for terminals & fonts without box drawing characters.
+    Ascii,
+}
+
+/// How the progress bar is drawn.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStyle {
+    /// The two `progress` characters, which are `/` & a space by default.
+    Characters,
+
+    /// Solid blocks, which fill up an eighth of a column at a time.
+    Blocks,
+
+    /// Braille dots, which fill up a column in eight steps.
+    Braille,
+
+    /// Solid blocks, which fade out with shaded ones at the end, like `██▓░░`.
+    Gradient,
+
+    /// Just the percentage that's been played, without a bar.
+    Percentage,
+}
+
+/// Settings for how the UI looks.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// The characters that the borders are drawn with.
+    pub borders: Borders,
+
+    /// The two characters that the progress bar is made of, the filled part & then the empty part.
+    /// These are only used with the `characters` [ProgressStyle].
+    pub progress: String,
+
+    /// How the progress bar is drawn.
+    pub progress_style: ProgressStyle,
+
+    /// Whether to use colors at all. Setting the `NO_COLOR` environment variable also turns them off.
+    pub color: bool,
+
+    /// The color of the borders.
+    pub border_color: Option<Color>,
+
+    /// The color of the keys in the controls & the filled part of the progress bar.
+    pub accent: Option<Color>,
+
+    /// The color of notices about something going wrong.
+    pub error: Option<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            borders: Borders::Square,
+            progress: "/ ".to_owned(),
+            progress_style: ProgressStyle::Characters,
+            color: true,
+            border_color: None,
+            accent: None,
+            error: Some(Color::Red),
+        }
+    }
+}
+
+/// lowfi's configuration, which is read from `config.toml` in the
+/// config directory, usually `~/.config/lowfi/config.toml`.
+///
+/// Every field is optional, and CLI flags take priority over the file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The volume that lowfi starts at, as a percentage.
+    ///
+    /// If `persist_volume` is enabled, this is only used
+    /// if no volume has been saved yet.
+    pub volume: u8,
+
+    /// Whether to save the volume when it changes, and restore it next time.
+    pub persist_volume: bool,
+
+    /// The inner width of the UI, not including the borders.
+    ///
+    /// If this isn't set, then the UI fits itself to the terminal.
+    pub width: Option<usize>,
+
+    /// Whether to use an alternate terminal screen.
+    pub alternate: bool,
+
+    /// How many frames the visualizer draws per second, which drops to 1 while paused or
+    /// while the terminal isn't focused. The rest of the UI is only drawn when something
+    /// changes, and once a second for the progress bar.
+    pub fps: f32,
+
+    /// Whether to draw the album art beside the UI, in terminals that support it.
+    pub artwork: bool,
+
+    /// Which frontend the UI is drawn with.
+    pub frontend: Frontend,
+
+    /// How the name of the track is shown, like `{title}[ — {artist}][ ({source})]`, see [Template].
+    pub format: Template,
+
+    /// Whether to show a short "tuning" animation in the boxed frontend when the track changes.
+    pub transitions: bool,
+
+    /// Whether names that are too long for the box go onto a second line,
+    /// instead of being cut off. This only applies to the boxed frontend.
+    pub wrap_names: bool,
+
+    /// Whether the UI can be clicked on. This stops the terminal
+    /// from selecting text normally while lowfi is running.
+    pub mouse: bool,
+
+    /// The keys that trigger each action.
+    pub keybinds: Keybinds,
+
+    /// Credentials for scrobbling services.
+    pub scrobble: Scrobble,
+
+    /// Settings for the track cache.
+    pub cache: Cache,
+
+    /// Settings for downloading tracks.
+    pub network: Network,
+
+    /// Settings for the equalizer.
+    pub equalizer: Equalizer,
+
+    /// Settings for the ambient sounds.
+    pub ambience: Ambience,
+
+    /// Settings for how tracks are shuffled.
+    pub shuffle: Shuffle,
+
+    /// Settings for turning the music down while something else is playing.
+    #[cfg(feature = "duck")]
+    pub duck: Duck,
+
+    /// Settings for how the UI looks.
+    pub theme: Theme,
+
+    /// Settings for scrolling long names.
+    pub marquee: Marquee,
+
+    /// Settings for saving power on a battery.
+    pub power: Power,
+
+    /// The station that lowfi starts on, see [crate::tracks::station].
+    /// This is ignored if a custom track list is used, or if there are any `sources`.
+    pub station: Option<String>,
+
+    /// Where tracks come from, which are mixed together by their weights.
+    /// This is used instead of `station` unless it's empty, and is ignored if a custom track list is used.
+    pub sources: Vec<Source>,
+
+    /// The name of the audio device to play on, instead of the default one.
+    /// See `lowfi devices` for the names.
+    pub device: Option<String>,
+
+    /// The audio system to play through, which decides what the devices are.
+    pub backend: Backend,
+
+    /// Whether to pause when the audio device disappears, like when headphones are unplugged,
+    /// instead of carrying on through the default device.
+    pub pause_on_disconnect: bool,
+
+    /// A shell command that's ran whenever a new track starts.
+    pub on_track_change: Option<String>,
+
+    /// A file that's kept up to date with what's playing.
+    pub now_playing_file: Option<PathBuf>,
+
+    /// A named pipe that everything which is played gets copied into, for visualizers like cava.
+    /// See [crate::audio::fifo] for the format.
+    #[cfg(unix)]
+    pub audio_fifo: Option<PathBuf>,
+
+    /// The address to serve the web remote on, like `127.0.0.1:8080`.
+    pub web: Option<SocketAddr>,
+
+    /// Whether the web remote also serves metrics for Prometheus at `/metrics`.
+    pub metrics: bool,
+
+    /// The address to serve the MPD protocol on, like `127.0.0.1:6600`.
+    pub mpd: Option<SocketAddr>,
+
+    /// Where tracks are saved to when they're downloaded, which is `~/Music/lowfi` by default.
+    pub download_dir: Option<PathBuf>,
+
+    /// Whether to keep the pitch the same when the playback speed changes,
+    /// which takes a bit more processing.
+    pub pitch_correction: bool,
+
+    /// Whether to even out the loudness of tracks, see [crate::audio::normalize].
+    pub normalize: bool,
+
+    /// How many milliseconds it takes to fade in & out when pausing, resuming & skipping.
+    /// `0` turns fading off.
+    pub fade: u64,
+
+    /// Whether to save the history of played tracks, so that it's kept between sessions.
+    pub save_history: bool,
+
+    /// Whether the sleep timer pauses playback when it runs out, instead of quitting.
+    pub pause_on_timer: bool,
+
+    /// What happens to the music during pomodoro breaks, see [crate::player::pomodoro].
+    pub pomodoro_break: Break,
+
+    /// Whether to start playing again after the system wakes up, if lowfi paused it for a suspend.
+    #[cfg(feature = "suspend")]
+    pub resume_after_suspend: bool,
+
+    /// The ID of the Discord application that Rich Presence is shown under.
+    #[cfg(feature = "discord")]
+    pub discord_client_id: Option<String>,
+
+    /// Whether to show what's playing on Discord.
+    /// This can only be set with the `--discord` flag.
+    #[cfg(feature = "discord")]
+    #[serde(skip)]
+    pub discord: bool,
+
+    /// How long the sleep timer should initially be set to.
+    /// This can only be set with the `--timer` flag.
+    #[serde(skip)]
+    pub timer: Option<Duration>,
+
+    /// How long the pomodoro focus & break intervals are, if it's on.
+    /// This can only be set with the `--pomodoro` flag.
+    #[serde(skip)]
+    pub pomodoro: Option<Intervals>,
+
+    /// How long the volume takes to go up from nothing to `volume` once the first track starts.
+    /// This can only be set by `lowfi alarm`.
+    #[serde(skip)]
+    pub ramp: Option<Duration>,
+
+    /// Where to record everything that's played to, see [crate::audio::record].
+    /// This can only be set with the `--record` flag.
+    #[serde(skip)]
+    pub record: Option<PathBuf>,
+
+    /// Whether to only play tracks that are in the cache.
+    /// This can only be set with the `--offline` flag.
+    #[serde(skip)]
+    pub offline: bool,
+
+    /// Whether to start with the track that was playing when lowfi last quit, see [crate::storage::load_resume].
+    /// This can only be set with the `--resume` flag.
+    #[serde(skip)]
+    pub resume: bool,
+
+    /// Whether to run in the background without a UI, like with `--daemon`.
+    /// This can only be set with the `--daemon` flag.
+    #[cfg(unix)]
+    #[serde(skip)]
+    pub daemon: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            persist_volume: true,
+            width: None,
+            alternate: false,
+            fps: 12.0,
+            artwork: false,
+            frontend: Frontend::Boxed,
+            format: Template::default(),
+            transitions: true,
+            wrap_names: false,
+            mouse: true,
+            keybinds: Keybinds::default(),
+            scrobble: Scrobble::default(),
+            cache: Cache::default(),
+            network: Network::default(),
+            equalizer: Equalizer::default(),
+            ambience: Ambience::default(),
+            shuffle: Shuffle::default(),
+            #[cfg(feature = "duck")]
+            duck: Duck::default(),
+            theme: Theme::default(),
+            marquee: Marquee::default(),
+            power: Power::default(),
+            station: None,
+            sources: Vec::new(),
+            device: None,
+            backend: Backend::Default,
+            pause_on_disconnect: true,
+            on_track_change: None,
+            now_playing_file: None,
+            #[cfg(unix)]
+            audio_fifo: None,
+            web: None,
+            metrics: false,
+            mpd: None,
+            download_dir: None,
+            pitch_correction: true,
+            normalize: false,
+            fade: 300,
+            pause_on_timer: false,
+            pomodoro_break: Break::default(),
+            save_history: false,
+            #[cfg(feature = "suspend")]
+            resume_after_suspend: false,
+            #[cfg(feature = "discord")]
+            discord_client_id: None,
+            #[cfg(feature = "discord")]
+            discord: false,
+            timer: None,
+            pomodoro: None,
+            ramp: None,
+            record: None,
+            offline: false,
+            resume: false,
+            #[cfg(unix)]
+            daemon: false,
+        }
+    }
+}
+
+impl Config {
+    /// Gets the path to the config file, which might not exist.
+    pub fn path() -> eyre::Result<PathBuf> {
+        Ok(dirs::config_dir()
+            .ok_or_else(|| eyre!("unable to find the config directory"))?
+            .join("lowfi")
+            .join("config.toml"))
+    }
+
+    /// Reads the config file, falling back to the defaults if it doesn't exist.
+    pub fn load() -> eyre::Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(&path)?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("invalid config in {}", path.display()))?;
+
+        config.volume = config.volume.min(100);
+        config.width = config.width.map(|x| x.max(MIN_WIDTH));
+        if !config.fps.is_normal() || config.fps < 0.0 {
+            config.fps = Self::default().fps;
+        }
+        if !config.marquee.speed.is_normal() || config.marquee.speed < 0.0 {
+            config.marquee.speed = Marquee::default().speed;
+        }
+        if !config.marquee.pause.is_finite() || config.marquee.pause < 0.0 {
+            config.marquee.pause = Marquee::default().pause;
+        }
+        if !config.power.fps.is_normal() || config.power.fps < 0.0 {
+            config.power.fps = Power::default().fps;
+        }
+        config.power.threshold = config.power.threshold.min(100);
+        if !config.network.buffer_size.is_finite() || config.network.buffer_size < 0.0 {
+            config.network.buffer_size = Network::default().buffer_size;
+        }
+
+        // Anything wider than a single column would throw off the progress bar.
+        let glyphs: Vec<char> = config.theme.progress.chars().collect();
+        if glyphs.len() != 2 || glyphs.iter().any(|x| x.width() != Some(1)) {
+            bail!(
+                "invalid progress characters '{}' in {}, there should be two like \"/ \"",
+                config.theme.progress,
+                path.display()
+            );
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,144 @@
+//! A simple control interface over a unix socket, so that lowfi can be scripted.
+//!
+//! Every line that is sent to the socket is a command, and every command gets
+//! a single line of JSON back, which is either the status of the player or an error.
+//! The commands are the same as every other remote, see [crate::remote].
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use eyre::eyre;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::mpsc::Sender,
+    task,
+};
+use tracing::{debug, info};
+
+use lowfi::{
+    format_duration,
+    player::{Messages, Player},
+};
+
+use crate::remote::{parse, status};
+
+/// Gets the path of the socket, which is `$XDG_RUNTIME_DIR/lowfi.sock`,
+/// falling back to the temporary directory if there isn't a runtime directory.
+pub fn socket() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lowfi.sock")
+}
+
+/// Handles a single connection until it's closed.
+async fn handle(
+    stream: UnixStream,
+    player: Arc<Player>,
+    sender: Sender<Messages>,
+) -> eyre::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = match parse(&line) {
+            Ok(message) => {
+                if let Some(message) = message {
+                    sender.send(message).await?;
+                }
+
+                status(&player)
+            }
+            Err(error) => json!({ "error": error }),
+        };
+
+        writer
+            .write_all(format!("{}\n", response).as_bytes())
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Starts listening on the socket in the background.
+///
+/// If another instance of lowfi is already listening, then this does nothing
+/// and returns `false`, otherwise it returns `true`.
+pub async fn start(player: Arc<Player>, sender: Sender<Messages>) -> eyre::Result<bool> {
+    let path = socket();
+
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            return Ok(false);
+        }
+
+        // Nobody is listening, so it's just left over from a session which didn't exit cleanly.
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    info!("listening on {}", path.display());
+
+    task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let player = Arc::clone(&player);
+            let sender = sender.clone();
+
+            task::spawn(async move {
+                if let Err(error) = handle(stream, player, sender).await {
+                    debug!("control connection failed: {:#}", error);
+                }
+            });
+        }
+    });
+
+    Ok(true)
+}
+
+/// Removes the socket, which should be done when lowfi exits
+/// if [start] returned `true`.
+pub fn stop() {
+    let _ = std::fs::remove_file(socket());
+}
+
+/// Queries the status of an already running instance of lowfi, and prints it.
+///
+/// If `json` is true, then the raw JSON is printed, otherwise it's
+/// formatted as a single line which is suitable for status bars.
+pub async fn query(json: bool) -> eyre::Result<()> {
+    let stream = UnixStream::connect(socket())
+        .await
+        .map_err(|_| eyre!("lowfi isn't running"))?;
+
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(b"status\n").await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| eyre!("lowfi closed the connection"))?;
+
+    if json {
+        println!("{}", line);
+        return Ok(());
+    }
+
+    let status: Value = serde_json::from_str(&line)?;
+    let seconds = |key: &str| Duration::from_secs(status[key].as_u64().unwrap_or(0));
+
+    let state = status["state"].as_str().unwrap_or("unknown");
+    match status["track"].as_str() {
+        Some(track) => println!(
+            "{} {} {}/{} {}%",
+            state,
+            track,
+            format_duration(&seconds("elapsed")),
+            format_duration(&seconds("duration")),
+            status["volume"]
+        ),
+        None => println!("{} {}%", state, status["volume"]),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+//! Runs lowfi in the background without a UI, which is done with `--daemon`.
+//!
+//! A daemon is controlled entirely through the [crate::control] socket & MPRIS,
+//! and always logs what it's doing to a file, since there's no terminal to show it in.
+
+use crate::shutdown;
+
+/// Runs in place of the UI, until lowfi is told to stop with a signal, see [shutdown::signal].
+pub async fn run() -> eyre::Result<()> {
+    shutdown::signal().await
+}
@@ -0,0 +1,108 @@
+//! Turns the music down while another application is playing something, like a
+//! notification sound or a call, which needs the `duck` feature.
+//!
+//! This watches the streams of the sound server with `pactl`, which works with both
+//! PulseAudio & PipeWire (through pipewire-pulse), so it only works on Linux.
+//! The music is ducked through the [Fader](lowfi::audio::fade::Fader), so the volume
+//! that's shown & saved never changes.
+
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use eyre::{bail, eyre};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    task,
+    time::timeout,
+};
+use tracing::{debug, info, warn};
+
+use lowfi::{config::Duck, player::Player};
+
+/// How long to wait for more events after one comes in, since they come in bursts.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Whether any stream from another application, with one of `roles`, is playing right now.
+async fn playing(roles: &[String]) -> eyre::Result<bool> {
+    let output = Command::new("pactl")
+        .args(["--format", "json", "list", "sink-inputs"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("pactl failed with {}", output.status);
+    }
+
+    let streams: Vec<Value> = serde_json::from_slice(&output.stdout)?;
+    let pid = std::process::id().to_string();
+
+    Ok(streams.iter().any(|stream| {
+        let properties = &stream["properties"];
+        let role = properties["media.role"].as_str().unwrap_or_default();
+
+        properties["application.process.id"].as_str() != Some(pid.as_str())
+            && stream["corked"] != true
+            && stream["mute"] != true
+            && (roles.is_empty() || roles.iter().any(|x| x == role))
+    }))
+}
+
+/// Ducks or unducks the music, depending on whether anything else is playing.
+async fn update(player: &Player, config: &Duck) {
+    match playing(&config.roles).await {
+        Ok(true) if !player.fader.ducked() => {
+            info!("ducking for another application");
+            player.fader.duck(config.volume);
+        }
+        Ok(false) if player.fader.ducked() => {
+            info!("done ducking");
+            player.fader.duck(1.0);
+        }
+        Ok(_) => {}
+        Err(error) => debug!("unable to list the streams: {:#}", error),
+    }
+}
+
+/// Starts watching for other applications playing audio in the background.
+pub async fn start(player: Arc<Player>, config: Duck) -> eyre::Result<()> {
+    // Checking once first means that a missing or outdated pactl is caught straight away.
+    playing(&config.roles)
+        .await
+        .map_err(|error| eyre!("unable to list the streams with pactl: {:#}", error))?;
+
+    let mut child = Command::new("pactl")
+        .arg("subscribe")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| eyre!("pactl doesn't have any output"))?;
+
+    task::spawn(async move {
+        // The child is moved in here so that it's only killed once this stops.
+        let _child = child;
+        let mut lines = BufReader::new(stdout).lines();
+
+        update(&player, &config).await;
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !line.contains("sink-input") {
+                continue;
+            }
+
+            // Only the last of a burst of events matters.
+            while let Ok(Ok(Some(_))) = timeout(DEBOUNCE, lines.next_line()).await {}
+            update(&player, &config).await;
+        }
+
+        warn!("stopped watching for other applications");
+        player.fader.duck(1.0);
+    });
+
+    Ok(())
+}
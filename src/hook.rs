@@ -0,0 +1,103 @@
+//! Runs a user's command whenever a new track starts, which is set with `on_track_change`.
+//!
+//! The command is ran through the shell, with the track passed in as environment variables:
+//! `LOWFI_TRACK_NAME`, `LOWFI_TRACK_URL`, `LOWFI_TRACK_DURATION` (in seconds), and
+//! `LOWFI_TRACK_ARTIST` & `LOWFI_TRACK_ALBUM` if the track's tags have them.
+
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use tokio::{process::Command, task, time::sleep};
+use tracing::{debug, warn};
+
+use lowfi::{player::Player, tracks::TrackInfo};
+
+/// How often to check whether the track has changed.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Builds the command that runs `hook` through the shell.
+fn shell(hook: &str) -> Command {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(hook);
+        command
+    };
+
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(hook);
+        command
+    };
+
+    // Anything the hook prints would just mess up the UI.
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    command
+}
+
+/// Runs the hook for a single track, and waits for it to finish.
+async fn run(hook: &str, track: &TrackInfo) -> eyre::Result<()> {
+    let mut command = shell(hook);
+    command
+        .env("LOWFI_TRACK_NAME", &track.name)
+        .env("LOWFI_TRACK_URL", &track.url)
+        .env(
+            "LOWFI_TRACK_DURATION",
+            track
+                .duration
+                .map_or_else(String::new, |x| x.as_secs().to_string()),
+        );
+
+    if let Some(artist) = &track.artist {
+        command.env("LOWFI_TRACK_ARTIST", artist);
+    }
+
+    if let Some(album) = &track.album {
+        command.env("LOWFI_TRACK_ALBUM", album);
+    }
+
+    let status = command.spawn()?.wait().await?;
+    if !status.success() {
+        eyre::bail!("exited with {}", status);
+    }
+
+    Ok(())
+}
+
+/// Starts watching for new tracks in the background, which will keep going until lowfi exits.
+///
+/// Every run of the hook gets its own task, so a slow hook never holds up playback,
+/// or even the hook for the next track.
+pub fn start(player: Arc<Player>, hook: String) {
+    let hook: Arc<str> = hook.into();
+
+    task::spawn(async move {
+        let mut last: Option<Arc<TrackInfo>> = None;
+
+        loop {
+            let current = player.current.load_full();
+
+            if let Some(current) = &current {
+                if !last.as_ref().is_some_and(|x| Arc::ptr_eq(x, current)) {
+                    let hook = Arc::clone(&hook);
+                    let track = Arc::clone(current);
+
+                    task::spawn(async move {
+                        debug!("running the track change hook for {}", track.name);
+                        if let Err(error) = run(&hook, &track).await {
+                            warn!("the track change hook failed: {:#}", error);
+                        }
+                    });
+                }
+
+                last = Some(Arc::clone(current));
+            }
+
+            sleep(INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,66 @@
+//! The audio engine behind lowfi, which can also be used without lowfi's UI.
+//!
+//! Everything revolves around the [Player], which downloads, decodes & plays
+//! tracks from a [List]. It's driven entirely through [Messages], which are
+//! sent to [Player::play], and it describes itself through [PlayerState]s,
+//! which are published whenever anything changes.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use lowfi::{
+//!     config::Config,
+//!     tracks::{download::Network, station},
+//!     Messages, Player,
+//! };
+//! use tokio::sync::mpsc;
+//!
+//! # async fn example() -> eyre::Result<()> {
+//! let config = Config::load()?;
+//! let list = station::load(station::DEFAULT)?;
+//! let network = Network::new(&config.network)?;
+//!
+//! let station = Some(station::DEFAULT.to_owned());
+//! let player = Arc::new(Player::new(list, station, network, &config).await?);
+//! let (tx, rx) = mpsc::channel(8);
+//! tokio::spawn(Player::play(Arc::clone(&player), tx.clone(), rx));
+//!
+//! // Nothing plays until the first track is asked for.
+//! tx.send(Messages::Init).await?;
+//!
+//! let mut state = player.state.subscribe();
+//! while state.changed().await.is_ok() {
+//!     if let Some(track) = &state.borrow().current {
+//!         println!("now playing {}", track.display_name());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `lowfi` binary is just this, plus a terminal UI, a control socket &
+//! a handful of integrations which all work off of the same [Player].
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("lowfi needs either the `native-tls` or the `rustls` feature for HTTPS");
+
+use std::time::Duration;
+
+pub mod audio;
+pub mod config;
+pub mod player;
+pub mod stats;
+pub mod storage;
+pub mod tracks;
+
+pub use config::Config;
+pub use player::{Event, Messages, Player, PlayerState, UiMessage};
+pub use tracks::{list::List, TrackInfo};
+
+/// Small helper function to format durations.
+pub fn format_duration(duration: &Duration) -> String {
+    let seconds = duration.as_secs() % 60;
+    let minutes = duration.as_secs() / 60;
+
+    format!("{:02}:{:02}", minutes, seconds)
+}
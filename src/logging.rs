@@ -0,0 +1,34 @@
+//! Sets up logging with [tracing], which always goes to a file, since
+//! the terminal is taken up by the UI & is in raw mode anyway.
+
+use std::{fs::OpenOptions, path::Path, sync::Mutex};
+
+use tracing::level_filters::LevelFilter;
+
+/// Gets the most detailed level that's logged, with `verbosity` being how many times `-v` was passed.
+fn level(verbosity: u8) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Starts writing logs to the end of the file at `path`.
+pub fn init(path: &Path, verbosity: u8) -> eyre::Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|error| eyre::eyre!("unable to open {}: {}", path.display(), error))?;
+
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_max_level(level(verbosity))
+        .with_ansi(false)
+        .with_target(false)
+        .init();
+
+    Ok(())
+}
@@ -1,9 +1,31 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
 use clap::{Parser, Subcommand};
 
+mod check;
+#[cfg(unix)]
+mod control;
+#[cfg(unix)]
+mod daemon;
+#[cfg(feature = "duck")]
+mod duck;
+mod hook;
+mod logging;
+mod now_playing;
 mod play;
-mod player;
+#[cfg(feature = "discord")]
+mod presence;
+mod remote;
 mod scrape;
-mod tracks;
+#[cfg(feature = "lua")]
+mod script;
+mod scrobble;
+mod shutdown;
+#[cfg(feature = "suspend")]
+mod suspend;
+mod ui;
+
+use lowfi::{config, player, stats, storage, tracks};
 
 /// An extremely simple lofi player.
 #[derive(Parser)]
@@ -13,6 +35,137 @@ struct Args {
     #[clap(long, short)]
     alternate: bool,
 
+    /// A custom track list to use instead of the built-in one.
+    ///
+    /// This is a file with one URL per line, optionally followed by a tab & a name.
+    #[clap(long, short)]
+    tracks: Option<PathBuf>,
+
+    /// The station to start on, which is a track list in the stations directory.
+    #[clap(long, short, conflicts_with = "tracks")]
+    station: Option<String>,
+
+    /// The volume to start at as a percentage, instead of the one from last time.
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    volume: Option<u8>,
+
+    /// How much to boost or cut the bass in decibels, instead of the config or last time.
+    #[clap(long, allow_negative_numbers = true, value_parser = clap::value_parser!(i8).range(-12..=12))]
+    bass: Option<i8>,
+
+    /// How much to boost or cut the treble in decibels, instead of the config or last time.
+    #[clap(long, allow_negative_numbers = true, value_parser = clap::value_parser!(i8).range(-12..=12))]
+    treble: Option<i8>,
+
+    /// How many frames the visualizer draws per second, which drops to 1 while paused or unfocused.
+    #[clap(long)]
+    fps: Option<f32>,
+
+    /// The inner width of the UI, instead of fitting it to the terminal.
+    #[clap(long, short)]
+    width: Option<usize>,
+
+    /// Draws the album art beside the UI, in terminals that support it.
+    #[clap(long)]
+    artwork: bool,
+
+    /// Turns off every color, just like setting `NO_COLOR` or `color = false` in the config.
+    #[clap(long)]
+    no_color: bool,
+
+    /// Which frontend to draw the UI with.
+    #[clap(long, value_enum)]
+    frontend: Option<config::Frontend>,
+
+    /// Draws the UI as a single line, which is short for `--frontend minimal`.
+    #[clap(long, conflicts_with = "frontend")]
+    minimal: bool,
+
+    /// The name of the audio device to play on, see `lowfi devices`.
+    #[clap(long)]
+    device: Option<String>,
+
+    /// The audio system to play through, which also changes what `lowfi devices` lists.
+    #[clap(long, value_enum)]
+    backend: Option<config::Backend>,
+
+    /// Stops lowfi after some time, like `45m` or `1h30m`.
+    #[clap(long, value_parser = player::timer::parse)]
+    timer: Option<Duration>,
+
+    /// Alternates between focusing & taking breaks, like `25/5` for 25 minutes & then 5.
+    #[clap(long, value_parser = player::pomodoro::parse)]
+    pomodoro: Option<player::pomodoro::Intervals>,
+
+    /// The fastest that tracks can be downloaded, like `256k` or `1m` per second.
+    #[clap(long)]
+    max_bandwidth: Option<tracks::download::Bandwidth>,
+
+    /// The proxy to send every request through, like `http://host:port`.
+    #[clap(long)]
+    proxy: Option<String>,
+
+    /// How many seconds of a stream to buffer before it starts playing, which is 2 by default.
+    #[clap(long)]
+    buffer_size: Option<f32>,
+
+    /// A file to keep up to date with what's playing, for OBS & the like.
+    ///
+    /// This is the name of the track, or JSON with the elapsed time & duration if it ends with `.json`.
+    #[clap(long)]
+    now_playing_file: Option<PathBuf>,
+
+    /// A named pipe to copy the audio into, for visualizers like cava, which is made if it doesn't exist.
+    ///
+    /// The audio is 16 bit little endian stereo at 44.1kHz.
+    #[cfg(unix)]
+    #[clap(long)]
+    audio_fifo: Option<PathBuf>,
+
+    /// Records everything that's played into a single `.mp3` or `.ogg` file,
+    /// with a chapter for every track, which needs ffmpeg.
+    #[clap(long)]
+    record: Option<PathBuf>,
+
+    /// Serves a web remote on an address like `127.0.0.1:8080`, to control lowfi from a phone.
+    #[clap(long)]
+    web: Option<SocketAddr>,
+
+    /// Serves the MPD protocol on an address like `127.0.0.1:6600`, for MPD clients.
+    #[clap(long)]
+    mpd: Option<SocketAddr>,
+
+    /// Only play tracks which have already been downloaded & cached.
+    #[clap(long)]
+    offline: bool,
+
+    /// Starts with the track that was playing when lowfi last quit, from where it left off.
+    #[clap(long)]
+    resume: bool,
+
+    /// Runs in the background without a UI, only being controlled through
+    /// `lowfi status`, the control socket & MPRIS. Logs go to `lowfi.log`
+    /// in the data directory, unless there's a `--log-file`.
+    #[cfg(unix)]
+    #[clap(long)]
+    daemon: bool,
+
+    /// Show what's playing on Discord, which needs `discord_client_id` in the config.
+    #[cfg(feature = "discord")]
+    #[clap(long)]
+    discord: bool,
+
+    /// The file to write logs to, which is `lowfi.log` in the data directory by default.
+    #[clap(long)]
+    log_file: Option<PathBuf>,
+
+    /// Logs in more detail, which can be repeated up to `-vvv`.
+    ///
+    /// Only warnings are logged by default, and nothing is
+    /// logged at all without this, `--log-file` or `--daemon`.
+    #[clap(long, short, action = clap::ArgAction::Count)]
+    verbose: u8,
+
     /// The command that was ran.
     /// This is [None] if no command was specified.
     #[command(subcommand)]
@@ -32,20 +185,246 @@ enum Commands {
         #[clap(long, short)]
         include_full: bool,
     },
+
+    /// Plays only the tracks that were saved with the favorite keybind.
+    Favorites,
+
+    /// Plays the audio files in a directory, instead of a track list.
+    Play {
+        /// The directory to play, which is searched for mp3, ogg, flac & opus files.
+        dir: PathBuf,
+    },
+
+    /// Checks that every track in the track list works, and reports any that are broken or duplicated.
+    Check {
+        /// Writes a cleaned up track list to this path, with only the tracks that work.
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Lists the audio devices that lowfi can play on.
+    Devices,
+
+    /// Prints how much has been listened to, and the most played tracks.
+    Stats {
+        /// How many of the most played tracks to show.
+        #[clap(long, short, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Waits until a time of day, and then starts playing with the volume slowly going up.
+    Alarm {
+        /// When to start playing, like `07:30`.
+        #[clap(value_parser = player::alarm::parse)]
+        time: chrono::NaiveTime,
+
+        /// How long it takes to get up to the usual volume, like `5m` or `90s`.
+        #[clap(long, default_value = "3m", value_parser = player::timer::parse)]
+        ramp: Duration,
+    },
+
+    /// Prints the status of an already running instance of lowfi.
+    #[cfg(unix)]
+    Status {
+        /// Print the status as JSON.
+        #[clap(long, short)]
+        json: bool,
+    },
+}
+
+fn main() -> eyre::Result<()> {
+    // Setting environment variables isn't safe once there are other threads around,
+    // so this happens before the runtime is started.
+    player::output::label();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
 }
 
-#[tokio::main]
-async fn main() -> eyre::Result<()> {
+/// Actually runs lowfi, once the runtime has been started.
+async fn run() -> eyre::Result<()> {
     let cli = Args::parse();
 
+    // CLI flags always take priority over the config file.
+    let mut config = config::Config::load()?;
+    config.alternate |= cli.alternate;
+    config.artwork |= cli.artwork;
+    config.theme.color &= !cli.no_color;
+    if cli.minimal {
+        config.frontend = config::Frontend::Minimal;
+    } else if let Some(frontend) = cli.frontend {
+        config.frontend = frontend;
+    }
+    config.offline = cli.offline;
+    config.resume = cli.resume;
+    config.timer = cli.timer;
+    config.pomodoro = cli.pomodoro;
+    config.record = cli.record;
+    #[cfg(unix)]
+    if cli.daemon {
+        config.daemon = true;
+    }
+    if cli.device.is_some() {
+        config.device = cli.device;
+    }
+    if let Some(backend) = cli.backend {
+        config.backend = backend;
+    }
+    if cli.max_bandwidth.is_some() {
+        config.network.max_bandwidth = cli.max_bandwidth;
+    }
+    if cli.proxy.is_some() {
+        config.network.proxy = cli.proxy;
+    }
+    if let Some(buffer_size) = cli.buffer_size {
+        if !buffer_size.is_finite() || buffer_size < 0.0 {
+            eyre::bail!("--buffer-size has to be a number of seconds, like 5");
+        }
+
+        config.network.buffer_size = buffer_size;
+    }
+    if cli.now_playing_file.is_some() {
+        config.now_playing_file = cli.now_playing_file;
+    }
+    #[cfg(unix)]
+    if cli.audio_fifo.is_some() {
+        config.audio_fifo = cli.audio_fifo;
+    }
+    if cli.web.is_some() {
+        config.web = cli.web;
+    }
+    if cli.mpd.is_some() {
+        config.mpd = cli.mpd;
+    }
+    if cli.station.is_some() {
+        config.station = cli.station;
+        config.sources.clear();
+    }
+    if let Some(fps) = cli.fps {
+        if !fps.is_normal() || fps < 0.0 {
+            eyre::bail!("--fps has to be a number above 0, like 12");
+        }
+
+        config.fps = fps;
+    }
+    if let Some(width) = cli.width {
+        config.width = Some(width.max(config::MIN_WIDTH));
+    }
+
+    #[cfg(feature = "discord")]
+    if cli.discord {
+        if config.discord_client_id.is_none() {
+            eyre::bail!("--discord needs `discord_client_id` to be set in the config");
+        }
+
+        config.discord = true;
+    }
+
+    // The volume from last time takes priority over the config, but not the `--volume` flag.
+    if let Some(volume) = cli.volume {
+        config.volume = volume;
+    } else if config.persist_volume {
+        if let Some(volume) = storage::load_volume().await {
+            config.volume = volume;
+        }
+    }
+
+    // Just like the volume, the `--bass` & `--treble` flags take priority over the equalizer
+    // from last time, which in turn takes priority over the config.
+    if config.equalizer.persist {
+        if let Some((bass, treble)) = storage::load_equalizer().await {
+            config.equalizer.bass = bass;
+            config.equalizer.treble = treble;
+        }
+    }
+
+    if let Some(bass) = cli.bass {
+        config.equalizer.bass = bass;
+    }
+
+    if let Some(treble) = cli.treble {
+        config.equalizer.treble = treble;
+    }
+
+    // The terminal is taken up by the UI, so logs can only ever go to a file.
+    #[cfg(unix)]
+    let daemon = config.daemon;
+    #[cfg(not(unix))]
+    let daemon = false;
+
+    let log_file = match cli.log_file {
+        Some(path) => Some(path),
+        None if daemon || cli.verbose > 0 => Some(storage::log().await?),
+        None => None,
+    };
+
+    if let Some(path) = log_file {
+        // A daemon logs every track, since that's the only way of seeing what it's up to.
+        let verbosity = if daemon {
+            cli.verbose.max(1)
+        } else {
+            cli.verbose
+        };
+        logging::init(&path, verbosity)?;
+    }
+
     if let Some(command) = cli.command {
         match command {
             Commands::Scrape {
                 extension,
                 include_full,
             } => scrape::scrape(extension, include_full).await,
+            Commands::Favorites => {
+                let path = storage::favorites().await?;
+                if !path.exists() {
+                    eyre::bail!(
+                        "you haven't saved any favorites yet, press 'f' while a track is playing"
+                    );
+                }
+
+                play::play(config, Some(path)).await
+            }
+            Commands::Play { dir } => {
+                if !dir.is_dir() {
+                    eyre::bail!("{} isn't a directory", dir.display());
+                }
+
+                play::play(config, Some(dir)).await
+            }
+            Commands::Check { output } => check::run(config, cli.tracks, output).await,
+            Commands::Devices => {
+                let default = player::output::default_device(config.backend);
+                for device in player::output::devices(config.backend)? {
+                    if Some(&device) == default.as_ref() {
+                        println!("{} (default)", device);
+                    } else {
+                        println!("{}", device);
+                    }
+                }
+
+                Ok(())
+            }
+            Commands::Alarm { time, ramp } => {
+                let at = player::alarm::next(time);
+                println!(
+                    "waiting until {}, press ctrl+c to cancel",
+                    at.format("%H:%M on %A")
+                );
+                player::alarm::wait(at).await;
+
+                config.ramp = Some(ramp);
+                play::play(config, cli.tracks).await
+            }
+            Commands::Stats { top } => {
+                stats::Stats::load().await?.print(top);
+                Ok(())
+            }
+            #[cfg(unix)]
+            Commands::Status { json } => control::query(json).await,
         }
     } else {
-        play::play(cli.alternate).await
+        play::play(config, cli.tracks).await
     }
 }
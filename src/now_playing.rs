@@ -0,0 +1,89 @@
+//! Keeps a file up to date with what's playing, for things like OBS text sources.
+//!
+//! If the file ends with `.json`, then it's the track along with how far into it
+//! playback is, otherwise it's just the name of the track on its own.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde_json::json;
+use tokio::{fs, task, time::sleep};
+use tracing::warn;
+
+use lowfi::player::Player;
+
+/// How often the file is checked to see if it's out of date.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Gets what should be in the file right now.
+fn contents(player: &Player, json: bool) -> String {
+    let current = player.current.load();
+
+    if !json {
+        return current
+            .as_ref()
+            .map_or_else(String::new, |x| x.display_name());
+    }
+
+    let state = match current.as_ref() {
+        None if player.connectivity.lost() => "offline",
+        None => "loading",
+        Some(_) if player.sink.is_paused() => "paused",
+        Some(_) => "playing",
+    };
+
+    json!({
+        "state": state,
+        "track": current.as_ref().map(|x| &x.name),
+        "artist": current.as_ref().and_then(|x| x.artist.as_ref()),
+        "album": current.as_ref().and_then(|x| x.album.as_ref()),
+        "url": current.as_ref().map(|x| &x.url),
+        "elapsed": current.as_ref().map(|_| player.elapsed().as_secs()),
+        "duration": current.as_ref().and_then(|x| x.duration).map(|x| x.as_secs()),
+    })
+    .to_string()
+}
+
+/// Replaces the file all at once, by writing to a temporary file next to it and moving it over,
+/// so that nothing ever reads a half written file.
+async fn write(path: &Path, contents: &str) -> eyre::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, contents).await?;
+    fs::rename(&temporary, path).await?;
+
+    Ok(())
+}
+
+/// Starts writing to the file in the background, which will keep going until lowfi exits.
+pub fn start(player: Arc<Player>, path: PathBuf) {
+    let json = path.extension().is_some_and(|x| x == "json");
+
+    task::spawn(async move {
+        let mut written: Option<String> = None;
+
+        loop {
+            let contents = contents(&player, json);
+
+            // The file is only touched when something's actually different.
+            if written.as_ref() != Some(&contents) {
+                match write(&path, &contents).await {
+                    Ok(()) => written = Some(contents),
+                    Err(error) => warn!("unable to write to {}: {:#}", path.display(), error),
+                }
+            }
+
+            sleep(INTERVAL).await;
+        }
+    });
+}
+
+/// Empties the file, which should be done when lowfi exits so that nothing stale is left in it.
+pub async fn clear(path: &Path) -> eyre::Result<()> {
+    let json = path.extension().is_some_and(|x| x == "json");
+    write(path, if json { "{}" } else { "" }).await
+}
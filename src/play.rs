@@ -1,38 +1,288 @@
 //! Responsible for the basic initialization & shutdown of the audio server & frontend.
 
-use std::{io::stderr, sync::Arc};
+use std::{io::stderr, path::PathBuf, sync::Arc};
 
 use crossterm::{cursor::SavePosition, terminal};
 use tokio::{
+    select,
     sync::mpsc::{self},
     task::{self},
 };
+use tracing::{info, warn};
 
-use crate::player::Player;
-use crate::player::{ui, Messages};
+use lowfi::config::Config;
+use lowfi::player::Player;
+use lowfi::player::{alarm, output, pomodoro, power, reload, timer, Messages};
+use lowfi::storage;
+use lowfi::tracks::{download::Network, list::List, source, station};
+
+#[cfg(unix)]
+use crate::control;
+#[cfg(unix)]
+use crate::daemon;
+#[cfg(feature = "duck")]
+use crate::duck;
+use crate::hook;
+use crate::now_playing;
+#[cfg(feature = "discord")]
+use crate::presence;
+use crate::remote;
+#[cfg(feature = "lua")]
+use crate::script;
+use crate::scrobble;
+use crate::shutdown;
+#[cfg(feature = "suspend")]
+use crate::suspend;
+use crate::ui;
+
+/// Loads the list of tracks, along with the name of the station if it's from one.
+///
+/// `tracks` is an optional path to a custom track list or a directory of
+/// audio files, and the configured sources or station are used if it's [None].
+pub async fn load(
+    config: &Config,
+    tracks: Option<PathBuf>,
+    network: &Network,
+) -> eyre::Result<(List, Option<String>)> {
+    Ok(match tracks {
+        Some(path) => {
+            let entries = source::from_path(path).entries(network).await?;
+            (List::from_entries(entries)?, None)
+        }
+        None if !config.sources.is_empty() => {
+            let sources = config
+                .sources
+                .iter()
+                .map(|x| Ok((source::from_config(x)?, x.weight)))
+                .collect::<eyre::Result<Vec<_>>>()?;
+
+            (source::load(&sources, network).await?, None)
+        }
+        None => {
+            let station = config.station.as_deref().unwrap_or(station::DEFAULT);
+            (station::load(station)?, Some(station.to_owned()))
+        }
+    })
+}
 
 /// Initializes the audio server, and then safely stops
 /// it when the frontend quits.
-pub async fn play(alternate: bool) -> eyre::Result<()> {
-    // Save the position. This is important since later on we can revert to this position
-    // and clear any potential error messages that may have showed up.
-    // TODO: Figure how to set some sort of flag to hide error messages within rodio,
-    // TODO: Instead of just ignoring & clearing them after.
-    crossterm::execute!(stderr(), SavePosition)?;
+///
+/// `tracks` is where the tracks come from, see [load].
+pub async fn play(config: Config, tracks: Option<PathBuf>) -> eyre::Result<()> {
+    let config = Arc::new(config);
+
+    // Load the list before touching the terminal, so that any errors are printed normally.
+    // Only a track list file can be reloaded, since a directory is scanned just once.
+    let reloadable = tracks.clone().filter(|x| x.is_file());
+    let network = Network::new(&config.network)?;
+    let (list, station) = load(&config, tracks, &network).await?;
 
-    // Enable raw mode early in theory to prevent uncontrolled text in the terminal from the user.
-    terminal::enable_raw_mode()?;
+    // A daemon doesn't have a UI, so it leaves the terminal alone entirely.
+    #[cfg(unix)]
+    let headless = config.daemon;
+    #[cfg(not(unix))]
+    let headless = false;
 
+    if !headless {
+        // Save the position. This is important since later on we can revert to this position
+        // and clear any potential error messages that may have showed up.
+        // TODO: Figure how to set some sort of flag to hide error messages within rodio,
+        // TODO: Instead of just ignoring & clearing them after.
+        crossterm::execute!(stderr(), SavePosition)?;
+
+        // Enable raw mode early in theory to prevent uncontrolled text in the terminal from the user.
+        terminal::enable_raw_mode()?;
+        shutdown::restore_on_panic(Arc::clone(&config));
+    }
+
+    info!("starting");
     let (tx, rx) = mpsc::channel(8);
 
-    let player = Arc::new(Player::new().await?);
-    let audio = task::spawn(Player::play(Arc::clone(&player), tx.clone(), rx));
-    tx.send(Messages::Init).await?;
+    let player = Arc::new(Player::new(list, station, network, &config).await?);
+    let mut audio = task::spawn(Player::play(Arc::clone(&player), tx.clone(), rx));
+
+    let resume = if config.resume {
+        storage::load_resume().await
+    } else {
+        None
+    };
+    match resume {
+        Some((entry, position)) => tx.send(Messages::Restore(entry, position)).await?,
+        None => tx.send(Messages::Init).await?,
+    }
+
+    task::spawn(timer::watch(
+        Arc::clone(&player),
+        tx.clone(),
+        config.pause_on_timer,
+    ));
+
+    if let Some(length) = config.ramp {
+        task::spawn(alarm::ramp(Arc::clone(&player), config.volume, length));
+    }
+
+    if player.pomodoro.is_some() {
+        task::spawn(pomodoro::watch(Arc::clone(&player), tx.clone()));
+    }
+
+    if config.power.enabled {
+        task::spawn(power::watch(Arc::clone(&player), config.power.threshold));
+    }
+
+    if let Some(path) = reloadable {
+        task::spawn(reload::watch(Arc::clone(&player), tx.clone(), path));
+    }
+
+    if config.persist_volume {
+        task::spawn(Player::persist_volume(Arc::clone(&player)));
+    }
+
+    task::spawn(output::watch(
+        Arc::clone(&player),
+        tx.clone(),
+        config.pause_on_disconnect,
+    ));
+
+    #[cfg(unix)]
+    let listening = control::start(Arc::clone(&player), tx.clone()).await?;
+
+    // Without the socket, there'd be no way of controlling a daemon.
+    #[cfg(unix)]
+    if headless && !listening {
+        audio.abort();
+        eyre::bail!("lowfi is already running, see `lowfi status`");
+    }
+
+    // The terminal is already in raw mode by now, so this can't just bail.
+    if let Some(address) = config.web {
+        if let Err(error) =
+            remote::http::start(address, config.metrics, Arc::clone(&player), tx.clone()).await
+        {
+            warn!("{:#}", error);
+            player.report(format!("{:#}", error));
+        }
+    }
 
-    ui::start(Arc::clone(&player), tx.clone(), alternate).await?;
+    if let Some(address) = config.mpd {
+        if let Err(error) = remote::mpd::start(address, Arc::clone(&player), tx.clone()).await {
+            warn!("{:#}", error);
+            player.report(format!("{:#}", error));
+        }
+    }
 
+    // Only actually does anything if some scrobbling service has been set up.
+    scrobble::start(
+        Arc::clone(&player),
+        scrobble::Service::load(&config.scrobble),
+    );
+
+    if let Some(command) = &config.on_track_change {
+        hook::start(Arc::clone(&player), command.clone());
+    }
+
+    if let Some(path) = &config.now_playing_file {
+        now_playing::start(Arc::clone(&player), path.clone());
+    }
+
+    // Not every Linux system has logind, in which case lowfi just can't tell when it suspends.
+    #[cfg(feature = "suspend")]
+    if let Err(error) = suspend::start(Arc::clone(&player), config.resume_after_suspend).await {
+        warn!("unable to watch for suspends: {:#}", error);
+    }
+
+    // Without pactl, there's no way of telling when something else is playing.
+    #[cfg(feature = "duck")]
+    if config.duck.enabled {
+        if let Err(error) = duck::start(Arc::clone(&player), config.duck.clone()).await {
+            warn!("unable to watch for other applications: {:#}", error);
+        }
+    }
+
+    #[cfg(feature = "lua")]
+    if let Err(error) = script::start(Arc::clone(&player), tx.clone()).await {
+        warn!("unable to run the scripts: {:#}", error);
+    }
+
+    #[cfg(feature = "discord")]
+    if let (true, Some(client_id)) = (config.discord, &config.discord_client_id) {
+        presence::start(Arc::clone(&player), client_id.clone());
+    }
+
+    #[cfg(unix)]
+    let mut ui = if headless {
+        task::spawn(daemon::run())
+    } else {
+        task::spawn(ui::start(
+            Arc::clone(&player),
+            tx.clone(),
+            Arc::clone(&config),
+        ))
+    };
+    #[cfg(not(unix))]
+    let mut ui = task::spawn(ui::start(
+        Arc::clone(&player),
+        tx.clone(),
+        Arc::clone(&config),
+    ));
+
+    // The UI finishes when the user quits (or a daemon is stopped), while the audio
+    // server finishes when something else tells it to, like the sleep timer.
+    // A daemon already stops on its own after a signal, see [daemon::run].
+    let result = select! {
+        result = &mut audio => result,
+        result = &mut ui => result,
+        result = shutdown::signal(), if !headless => Ok(result),
+    };
+
+    ui.abort();
     audio.abort();
+
+    // This is saved every time, so that there's always something for `--resume` to go back to.
+    if let Some(current) = player.current.load_full() {
+        if let Err(error) = storage::save_resume(&current, player.elapsed()).await {
+            warn!("unable to save the current track: {:#}", error);
+        }
+    }
+
+    player.record(false).await;
     player.sink.stop();
 
-    Ok(())
+    // The terminal always has to be restored, even if something went wrong.
+    if !headless {
+        ui::stop(&config)?;
+    }
+
+    // The music has stopped by now, so nothing more is going to be recorded.
+    if let Some(recorder) = &player.recorder {
+        match task::block_in_place(|| recorder.finish()) {
+            Ok(path) => info!("saved the recording to {}", path.display()),
+            Err(error) => {
+                warn!("unable to save the recording: {:#}", error);
+                eprintln!("unable to save the recording, {:#}", error);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    if listening {
+        control::stop();
+    }
+
+    if let Some(path) = &config.now_playing_file {
+        now_playing::clear(path).await?;
+    }
+
+    // The debounced saver might not have caught the last change yet.
+    if config.persist_volume {
+        storage::save_volume(player.volume_percentage()).await?;
+    }
+
+    if config.equalizer.persist {
+        let equalizer = &player.equalizer;
+        storage::save_equalizer(equalizer.bass(), equalizer.treble()).await?;
+    }
+
+    // Whatever went wrong is only returned once everything's been cleaned up & saved.
+    result?
 }
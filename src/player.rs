@@ -2,25 +2,81 @@
 //! This also has the code for the underlying
 //! audio server which adds new tracks.
 
-use std::{collections::VecDeque, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
 
-use arc_swap::ArcSwapOption;
-use downloader::Downloader;
-use reqwest::Client;
-use rodio::{OutputStream, OutputStreamHandle, Sink};
+use ambience::Ambience;
+use arc_swap::{ArcSwap, ArcSwapOption};
+use connectivity::Connectivity;
+use downloader::{Downloader, Pending};
+use eyre::eyre;
+use metrics::Metrics;
+use output::Output;
+use pomodoro::{Break, Phase, Pomodoro};
+use rodio::Sink;
+use timer::Timer;
 use tokio::{
     select,
     sync::{
+        broadcast,
         mpsc::{Receiver, Sender},
-        RwLock,
+        watch, RwLock,
     },
-    task,
+    time::sleep,
 };
+use tracing::{debug, info, warn};
 
-use crate::tracks::{DecodedTrack, Track, TrackInfo};
+#[cfg(unix)]
+use crate::audio::fifo::Fifo;
+use crate::{
+    audio::{
+        fade::{Faded, Fader},
+        filters::{Equalized, Equalizer},
+        normalize::Normalized,
+        position::Position,
+        record::Recorder,
+        speed::{Speed, Stretched},
+        tap::{Tap, Tapped},
+        tee::{Destination, Teed},
+    },
+    config::Config,
+    stats::Stats,
+    storage,
+    tracks::{
+        cache::Cache,
+        download::{self, Network, Reporter},
+        list::{Entry, List},
+        local,
+        ratings::{Rating, Ratings},
+        selector::{self, Failures, Selector},
+        station, DecodedData, DecodedTrack, Track, TrackInfo,
+    },
+};
 
+pub mod alarm;
+pub mod ambience;
+pub mod browser;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod connectivity;
 pub mod downloader;
-pub mod ui;
+pub mod keymap;
+pub mod metrics;
+pub mod output;
+pub mod pomodoro;
+pub mod power;
+pub mod reload;
+pub mod timer;
+
+#[cfg(feature = "mpris")]
+pub mod mpris;
 
 /// Handles communication between the frontend & audio player.
 pub enum Messages {
@@ -34,17 +90,245 @@ pub enum Messages {
     /// Similar to Next, but specific to the first track.
     Init,
 
-    /// Pauses the [Sink]. This will also unpause it if it is paused.
+    /// Plays a specific track from some position, instead of the usual first track.
+    /// This is how `--resume` picks up where the last session left off.
+    Restore(Entry, Duration),
+
+    /// Pauses the [Sink], or unpauses it if it's already paused.
+    PlayPauseToggle,
+
+    /// Pauses the [Sink], doing nothing if it's already paused.
     Pause,
 
+    /// Unpauses the [Sink], doing nothing if it's already playing.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Resume,
+
     /// Change the volume of playback
     ChangeVolume(f32),
+
+    /// Sets the volume to an exact amount, from `0.0` to `1.0`.
+    SetVolume(f32),
+
+    /// Mutes playback, or unmutes it if it's already muted.
+    ToggleMute,
+
+    /// Saves the current track to the favorites file.
+    Favorite,
+
+    /// Copies the URL of the current track to the clipboard.
+    Copy,
+
+    /// Opens the URL of the current track in the browser.
+    Open,
+
+    /// Saves the current track to the downloads directory.
+    Download,
+
+    /// Turns the selected ambient sound up a step, or off after the loudest one, see [ambience].
+    CycleAmbience,
+
+    /// Selects the next ambient sound, for [Messages::CycleAmbience] to change.
+    SelectAmbience,
+
+    /// Seeks by a relative amount of seconds within the current track.
+    /// A negative amount seeks backwards.
+    Seek(i64),
+
+    /// Seeks to an exact position within the current track.
+    SeekTo(Duration),
+
+    /// Changes the playback speed, where `1.0` is normal.
+    ChangeSpeed(f32),
+
+    /// Boosts or cuts the bass by some amount of decibels.
+    ChangeBass(i8),
+
+    /// Boosts or cuts the treble by some amount of decibels.
+    ChangeTreble(i8),
+
+    /// Goes back to the most recent track in the history.
+    Previous,
+
+    /// Likes the current track, or unlikes it if it's already liked.
+    Like,
+
+    /// Blocks the current track so that it's never played again, and then skips it.
+    Block,
+
+    /// Turns repeating the current track on & off.
+    ToggleRepeat,
+
+    /// Plays the current track again from the start, which is
+    /// sent when a track ends while repeating.
+    Replay,
+
+    /// Moves the audio output over to the next device.
+    CycleDevice,
+
+    /// Switches to another station, see [station].
+    ChangeStation(String),
+
+    /// Replaces the custom track list with a new version of it, see [reload].
+    ReloadList(List),
+
+    /// Removes a track from the buffer, where `0` is the first one after the upcoming track.
+    RemoveQueued(usize),
+
+    /// Moves a track in the buffer from one position to another.
+    MoveQueued(usize, usize),
+
+    /// Cycles the sleep timer through its presets, and then turns it off.
+    CycleTimer,
+
+    /// The [pomodoro] cycle moved on to another phase.
+    Pomodoro(Phase),
+
+    /// Stops the audio server, which will then make lowfi quit.
+    Quit,
+}
+
+/// The maximum amount of tracks to keep in the history.
+const HISTORY_SIZE: usize = 100;
+
+/// How long the volume has to stay the same before it's saved.
+const VOLUME_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// How often to check whether the current track has ended.
+const END_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many percent of a track has to be downloaded for it to not be skipped
+/// once it's taken too long, see `skip_timeout`.
+const SKIP_PATIENCE: u8 = 50;
+
+/// How many [UiMessage]s can be waiting for the UI before the oldest ones are dropped.
+const MESSAGE_CAPACITY: usize = 16;
+
+/// A short message that the UI shows for a few seconds below the box, like
+/// "liked some track" or "download failed, retrying", see [Player::notify].
+#[derive(Clone, Debug)]
+pub struct UiMessage {
+    /// The message itself.
+    pub message: String,
+
+    /// Whether the message is about something going wrong.
+    pub problem: bool,
 }
 
-const TIMEOUT: Duration = Duration::from_secs(8);
+/// A snapshot of everything about the [Player] that the UI shows, apart from
+/// the position within the current track, since that changes all the time.
+/// That's read with [Player::elapsed] instead, which is exact to the frame.
+///
+/// A new snapshot is sent through [Player::state] whenever any of this changes, see [Player::publish].
+#[derive(Clone, Default, PartialEq)]
+pub struct PlayerState {
+    /// The track that's playing, which is [None] while loading.
+    pub current: Option<Arc<TrackInfo>>,
+
+    /// Whether the next track has already been queued up in the sink.
+    pub upcoming: bool,
+
+    /// Whether playback is paused.
+    pub paused: bool,
+
+    /// The volume as a percentage, which is the one from before muting while muted.
+    pub volume: u8,
+
+    /// Whether playback is muted.
+    pub muted: bool,
+
+    /// The playback speed, where `1.0` is normal.
+    pub speed: f32,
+
+    /// Whether the current track is repeating.
+    pub repeat: bool,
+
+    /// Whether the current track is liked.
+    pub liked: bool,
+
+    /// The bass & treble gains of the equalizer.
+    pub equalizer: (i8, i8),
 
-/// The amount of songs to buffer up.
-const BUFFER_SIZE: usize = 5;
+    /// When the sleep timer runs out, if it's on.
+    pub timer: Option<Instant>,
+
+    /// The pomodoro cycle, if it's on.
+    pub pomodoro: Option<Pomodoro>,
+
+    /// The name of the current station.
+    pub station: Option<Arc<String>>,
+
+    /// Whether the network seems to be down, in which case lowfi is waiting for it to come back.
+    pub disconnected: bool,
+}
+
+/// Something that happened to the [Player], which is worked out by comparing [PlayerState]s.
+#[derive(Clone)]
+pub enum Event {
+    /// A new track started playing.
+    TrackStart(Arc<TrackInfo>),
+
+    /// Playback was paused.
+    Pause,
+
+    /// Playback was unpaused.
+    Resume,
+
+    /// The volume changed, or it was muted or unmuted.
+    VolumeChange {
+        /// The volume as a percentage, which is the one from before muting while muted.
+        volume: u8,
+
+        /// Whether playback is muted.
+        muted: bool,
+    },
+}
+
+impl Event {
+    /// The name of the event, like `track_start`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TrackStart(_) => "track_start",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::VolumeChange { .. } => "volume_change",
+        }
+    }
+}
+
+impl PlayerState {
+    /// The [Event]s that it took to get from `last` to this state.
+    pub fn events(&self, last: &Self) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if let Some(current) = &self.current {
+            if !last
+                .current
+                .as_ref()
+                .is_some_and(|x| Arc::ptr_eq(x, current))
+            {
+                events.push(Event::TrackStart(Arc::clone(current)));
+            }
+        }
+
+        if self.paused != last.paused {
+            events.push(if self.paused {
+                Event::Pause
+            } else {
+                Event::Resume
+            });
+        }
+
+        if (self.volume, self.muted) != (last.volume, last.muted) {
+            events.push(Event::VolumeChange {
+                volume: self.volume,
+                muted: self.muted,
+            });
+        }
+
+        events
+    }
+}
 
 /// Main struct responsible for queuing up & playing tracks.
 pub struct Player {
@@ -55,25 +339,146 @@ pub struct Player {
     /// This is [`None`] when lowfi is buffering.
     pub current: ArcSwapOption<TrackInfo>,
 
+    /// The [`TrackInfo`] of the track that has already been decoded and
+    /// queued up in the sink right after the current one, so that it can
+    /// start without any gap.
+    ///
+    /// This is [`None`] if the next track hasn't been downloaded yet.
+    pub upcoming: ArcSwapOption<TrackInfo>,
+
+    /// The tracks that have been played, with the most recent one first.
+    /// This doesn't include the current track.
+    pub history: RwLock<VecDeque<Arc<TrackInfo>>>,
+
+    /// Whether to also write the history to disk.
+    save_history: bool,
+
+    /// The playback speed.
+    pub speed: Arc<Speed>,
+
+    /// The position within the current track, which is counted from the
+    /// frames that have been played, see [Player::elapsed].
+    pub position: Arc<Position>,
+
+    /// The equalizer that's applied to every track.
+    pub equalizer: Arc<Equalizer>,
+
+    /// Whether tracks are normalized to the same loudness, see [Normalized].
+    normalize: bool,
+
+    /// Fades tracks in & out when pausing, resuming & skipping.
+    pub fader: Arc<Fader>,
+
+    /// Where the samples that are being played go for the visualizer.
+    pub tap: Arc<Tap>,
+
+    /// The recording of the session, if there is one.
+    pub recorder: Option<Arc<Recorder>>,
+
+    /// Where the samples that are being played are copied to, like the [Recorder].
+    destinations: Vec<Arc<dyn Destination>>,
+
+    /// Where [UiMessage]s are sent, by the player itself & anything else that wants to tell the user something.
+    pub messages: broadcast::Sender<UiMessage>,
+
+    /// The sleep timer, which is [`None`] if it's off.
+    pub timer: ArcSwapOption<Timer>,
+
+    /// The latest [PlayerState], which the UI redraws whenever it changes.
+    pub state: watch::Sender<PlayerState>,
+
+    /// How far along the track that's being loaded is, which changes too often
+    /// to go through the [PlayerState].
+    pub progress: Reporter,
+
     /// The tracks, which is a [VecDeque] that holds
     /// *undecoded* [Track]s.
     tracks: RwLock<VecDeque<Track>>,
 
-    /// The web client, which can contain a UserAgent & some
-    /// settings that help lowfi work more effectively.
-    client: Client,
+    /// The tracks that are still being downloaded, in the order that they'll be played in,
+    /// see [Downloader].
+    pending: Mutex<VecDeque<Pending>>,
+
+    /// How many tracks are kept buffered or downloading, ahead of the current one.
+    prefetch: usize,
+
+    /// How many tracks are downloaded at once.
+    concurrency: usize,
+
+    /// How many tracks are downloaded at once while `saving` power.
+    saving_concurrency: usize,
+
+    /// Whether the laptop is running low on battery, which makes lowfi
+    /// save power where it can, see [power].
+    pub saving: AtomicBool,
+
+    /// Whether the current track plays again once it ends, instead of moving on.
+    pub repeat: AtomicBool,
+
+    /// The undecoded versions of `current` & `upcoming`, which are kept
+    /// so that the current track can be played again while repeating.
+    undecoded: Mutex<(Option<Track>, Option<Track>)>,
+
+    /// The volume from before muting, which is [Some] while muted so that it can be put back.
+    muted: Mutex<Option<f32>>,
+
+    /// The list of tracks to pick from, which is either the
+    /// current station's or one supplied with `--tracks`.
+    list: ArcSwap<List>,
 
-    /// The [OutputStreamHandle], which also can control some
-    /// playback, is for now unused and is here just to keep it
-    /// alive so the playback can function properly.
-    _handle: OutputStreamHandle,
+    /// Decides which track from `list` is played next.
+    selector: Box<dyn Selector>,
 
-    /// The [OutputStream], which is just here to keep the playback
-    /// alive and functioning.
-    _stream: OutputStream,
+    /// The liked & blocked tracks, which the selector also uses.
+    pub ratings: Arc<Ratings>,
+
+    /// The tracks that have failed to load, which the selector avoids.
+    failures: Arc<Failures>,
+
+    /// The URL of the track that's being waited on, if there is one, see [Player::next].
+    loading: ArcSwapOption<String>,
+
+    /// How long a track can take to start playing before it's skipped, which is [None] if it never is.
+    skip_timeout: Option<Duration>,
+
+    /// The name of the current station, which is [None] if a custom track list is used.
+    pub station: ArcSwapOption<String>,
+
+    /// Whether to only play tracks that are in the cache.
+    offline: bool,
+
+    /// Where tracks are saved to, see [storage::save_track].
+    download_dir: Option<PathBuf>,
+
+    /// The on-disk cache of tracks, which is [None] if it's disabled.
+    cache: Option<Cache>,
+
+    /// The web client & download settings.
+    pub network: Network,
+
+    /// Whether the network seems to be down, see [connectivity].
+    pub connectivity: Connectivity,
+
+    /// What's been downloaded & played so far, see [metrics].
+    pub metrics: Arc<Metrics>,
+
+    /// The audio output, which is what the [Sink] plays on.
+    pub output: Output,
+
+    /// The ambient sounds, which is [None] if there aren't any.
+    pub ambience: Option<Ambience>,
+
+    /// The pomodoro cycle, which is [None] if it's off.
+    pub pomodoro: Option<Pomodoro>,
+
+    /// What happens to the music during pomodoro breaks.
+    pomodoro_break: Break,
+
+    /// The sink that the pomodoro chimes are played on, which is only there if it's on.
+    chime: Option<Sink>,
 }
 
-/// SAFETY: This is necessary because [OutputStream] does not implement [Send],
+/// SAFETY: This is necessary because [rodio::OutputStream], which is in [Output], does not implement [Send],
 /// SAFETY: even though it is perfectly possible.
 unsafe impl Send for Player {}
 
@@ -82,49 +487,540 @@ unsafe impl Sync for Player {}
 
 impl Player {
     /// Initializes the entire player, including audio devices & sink.
-    pub async fn new() -> eyre::Result<Self> {
-        let (_stream, handle) = OutputStream::try_default()?;
-        let sink = Sink::try_new(&handle)?;
+    ///
+    /// `station` is the name of the station that `list` belongs to, if any, and `network`
+    /// should be the one that `list` was loaded with, so that it keeps any credentials.
+    pub async fn new(
+        list: List,
+        station: Option<String>,
+        network: Network,
+        config: &Config,
+    ) -> eyre::Result<Self> {
+        let (output, sink) = Output::new(config.device.as_deref(), config.backend)?;
+
+        // When the volume is going to ramp up, it starts from nothing.
+        if config.ramp.is_none() {
+            sink.set_volume(f32::from(config.volume) / 100.0);
+        } else {
+            sink.set_volume(0.0);
+        }
+
+        let ambience = match &config.ambience.tracks {
+            Some(path) => Some(Ambience::new(
+                List::load(path)?,
+                &output,
+                config.ambience.volume,
+                &config.ambience.volumes,
+            )?),
+            None => None,
+        };
+
+        let pomodoro = config.pomodoro.map(Pomodoro::new);
+
+        let mut destinations: Vec<Arc<dyn Destination>> = Vec::new();
+
+        #[cfg(unix)]
+        if let Some(path) = &config.audio_fifo {
+            let fifo = Fifo::open(path.clone()).map_err(|error| {
+                eyre!(
+                    "unable to open the audio fifo {}: {:#}",
+                    path.display(),
+                    error
+                )
+            })?;
+            destinations.push(Arc::new(fifo));
+        }
+
+        let recorder = match &config.record {
+            Some(path) => Some(Arc::new(Recorder::start(path.clone()).map_err(
+                |error| eyre!("unable to record to {}: {:#}", path.display(), error),
+            )?)),
+            None => None,
+        };
+        if let Some(recorder) = &recorder {
+            destinations.push(Arc::clone(recorder) as Arc<dyn Destination>);
+        }
+        let chime = match pomodoro {
+            Some(_) => Some(output.layer()?),
+            None => None,
+        };
+
+        let cache = if config.cache.size > 0 || config.offline {
+            let dir = match &config.cache.dir {
+                Some(dir) => dir.clone(),
+                None => Cache::default_dir()?,
+            };
+
+            Some(Cache::new(dir, config.cache.size * 1024 * 1024).await?)
+        } else {
+            None
+        };
+
+        let list = Self::playable(list, cache.as_ref(), config.offline)?;
+
+        // The favorites only matter if they're going to be weighted differently.
+        let favorites = if config.shuffle.favorite_weight == 1.0 {
+            None
+        } else {
+            match storage::favorites().await {
+                Ok(path) if path.exists() => List::load(&path).ok(),
+                _ => None,
+            }
+        };
+        let ratings = Arc::new(Ratings::load().await);
+        let failures = Arc::new(Failures::default());
+        let selector = selector::from_config(
+            &config.shuffle,
+            favorites.as_ref(),
+            Arc::clone(&ratings),
+            Arc::clone(&failures),
+        );
+
+        let history = if config.save_history {
+            storage::load_history(HISTORY_SIZE)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Arc::new)
+                .collect()
+        } else {
+            VecDeque::new()
+        };
+
+        let messages = broadcast::Sender::new(MESSAGE_CAPACITY);
+        let metrics = Arc::new(Metrics::default());
+        let network = network
+            .with_messages(messages.clone())
+            .with_metrics(Arc::clone(&metrics));
 
         Ok(Self {
-            tracks: RwLock::new(VecDeque::with_capacity(5)),
+            tracks: RwLock::new(VecDeque::with_capacity(config.network.prefetch)),
+            pending: Mutex::new(VecDeque::new()),
+            prefetch: config.network.prefetch,
+            concurrency: config.network.concurrency.max(1),
+            saving_concurrency: config.power.concurrency.max(1),
+            saving: AtomicBool::new(false),
+            repeat: AtomicBool::new(false),
+            undecoded: Mutex::new((None, None)),
+            muted: Mutex::new(None),
+            history: RwLock::new(history),
+            save_history: config.save_history,
             current: ArcSwapOption::new(None),
-            client: Client::builder()
-                .user_agent(concat!(
-                    env!("CARGO_PKG_NAME"),
-                    "/",
-                    env!("CARGO_PKG_VERSION")
-                ))
-                .timeout(TIMEOUT)
-                .build()?,
+            upcoming: ArcSwapOption::new(None),
+            speed: Arc::new(Speed::new(1.0, config.pitch_correction)),
+            position: Arc::new(Position::default()),
+            equalizer: Arc::new(Equalizer::new(
+                config.equalizer.bass,
+                config.equalizer.treble,
+            )),
+            normalize: config.normalize,
+            fader: Arc::new(Fader::new(Duration::from_millis(config.fade))),
+            tap: Arc::new(Tap::default()),
+            recorder,
+            destinations,
+            messages,
+            timer: ArcSwapOption::new(config.timer.map(|x| Arc::new(Timer::new(x)))),
+            state: watch::Sender::new(PlayerState::default()),
+            progress: watch::Sender::new(None),
+            list: ArcSwap::from_pointee(list),
+            selector,
+            ratings,
+            failures,
+            loading: ArcSwapOption::new(None),
+            skip_timeout: (config.network.skip_timeout > 0)
+                .then(|| Duration::from_secs(config.network.skip_timeout)),
+            station: ArcSwapOption::new(station.map(Arc::new)),
+            offline: config.offline,
+            download_dir: config.download_dir.clone(),
+            cache,
+            network,
+            connectivity: Connectivity::default(),
+            metrics,
             sink,
-            _handle: handle,
-            _stream,
+            output,
+            ambience,
+            pomodoro,
+            pomodoro_break: config.pomodoro_break,
+            chime,
         })
     }
 
+    /// Narrows down a list to the tracks that can actually be played.
+    fn playable(list: List, cache: Option<&Cache>, offline: bool) -> eyre::Result<List> {
+        // When offline, the only tracks that can be played are the ones that are already cached.
+        match (cache, offline) {
+            (Some(cache), true) => {
+                list.filter(|entry| local::is_local(&entry.url) || cache.contains(&entry.url))
+            }
+            _ => Ok(list),
+        }
+    }
+
     /// Just a shorthand for setting `current`.
     async fn set_current(&self, info: TrackInfo) -> eyre::Result<()> {
+        info!("playing {}", info.display_name());
+        self.metrics.play();
         self.current.store(Some(Arc::new(info)));
+        self.publish();
 
         Ok(())
     }
 
+    /// Takes a [PlayerState] snapshot of how things are right now.
+    fn snapshot(&self) -> PlayerState {
+        let current = self.current.load_full();
+        let liked = current
+            .as_ref()
+            .is_some_and(|x| self.ratings.get(&x.url) == Some(Rating::Liked));
+
+        PlayerState {
+            current,
+            liked,
+            upcoming: self.upcoming.load().is_some(),
+            paused: self.sink.is_paused(),
+            volume: self.volume_percentage(),
+            muted: self.muted().is_some(),
+            speed: self.speed.factor(),
+            repeat: self.repeat.load(Ordering::Relaxed),
+            equalizer: (self.equalizer.bass(), self.equalizer.treble()),
+            timer: self.timer.load().as_ref().map(|x| x.end),
+            pomodoro: self.pomodoro,
+            station: self.station.load_full(),
+            disconnected: self.connectivity.lost(),
+        }
+    }
+
+    /// Sends out a new [PlayerState], if anything has changed since the last one.
+    ///
+    /// This should be called after changing anything that's in the snapshot
+    /// outside of the audio server, which already does it after every message.
+    pub fn publish(&self) {
+        let snapshot = self.snapshot();
+        self.state.send_if_modified(|state| {
+            let changed = *state != snapshot;
+            *state = snapshot;
+            changed
+        });
+    }
+
     /// This will play the next track, as well as refilling the buffer in the background.
     pub async fn next(queue: Arc<Self>) -> eyre::Result<DecodedTrack> {
-        let track = match queue.tracks.write().await.pop_front() {
-            Some(x) => x,
+        let buffered = queue.tracks.write().await.pop_front();
+        let pending = match buffered {
+            Some(_) => None,
+            None => queue.pending().pop_front(),
+        };
+
+        // Having to wait for a download holds up playback, apart from with the very first track.
+        if buffered.is_none() && queue.metrics.played() > 0 {
+            queue.metrics.underrun();
+        }
+
+        let track = match (buffered, pending) {
+            (Some(x), _) => x,
+            // If nothing's been buffered yet, then the next track is probably already downloading,
+            // so that gets waited for instead of starting over with another one.
+            (None, Some(pending)) => {
+                queue
+                    .loading
+                    .store(Some(Arc::new(pending.entry.url.clone())));
+                pending.finish(Some(&queue.progress)).await?
+            }
             // If the queue is completely empty, then fallback to simply getting a new track.
             // This is relevant particularly at the first song.
-            None => Track::random(&queue.client).await?,
+            (None, None) => {
+                let list = queue.list.load_full();
+                let entry = list.pick(queue.selector.as_ref());
+                queue.loading.store(Some(Arc::new(entry.url.clone())));
+                Track::fetch(
+                    entry,
+                    &queue.network,
+                    queue.cache.as_ref(),
+                    Some(&queue.progress),
+                )
+                .await?
+            }
         };
+        queue.loading.store(None);
 
-        let decoded = track.decode()?;
+        let decoded = track.clone().decode()?;
+        queue.undecoded().0 = Some(track);
         queue.set_current(decoded.info.clone()).await?;
 
         Ok(decoded)
     }
 
+    /// Locks the tracks that are still downloading.
+    fn pending(&self) -> MutexGuard<'_, VecDeque<Pending>> {
+        self.pending.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Locks the undecoded versions of `current` & `upcoming`.
+    fn undecoded(&self) -> MutexGuard<'_, (Option<Track>, Option<Track>)> {
+        self.undecoded.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Locks the volume from before muting.
+    fn muted(&self) -> MutexGuard<'_, Option<f32>> {
+        self.muted.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Shows a [UiMessage] in the UI.
+    pub fn notify(&self, message: String, problem: bool) {
+        if problem {
+            warn!("{}", message);
+        } else {
+            info!("{}", message);
+        }
+
+        // It doesn't matter if nothing's listening, like when there's no UI.
+        let _ = self.messages.send(UiMessage { message, problem });
+    }
+
+    /// How many tracks can be downloading at once, which is lower while `saving` power.
+    fn concurrency(&self) -> usize {
+        if self.saving.load(Ordering::Relaxed) {
+            self.concurrency.min(self.saving_concurrency)
+        } else {
+            self.concurrency
+        }
+    }
+
+    /// Shows a [UiMessage] about something that went wrong.
+    pub fn report(&self, message: String) {
+        self.notify(message, true);
+    }
+
+    /// Adds the current track to the history, which should be done right before it's replaced.
+    async fn remember(&self) {
+        let Some(current) = self.current.load_full() else {
+            return;
+        };
+
+        if self.save_history {
+            // The history is a nice to have, so it's fine if it fails to save.
+            if let Err(error) = storage::add_history(&current).await {
+                warn!("unable to save the history: {:#}", error);
+            }
+        }
+
+        // The album art isn't needed anymore, and could add up to a lot of memory.
+        let current = Arc::new(TrackInfo {
+            artwork: None,
+            ..(*current).clone()
+        });
+
+        let mut history = self.history.write().await;
+        history.push_front(current);
+        history.truncate(HISTORY_SIZE);
+    }
+
+    /// Records how long the current track was listened to in the [Stats],
+    /// which should be done right before it stops playing.
+    ///
+    /// `ended` is whether it played all the way through, rather than being skipped.
+    pub async fn record(&self, ended: bool) {
+        let Some(current) = self.current.load_full() else {
+            return;
+        };
+
+        let listened = match current.duration {
+            Some(duration) if ended => duration,
+            _ => self.elapsed(),
+        };
+
+        if let Err(error) = Stats::record(&current, listened, ended).await {
+            warn!("unable to save the stats: {:#}", error);
+        }
+    }
+
+    /// How far into the current track playback is.
+    ///
+    /// This should be used instead of [Sink::get_pos], which isn't accurate
+    /// once the speed changes or after seeking, see [Position].
+    pub fn elapsed(&self) -> Duration {
+        self.position.elapsed()
+    }
+
+    /// Seeks to `position` within the current track, if there is one.
+    fn seek(&self, position: Duration) {
+        if self.current.load().is_none() {
+            return;
+        }
+
+        // Not every track supports seeking, in which case nothing happens.
+        if let Err(error) = self.sink.try_seek(position) {
+            debug!("unable to seek: {}", error);
+        }
+
+        // The position isn't in the snapshot, so the UI has to be told to redraw either way.
+        self.state.send_modify(|_| ());
+    }
+
+    /// The names of the tracks that are waiting to be played, in order.
+    ///
+    /// This doesn't include [Player::upcoming], which is already in the sink.
+    pub async fn queue(&self) -> Vec<String> {
+        self.tracks
+            .read()
+            .await
+            .iter()
+            .map(|track| track.name.clone())
+            .collect()
+    }
+
+    /// Queues up a decoded track in the sink, with the [Speed], [Equalizer] & [Fader]
+    /// applied to it, and the [Tap] on the end, which is then copied to the `destinations`.
+    ///
+    /// If normalization is on, the track is brought to a consistent loudness first,
+    /// with `replay_gain` if it's known.
+    ///
+    /// `name` is what the track is called in a recording.
+    fn append(&self, data: DecodedData, replay_gain: Option<f32>, name: String) {
+        let data: DecodedData = if self.normalize {
+            Box::new(Normalized::new(data, replay_gain))
+        } else {
+            data
+        };
+
+        let stretched = Stretched::new(data, Arc::clone(&self.speed), Arc::clone(&self.position));
+        let equalized = Equalized::new(stretched, Arc::clone(&self.equalizer));
+        let faded = Faded::new(equalized, Arc::clone(&self.fader));
+        let tapped = Tapped::new(faded, Arc::clone(&self.tap));
+
+        self.sink
+            .append(Teed::new(tapped, self.destinations.clone(), name));
+    }
+
+    /// Fades out whatever's playing, so that it can be paused or stopped without a hard cut.
+    ///
+    /// The [Fader] has to be faded in again afterwards, or nothing will be heard.
+    async fn fade_out(&self) {
+        self.fader.fade_out();
+        if !self.sink.is_paused() && !self.sink.empty() {
+            sleep(self.fader.duration()).await;
+        }
+    }
+
+    /// Fades out & then pauses.
+    async fn pause(&self) {
+        if !self.sink.is_paused() {
+            self.fade_out().await;
+            self.sink.pause();
+            if let Some(ambience) = &self.ambience {
+                ambience.pause();
+            }
+        }
+    }
+
+    /// Unpauses & then fades back in.
+    fn resume(&self) {
+        self.sink.play();
+        self.fader.fade_in();
+        if let Some(ambience) = &self.ambience {
+            ambience.resume();
+        }
+    }
+
+    /// Does whatever [Player::pomodoro_break] says to do with the music for a break,
+    /// and returns whether it was paused.
+    async fn rest(&self) -> bool {
+        match (self.pomodoro_break, &self.ambience) {
+            (Break::Continue, _) => false,
+            // Only the music is paused, so that the ambient sounds carry on by themselves.
+            (Break::Ambience, Some(ambience)) => {
+                self.fade_out().await;
+                self.sink.pause();
+                ambience.resume();
+                true
+            }
+            (Break::Pause | Break::Ambience, _) => {
+                self.pause().await;
+                true
+            }
+        }
+    }
+
+    /// Decodes the next track in the buffer and queues it up in the sink,
+    /// if there isn't one queued up already.
+    ///
+    /// This is cancel safe, since nothing is taken from the buffer until
+    /// the last `await` point.
+    async fn prefetch(&self) -> eyre::Result<()> {
+        // While repeating, the next track would just get in the way.
+        if self.current.load().is_none()
+            || self.upcoming.load().is_some()
+            || self.repeat.load(Ordering::Relaxed)
+        {
+            return Ok(());
+        }
+
+        let Some(track) = self.tracks.write().await.pop_front() else {
+            return Ok(());
+        };
+
+        let decoded = track.clone().decode()?;
+        self.append(
+            decoded.data,
+            decoded.replay_gain,
+            decoded.info.display_name(),
+        );
+        self.upcoming.store(Some(Arc::new(decoded.info)));
+        self.undecoded().1 = Some(track);
+        self.publish();
+
+        Ok(())
+    }
+
+    /// Finishes at the end of the current track, prefetching the
+    /// next track in the meantime as soon as it's been downloaded.
+    async fn track_end(&self) {
+        loop {
+            // Undecodable tracks are skipped over, but not without saying so.
+            if let Err(error) = self.prefetch().await {
+                debug!("unable to decode the upcoming track: {:#}", error);
+                self.report(format!("skipped a track, {}", download::describe(&error)));
+            }
+
+            // The sink holds the current track, and maybe the upcoming one.
+            let expected = if self.upcoming.load().is_some() { 2 } else { 1 };
+            if self.sink.len() < expected {
+                return;
+            }
+
+            sleep(END_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Gets the current volume as a rounded percentage.
+    ///
+    /// While muted, this is the volume that unmuting goes back to, so that it's what gets saved.
+    pub fn volume_percentage(&self) -> u8 {
+        let volume = self.muted().unwrap_or_else(|| self.sink.volume());
+        (volume * 100.0).round() as u8
+    }
+
+    /// Saves the volume whenever it changes, which is debounced so that
+    /// holding down a volume key doesn't write to the disk over and over.
+    pub async fn persist_volume(player: Arc<Self>) {
+        let mut saved = player.volume_percentage();
+        let mut last = saved;
+
+        loop {
+            sleep(VOLUME_DEBOUNCE).await;
+
+            let volume = player.volume_percentage();
+            if volume == last && volume != saved {
+                match storage::save_volume(volume).await {
+                    Ok(()) => saved = volume,
+                    Err(error) => warn!("unable to save the volume: {:#}", error),
+                }
+            }
+
+            last = volume;
+        }
+    }
+
     /// This is the main "audio server".
     ///
     /// `rx` & `ts` are used to communicate with it, for example when to
@@ -136,61 +1032,595 @@ impl Player {
     ) -> eyre::Result<()> {
         // `itx` is used to notify the `Downloader` when it needs to download new tracks.
         let (downloader, itx) = Downloader::new(player.clone());
-        downloader.start().await;
+        let _downloader = downloader.start();
 
         // Start buffering tracks immediately.
         itx.send(()).await?;
 
+        tokio::spawn(connectivity::watch(Arc::clone(&player)));
+
+        // The ambient sounds are loaded alongside the first tracks, without holding them up.
+        if player.ambience.is_some() {
+            let player = Arc::clone(&player);
+            tokio::spawn(async move {
+                let Some(ambience) = &player.ambience else {
+                    return;
+                };
+
+                for (name, error) in ambience.load(&player.network, player.cache.as_ref()).await {
+                    warn!("unable to load the ambient sound {}: {}", name, error);
+                    player.report(format!("unable to load the ambient sound {}", name));
+                }
+            });
+        }
+
+        // Whether the music was paused for a pomodoro break, so that it's only resumed if it was.
+        let mut resting = false;
+
+        // How many tracks in a row have failed to play, which makes lowfi wait longer before the next one.
+        let mut failures = 0;
+
+        // Messages that came in while the next track was loading, which are handled before any new ones.
+        let mut deferred = VecDeque::new();
+
+        // Registers lowfi on the session bus so that media keys & desktop applets can control it.
+        #[cfg(feature = "mpris")]
+        let mpris = mpris::Server::new(Arc::clone(&player), tx.clone()).await?;
+
         loop {
-            let clone = Arc::clone(&player);
-            let msg = select! {
-                Some(x) = rx.recv() => x,
+            // Whatever the last message changed is sent out here, before waiting for the next one.
+            player.publish();
+
+            // Whether the current track just ended by itself, rather than because of a message.
+            let (msg, ended) = match deferred.pop_front() {
+                Some(x) => (x, false),
+                None => select! {
+                    Some(x) = rx.recv() => (x, false),
+
+                    // Nothing was playing because the network went down, and now it's back.
+                    () = player.connectivity.unstalled() => (Messages::TryAgain, false),
 
-                // This future will finish only at the end of the current track.
-                Ok(_) = task::spawn_blocking(move || clone.sink.sleep_until_end()) => Messages::Next,
+                    // This future will finish only at the end of the current track.
+                    // While stalled there isn't one, so it'd otherwise finish straight away.
+                    () = player.track_end(), if !player.connectivity.stalled() => if player.repeat.load(Ordering::Relaxed) {
+                        (Messages::Replay, true)
+                    } else {
+                        (Messages::Next, true)
+                    },
+                },
             };
 
             match msg {
                 Messages::Next | Messages::Init | Messages::TryAgain => {
-                    // Skip as early as possible so that music doesn't play
-                    // while lowfi is "loading".
-                    player.sink.stop();
+                    player.connectivity.stall(false);
+                    if !ended {
+                        player.fade_out().await;
+                    }
+
+                    player.record(ended).await;
+                    player.remember().await;
+
+                    // If the next track is already queued up, then just move on to it.
+                    if let Some(upcoming) = player.upcoming.swap(None) {
+                        // The current track might've already ended by itself.
+                        if player.sink.len() > 1 {
+                            player.sink.skip_one();
+                        }
+                        player.fader.fade_in();
+
+                        info!("playing {}", upcoming.display_name());
+                        player.metrics.play();
+                        player.current.store(Some(upcoming));
+
+                        let upcoming = player.undecoded().1.take();
+                        player.undecoded().0 = upcoming;
+
+                        player.publish();
+
+                        // Notify the background downloader that there's an empty spot
+                        // in the buffer.
+                        itx.send(()).await?;
+                    } else {
+                        // Skip as early as possible so that music doesn't play
+                        // while lowfi is "loading".
+                        player.sink.stop();
+                        player.fader.fade_in();
+                        player.position.reset();
+
+                        // Serves as an indicator that the queue is "loading".
+                        // This is also set by Player::next.
+                        player.current.store(None);
+                        player.publish();
+
+                        // Skipping again while the track is still loading cancels its download,
+                        // and anything else that comes in is handled once it's done.
+                        let loading = Self::next(Arc::clone(&player));
+                        tokio::pin!(loading);
+
+                        // A track that takes too long to start is skipped, unless it's nearly there.
+                        let timeout = sleep(player.skip_timeout.unwrap_or_default());
+                        tokio::pin!(timeout);
+                        let mut patient = player.skip_timeout.is_none();
+
+                        let track = loop {
+                            select! {
+                                track = &mut loading => break Some(track),
+                                () = &mut timeout, if !patient => {
+                                    let progress = player.progress.borrow().and_then(|x| x.percentage());
+                                    if progress.is_some_and(|x| x >= SKIP_PATIENCE) {
+                                        patient = true;
+                                        continue;
+                                    }
+
+                                    if let Some(url) = player.loading.swap(None) {
+                                        player.failures.record(&url);
+                                        player.report(format!(
+                                            "{} took too long to start, skipping it",
+                                            TrackInfo::format_name(&url)
+                                        ));
+                                    }
+
+                                    deferred.push_front(Messages::Next);
+                                    break None;
+                                },
+                                Some(x) = rx.recv() => match x {
+                                    Messages::Next | Messages::ChangeStation(_) | Messages::Quit => {
+                                        deferred.push_front(x);
+                                        break None;
+                                    }
+                                    x => deferred.push_back(x),
+                                },
+                            }
+                        };
+
+                        let Some(track) = track else {
+                            debug!("cancelled loading the next track");
+                            continue;
+                        };
 
-                    // Serves as an indicator that the queue is "loading".
-                    // This is also set by Player::next.
+                        match track {
+                            Ok(track) => {
+                                failures = 0;
+                                player.append(
+                                    track.data,
+                                    track.replay_gain,
+                                    track.info.display_name(),
+                                );
+
+                                // Notify the background downloader that there's an empty spot
+                                // in the buffer.
+                                itx.send(()).await?;
+                            }
+                            Err(error) => {
+                                debug!("unable to play the next track: {:#}", error);
+
+                                // It's not the track's fault if the network is down, so rather than
+                                // backing off & trying another one, lowfi waits for it to come back.
+                                match player.loading.swap(None) {
+                                    Some(url) if connectivity::unreachable(&error) => {
+                                        player.connectivity.lose(url.to_string());
+                                        player.connectivity.stall(true);
+                                        continue;
+                                    }
+                                    Some(url) => player.failures.record(&url),
+                                    None => {}
+                                }
+
+                                failures += 1;
+                                let delay = download::backoff(failures);
+                                player.report(format!(
+                                    "{}, trying another track in {}s",
+                                    download::describe(&error),
+                                    delay.as_secs()
+                                ));
+
+                                sleep(delay).await;
+                                tx.send(Messages::TryAgain).await?
+                            }
+                        };
+                    }
+                }
+                Messages::PlayPauseToggle => {
+                    if player.sink.is_paused() {
+                        player.resume();
+                    } else {
+                        player.pause().await;
+                    }
+                }
+                Messages::Pause => player.pause().await,
+                Messages::Resume => player.resume(),
+                Messages::ChangeVolume(change) => {
+                    // Changing the volume while muted unmutes, starting from where it was.
+                    let volume = player
+                        .muted()
+                        .take()
+                        .unwrap_or_else(|| player.sink.volume());
+                    player.sink.set_volume((volume + change).clamp(0.0, 1.0));
+                }
+                Messages::SetVolume(volume) => {
+                    player.muted().take();
+                    player.sink.set_volume(volume.clamp(0.0, 1.0));
+                }
+                Messages::ToggleMute => {
+                    let mut muted = player.muted();
+                    match muted.take() {
+                        Some(volume) => player.sink.set_volume(volume),
+                        None => {
+                            *muted = Some(player.sink.volume());
+                            player.sink.set_volume(0.0);
+                        }
+                    }
+                }
+                Messages::ChangeSpeed(change) => {
+                    let factor = player.speed.change(change);
+
+                    // Without pitch correction, rodio can just resample everything.
+                    if !player.speed.pitch_correction() {
+                        player.sink.set_speed(factor);
+                    }
+                }
+                Messages::ChangeBass(change) => {
+                    player.equalizer.change_bass(change);
+                }
+                Messages::ChangeTreble(change) => {
+                    player.equalizer.change_treble(change);
+                }
+                Messages::Seek(offset) => {
+                    // Seeking to before the start of the track just restarts it, and
+                    // rodio already makes sure we never seek past the end.
+                    let position = player.elapsed().as_secs_f64() + offset as f64;
+                    player.seek(Duration::from_secs_f64(position.max(0.0)));
+                }
+                Messages::SeekTo(position) => player.seek(position),
+                Messages::Previous => {
+                    let Some(previous) = player.history.write().await.pop_front() else {
+                        continue;
+                    };
+
+                    player.fade_out().await;
+                    player.record(false).await;
+
+                    // The track that was playing goes into the history too, right where
+                    // the previous one was, so that going back doesn't lose it.
+                    player.remember().await;
+
+                    // The queued up track is thrown away, since it'd be played before the previous one.
+                    player.sink.stop();
+                    player.fader.fade_in();
+                    player.position.reset();
                     player.current.store(None);
+                    player.upcoming.store(None);
+                    player.undecoded().1 = None;
+                    player.publish();
 
-                    let track = Self::next(Arc::clone(&player)).await;
+                    let track = Track::fetch(
+                        &previous.entry(),
+                        &player.network,
+                        player.cache.as_ref(),
+                        Some(&player.progress),
+                    )
+                    .await
+                    .and_then(|track| {
+                        player.undecoded().0 = Some(track.clone());
+                        track.decode()
+                    });
 
                     match track {
                         Ok(track) => {
-                            player.sink.append(track.data);
-
-                            // Notify the background downloader that there's an empty spot
-                            // in the buffer.
-                            itx.send(()).await?;
+                            info!("playing {}", track.info.display_name());
+                            player.metrics.play();
+                            let name = track.info.display_name();
+                            player.current.store(Some(Arc::new(track.info)));
+                            player.append(track.data, track.replay_gain, name);
                         }
+                        // If the previous track can't be played anymore, just move on to a new one.
                         Err(error) => {
-                            if !error.downcast::<reqwest::Error>()?.is_timeout() {
-                                tokio::time::sleep(TIMEOUT).await;
+                            debug!("unable to play the previous track: {:#}", error);
+                            player.report(format!(
+                                "couldn't go back, {}",
+                                download::describe(&error)
+                            ));
+                            tx.send(Messages::TryAgain).await?
+                        }
+                    }
+                }
+                Messages::Restore(entry, position) => {
+                    let track = Track::fetch(
+                        &entry,
+                        &player.network,
+                        player.cache.as_ref(),
+                        Some(&player.progress),
+                    )
+                    .await
+                    .and_then(|track| {
+                        player.undecoded().0 = Some(track.clone());
+                        track.decode()
+                    });
+
+                    match track {
+                        Ok(track) => {
+                            info!("resuming {}", track.info.display_name());
+                            player.metrics.play();
+                            let name = track.info.display_name();
+                            player.current.store(Some(Arc::new(track.info)));
+                            player.append(track.data, track.replay_gain, name);
+
+                            if let Err(error) = player.sink.try_seek(position) {
+                                debug!("unable to seek to where the track left off: {}", error);
                             }
+                        }
+                        // The track might not be around anymore, in which case it's a normal start.
+                        Err(error) => {
+                            debug!("unable to resume the last track: {:#}", error);
+                            player
+                                .report(format!("couldn't resume, {}", download::describe(&error)));
+                            tx.send(Messages::Init).await?
+                        }
+                    }
+                }
+                Messages::ChangeStation(name) => {
+                    if player.station.load().as_deref() == Some(&name) {
+                        continue;
+                    }
 
-                            tx.send(Messages::TryAgain).await?
+                    // A station that can't be loaded is just ignored, leaving the current one playing.
+                    let list = station::load(&name).and_then(|list| {
+                        Self::playable(list, player.cache.as_ref(), player.offline)
+                    });
+                    let list = match list {
+                        Ok(list) => list,
+                        Err(error) => {
+                            warn!("unable to load station {}: {:#}", name, error);
+                            continue;
                         }
                     };
+
+                    info!("switching to station {}", name);
+
+                    player.list.store(Arc::new(list));
+                    player.station.store(Some(Arc::new(name)));
+
+                    // Everything that was buffered up belongs to the old station,
+                    // and anything that's still downloading isn't needed anymore.
+                    player.tracks.write().await.clear();
+                    player.pending().clear();
+                    player.upcoming.store(None);
+                    player.undecoded().1 = None;
+                    tx.send(Messages::Next).await?;
                 }
-                Messages::Pause => {
-                    if player.sink.is_paused() {
-                        player.sink.play();
+                Messages::ReloadList(list) => {
+                    // Once another station has been picked, the custom list isn't being played anymore.
+                    if player.station.load().is_some() {
+                        continue;
+                    }
+
+                    let list = match Self::playable(list, player.cache.as_ref(), player.offline) {
+                        Ok(list) => list,
+                        Err(error) => {
+                            player.report(format!("unable to reload the track list: {:#}", error));
+                            continue;
+                        }
+                    };
+
+                    // Tracks which were taken out of the list shouldn't be played anymore,
+                    // even if they've already been downloaded.
+                    let mut tracks = player.tracks.write().await;
+                    let buffered = tracks.len();
+                    tracks.retain(|track| list.entries().iter().any(|x| x.url == track.url));
+                    let mut removed = buffered - tracks.len();
+                    drop(tracks);
+
+                    // The same goes for the ones that are still downloading.
+                    removed += {
+                        let mut pending = player.pending();
+                        let downloading = pending.len();
+                        pending.retain(|x| list.entries().iter().any(|y| y.url == x.entry.url));
+                        downloading - pending.len()
+                    };
+
+                    let count = list.entries().len();
+                    info!("reloaded the track list, which has {} tracks", count);
+                    player.notify(format!("reloaded the track list ({} tracks)", count), false);
+                    player.list.store(Arc::new(list));
+
+                    if removed > 0 {
+                        itx.send(()).await?;
+                    }
+                }
+                Messages::RemoveQueued(index) => {
+                    let removed = player.tracks.write().await.remove(index);
+                    if let Some(track) = removed {
+                        player.notify(format!("removed {} from the queue", track.name), false);
+
+                        // Another track is downloaded to take its place.
+                        itx.send(()).await?;
+                    }
+                }
+                Messages::MoveQueued(from, to) => {
+                    let mut tracks = player.tracks.write().await;
+                    if from < tracks.len() && to < tracks.len() {
+                        if let Some(track) = tracks.remove(from) {
+                            tracks.insert(to, track);
+                        }
+                    }
+                }
+                Messages::Like | Messages::Block => {
+                    let Some(current) = player.current.load_full() else {
+                        continue;
+                    };
+
+                    let rating = if matches!(msg, Messages::Like) {
+                        Rating::Liked
                     } else {
-                        player.sink.pause();
+                        Rating::Blocked
+                    };
+
+                    match player.ratings.toggle(&current.url, rating).await {
+                        Ok(Some(Rating::Liked)) => {
+                            player.notify(format!("liked {}", current.name), false);
+                        }
+                        Ok(Some(Rating::Blocked)) => {
+                            player.notify(format!("blocked {}", current.name), false);
+                            tx.send(Messages::Next).await?;
+                        }
+                        Ok(None) => player.notify(format!("unrated {}", current.name), false),
+                        Err(error) => player.report(format!("couldn't save the rating, {}", error)),
                     }
                 }
-                Messages::ChangeVolume(change) => {
-                    // let new_volume = f32::min(1.0, f32::max(0.0, player.sink.volume() + change));
-                    player.sink.set_volume((player.sink.volume() + change).clamp(0.0, 1.0));
+                Messages::ToggleRepeat => {
+                    player.repeat.fetch_xor(true, Ordering::Relaxed);
+                }
+                Messages::Replay => {
+                    player.record(true).await;
+
+                    let (current, upcoming) = player.undecoded().clone();
+                    let Some(current) = current else {
+                        tx.send(Messages::Next).await?;
+                        continue;
+                    };
+
+                    // If the next track was queued up before repeating was turned on,
+                    // then it's already started playing, so it goes back into the buffer.
+                    if player.upcoming.swap(None).is_some() {
+                        player.undecoded().1 = None;
+                        if let Some(upcoming) = upcoming {
+                            player.tracks.write().await.push_front(upcoming);
+                        }
+                    }
+
+                    player.sink.stop();
+                    player.position.reset();
+
+                    match current.decode() {
+                        Ok(track) => {
+                            debug!("repeating {}", track.info.display_name());
+                            player.append(track.data, track.replay_gain, track.info.display_name());
+
+                            // Nothing in the snapshot changes, but the position starts over.
+                            player.state.send_modify(|_| ());
+                        }
+                        Err(error) => {
+                            debug!("unable to repeat the track: {:#}", error);
+                            tx.send(Messages::Next).await?;
+                        }
+                    }
+                }
+                Messages::CycleDevice => match player.output.cycle() {
+                    Ok(device) => player.notify(format!("playing on {}", device), false),
+                    Err(error) => player.report(format!("couldn't switch devices, {}", error)),
+                },
+                Messages::CycleTimer => {
+                    let timer = Timer::cycle(player.timer.load().as_deref());
+                    player.timer.store(timer.map(Arc::new));
+                }
+                Messages::Pomodoro(phase) => {
+                    if let Some(chime) = &player.chime {
+                        pomodoro::chime(chime, phase, player.sink.volume());
+                    }
+
+                    match phase {
+                        Phase::Break => {
+                            player.notify("time for a break".to_owned(), false);
+                            resting = !player.sink.is_paused() && player.rest().await;
+                        }
+                        Phase::Focus => {
+                            player.notify("back to focusing".to_owned(), false);
+                            if std::mem::take(&mut resting) {
+                                player.resume();
+                            }
+                        }
+                    }
+                }
+                Messages::Quit => break,
+                Messages::Favorite => {
+                    if let Some(current) = player.current.load_full() {
+                        // Failing to save a favorite isn't worth stopping the music over.
+                        match storage::add_favorite(&current).await {
+                            Ok(()) => player.notify(format!("favorited {}", current.name), false),
+                            Err(error) => {
+                                player.report(format!("couldn't save the favorite, {:#}", error))
+                            }
+                        }
+                    }
                 }
+                Messages::CycleAmbience | Messages::SelectAmbience => {
+                    let Some(ambience) = &player.ambience else {
+                        player.report(
+                            "there aren't any ambient sounds, see `ambience.tracks` in the config"
+                                .to_owned(),
+                        );
+                        continue;
+                    };
+
+                    let (name, volume) = match msg {
+                        Messages::SelectAmbience => ambience.select(),
+                        _ => ambience.cycle(),
+                    };
+                    match volume {
+                        0 => player.notify(format!("{} off", name), false),
+                        volume => player.notify(format!("{} {}%", name, volume), false),
+                    }
+                }
+                Messages::Download => {
+                    let Some(current) = player.current.load_full() else {
+                        continue;
+                    };
+
+                    // The track was already downloaded to be played, so it doesn't have to be again.
+                    let data = player
+                        .undecoded()
+                        .0
+                        .as_ref()
+                        .filter(|x| x.url == current.url)
+                        .map(|x| x.data.clone());
+
+                    let result = match data {
+                        _ if local::is_local(&current.url) => {
+                            Err(eyre!("it's already on the disk"))
+                        }
+                        Some(data) if !data.is_empty() => {
+                            storage::save_track(&current, &data, player.download_dir.as_deref())
+                                .await
+                        }
+                        _ => Err(eyre!("streams can't be downloaded")),
+                    };
+
+                    match result {
+                        Ok(path) => player.notify(format!("saved to {}", path.display()), false),
+                        Err(error) => player.report(format!("couldn't save the track, {}", error)),
+                    }
+                }
+                Messages::Open => {
+                    if let Some(current) = player.current.load_full() {
+                        if let Err(error) = browser::open(&current) {
+                            player.report(format!("couldn't open the track, {}", error));
+                        }
+                    }
+                }
+                #[cfg(feature = "clipboard")]
+                Messages::Copy => {
+                    if let Some(current) = player.current.load_full() {
+                        match clipboard::copy(&current) {
+                            Ok(text) => player.notify(format!("copied {}", text), false),
+                            Err(error) => player.report(format!("couldn't copy, {}", error)),
+                        }
+                    }
+                }
+                #[cfg(not(feature = "clipboard"))]
+                Messages::Copy => {
+                    player.report(
+                        "copying needs lowfi to be built with --features clipboard".to_owned(),
+                    );
+                }
+            }
+
+            // This is allowed to fail, since a broken D-Bus connection shouldn't stop playback.
+            #[cfg(feature = "mpris")]
+            if let Err(error) = mpris.changed().await {
+                debug!("unable to update mpris: {:#}", error);
             }
         }
+
+        Ok(())
     }
 }
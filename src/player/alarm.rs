@@ -0,0 +1,85 @@
+//! The alarm, which is `lowfi alarm 07:30`.
+//!
+//! This waits until the time comes around, and then starts playing
+//! with the volume slowly going up from nothing, see [ramp].
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use tokio::time::sleep;
+
+use super::Player;
+
+/// How often the volume goes up while ramping.
+const STEP: Duration = Duration::from_millis(250);
+
+/// Parses a time of day like `07:30` or `19:05`.
+pub fn parse(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text, "%H:%M")
+        .map_err(|_| format!("invalid time '{}', it should be like 07:30", text))
+}
+
+/// Works out the next time that `time` comes around, which is tomorrow if it's already passed today.
+pub fn next(time: NaiveTime) -> DateTime<Local> {
+    let now = Local::now();
+    let mut date = now.date_naive();
+
+    loop {
+        // If the clocks go forward over the time, then it just goes off an hour later.
+        let at = date.and_time(time);
+        let at = at.and_local_timezone(Local).earliest().or_else(|| {
+            (at + TimeDelta::hours(1))
+                .and_local_timezone(Local)
+                .earliest()
+        });
+
+        match at {
+            Some(at) if at > now => return at,
+            _ => date = date.succ_opt().unwrap_or(date),
+        }
+    }
+}
+
+/// Waits until `at`.
+///
+/// This goes by the clock on the wall rather than how long it's been,
+/// so that it still goes off on time if the computer was asleep in the meantime.
+pub async fn wait(at: DateTime<Local>) {
+    while let Ok(left) = (at - Local::now()).to_std() {
+        if left.is_zero() {
+            break;
+        }
+
+        sleep(left.min(Duration::from_secs(1))).await;
+    }
+}
+
+/// Slowly turns the volume up from nothing to `target` over `length`,
+/// starting once the first track is actually playing.
+///
+/// The volume is left alone as soon as anything else changes it,
+/// like if it's turned up or muted by hand partway through.
+pub async fn ramp(player: Arc<Player>, target: u8, length: Duration) {
+    let mut state = player.state.subscribe();
+    if state.wait_for(|x| x.current.is_some()).await.is_err() {
+        return;
+    }
+
+    let target = f32::from(target) / 100.0;
+    let steps = (length.as_secs_f32() / STEP.as_secs_f32()).ceil().max(1.0) as u32;
+    let mut last = player.sink.volume();
+
+    for step in 1..=steps {
+        sleep(STEP).await;
+        if (player.sink.volume() - last).abs() > f32::EPSILON {
+            break;
+        }
+
+        // Loudness isn't linear, so this goes up slowly at first so that it isn't jarring.
+        let progress = step as f32 / steps as f32;
+        last = target * progress * progress;
+
+        player.sink.set_volume(last);
+        player.publish();
+    }
+}
@@ -0,0 +1,247 @@
+//! Ambient sounds, like rain or a fireplace, which loop underneath the music.
+//!
+//! These come from their own track list, where every track is a separate layer
+//! with its own [Sink] on the [Output], so they're mixed in with the music without
+//! being affected by anything that's done to it, like skipping or the equalizer.
+//!
+//! Every layer has its own volume, so that rain can be turned down under a fireplace.
+//! One of them is selected at a time, which is the one that [Ambience::cycle] changes.
+
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use rodio::{Decoder, Sink, Source};
+
+use crate::tracks::{cache::Cache, download::Network, list::List, DecodedData, Track};
+
+use super::output::Output;
+
+/// The volumes that [Ambience::cycle] goes through, as percentages, where `0` is off.
+const VOLUMES: [u8; 5] = [0, 25, 50, 75, 100];
+
+/// A single ambient sound.
+struct Layer {
+    /// The name of the sound, like `rain`.
+    name: String,
+
+    /// The sink that it loops on.
+    sink: Sink,
+
+    /// The volume of the sound, as a percentage.
+    volume: AtomicU8,
+}
+
+impl Layer {
+    /// Sets the volume of the sound, as a percentage.
+    fn set_volume(&self, volume: u8) {
+        let volume = volume.min(100);
+        self.volume.store(volume, Ordering::Relaxed);
+        self.sink.set_volume(f32::from(volume) / 100.0);
+    }
+}
+
+/// All of the ambient sounds, which each have a volume that's separate from the music's.
+pub struct Ambience {
+    /// The track list that the sounds come from.
+    list: List,
+
+    /// The sounds, in the same order as the list, which is never empty.
+    layers: Vec<Layer>,
+
+    /// The index of the sound that [Ambience::cycle] changes the volume of.
+    selected: AtomicUsize,
+}
+
+impl Ambience {
+    /// Creates a layer on the output for every track in `list`, starting at `volume`,
+    /// unless there's a volume for its name in `volumes`.
+    ///
+    /// Nothing plays until the sounds are actually loaded, see [Ambience::load].
+    pub fn new(
+        list: List,
+        output: &Output,
+        volume: u8,
+        volumes: &BTreeMap<String, u8>,
+    ) -> eyre::Result<Self> {
+        let layers = list
+            .entries()
+            .iter()
+            .map(|entry| {
+                let name = entry.name.clone().unwrap_or_else(|| entry.url.clone());
+                let layer = Layer {
+                    sink: output.layer()?,
+                    volume: AtomicU8::new(0),
+                    name,
+                };
+                layer.set_volume(volumes.get(&layer.name).copied().unwrap_or(volume));
+
+                Ok(layer)
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(Self {
+            list,
+            layers,
+            selected: AtomicUsize::new(0),
+        })
+    }
+
+    /// Downloads all of the sounds and starts looping them, which
+    /// returns the names of any that couldn't be loaded along with why.
+    pub async fn load(&self, network: &Network, cache: Option<&Cache>) -> Vec<(String, String)> {
+        let mut failures = Vec::new();
+
+        for (entry, layer) in self.list.entries().iter().zip(&self.layers) {
+            let track = match Track::fetch(entry, network, cache, None).await {
+                Ok(track) => track,
+                Err(error) => {
+                    failures.push((layer.name.clone(), format!("{:#}", error)));
+                    continue;
+                }
+            };
+
+            // A stream is endless already, so it doesn't need to loop.
+            if let Some(stream) = track.stream {
+                layer.sink.append(stream.play());
+                continue;
+            }
+
+            match Looped::new(track.data) {
+                Ok(looped) => layer.sink.append(looped),
+                Err(error) => failures.push((layer.name.clone(), format!("{:#}", error))),
+            }
+        }
+
+        failures
+    }
+
+    /// The sound that's selected, which starts off as the first one.
+    fn selected(&self) -> &Layer {
+        &self.layers[self.selected.load(Ordering::Relaxed) % self.layers.len()]
+    }
+
+    /// Selects the next sound, going back to the first one after the last,
+    /// and returns its name & volume.
+    pub fn select(&self) -> (&str, u8) {
+        let next = (self.selected.load(Ordering::Relaxed) + 1) % self.layers.len();
+        self.selected.store(next, Ordering::Relaxed);
+
+        let layer = self.selected();
+        (&layer.name, layer.volume.load(Ordering::Relaxed))
+    }
+
+    /// The volume of the sound called `name`, as a percentage, if there is one.
+    pub fn volume(&self, name: &str) -> Option<u8> {
+        self.layers
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.volume.load(Ordering::Relaxed))
+    }
+
+    /// Sets the volume of the sound called `name`, as a percentage,
+    /// returning false if there isn't one.
+    pub fn set_volume(&self, name: &str, volume: u8) -> bool {
+        let layer = self.layers.iter().find(|x| x.name == name);
+        if let Some(layer) = layer {
+            layer.set_volume(volume);
+        }
+
+        layer.is_some()
+    }
+
+    /// Moves the volume of the selected sound up to the next step, going back
+    /// to off after the loudest one, and returns its name & new volume.
+    pub fn cycle(&self) -> (&str, u8) {
+        let layer = self.selected();
+        let current = layer.volume.load(Ordering::Relaxed);
+        let next = VOLUMES
+            .iter()
+            .copied()
+            .find(|&x| x > current)
+            .unwrap_or(VOLUMES[0]);
+
+        layer.set_volume(next);
+        (&layer.name, next)
+    }
+
+    /// The names of all of the sounds.
+    pub fn names(&self) -> Vec<&str> {
+        self.layers.iter().map(|x| x.name.as_str()).collect()
+    }
+
+    /// Pauses all of the sounds, along with the music.
+    pub fn pause(&self) {
+        for layer in &self.layers {
+            layer.sink.pause();
+        }
+    }
+
+    /// Unpauses all of the sounds, along with the music.
+    pub fn resume(&self) {
+        for layer in &self.layers {
+            layer.sink.play();
+        }
+    }
+}
+
+/// A [Source] which decodes a track over and over again, forever.
+///
+/// This keeps the track undecoded, since even a few minutes of decoded audio takes up a lot of memory.
+struct Looped {
+    /// The undecoded track.
+    data: Bytes,
+
+    /// The current pass through the track.
+    decoded: DecodedData,
+}
+
+impl Looped {
+    /// Starts looping a track.
+    fn new(data: Bytes) -> eyre::Result<Self> {
+        let decoded = Self::decode(&data)?;
+        Ok(Self { data, decoded })
+    }
+
+    /// Decodes the track from the start.
+    fn decode(data: &Bytes) -> eyre::Result<DecodedData> {
+        let decoder = Decoder::new(Cursor::new(data.clone()))?;
+        Ok(Box::new(decoder.convert_samples()))
+    }
+}
+
+impl Iterator for Looped {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.decoded.next() {
+            return Some(sample);
+        }
+
+        // If the track doesn't have anything in it, then it'd just be decoded forever.
+        self.decoded = Self::decode(&self.data).ok()?;
+        self.decoded.next()
+    }
+}
+
+impl Source for Looped {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoded.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoded.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoded.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,45 @@
+//! Opens tracks in the default web browser, so that it's easy to find where they're from.
+
+use std::process::Stdio;
+
+use eyre::bail;
+use tokio::process::Command;
+
+use crate::tracks::{local, TrackInfo};
+
+/// The command that opens a URL with whatever the system's default for it is.
+fn opener(url: &str) -> Command {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // Unlike `start`, this doesn't treat the `&` in URLs as the end of the command.
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    command.arg(url);
+    command
+}
+
+/// Opens a track's URL in the browser, without waiting for the browser to close.
+///
+/// Nothing that the opener prints is shown, since it'd end up in the middle of the UI.
+pub fn open(track: &TrackInfo) -> eyre::Result<()> {
+    if local::is_local(&track.url) {
+        bail!("it's a local file");
+    }
+
+    let mut child = opener(&track.url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // The opener is waited on in the background, so that it doesn't linger once it's done.
+    tokio::spawn(async move { child.wait().await });
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Copies the current track to the system clipboard, so that it can be shared.
+
+use std::sync::Mutex;
+
+use arboard::Clipboard;
+use eyre::eyre;
+use lazy_static::lazy_static;
+use tracing::warn;
+
+use crate::tracks::{local, TrackInfo};
+
+lazy_static! {
+    /// The system clipboard, which is [None] if there isn't one, like over SSH.
+    ///
+    /// This is kept open for as long as lowfi runs, since on X11 whatever's
+    /// copied is only there for as long as the program that copied it.
+    static ref CLIPBOARD: Option<Mutex<Clipboard>> = Clipboard::new()
+        .map_err(|error| warn!("unable to open the clipboard: {}", error))
+        .ok()
+        .map(Mutex::new);
+}
+
+/// Copies a track's URL to the clipboard, or its name if it's a local file,
+/// returning what was copied.
+pub fn copy(track: &TrackInfo) -> eyre::Result<String> {
+    let text = if local::is_local(&track.url) {
+        track.display_name()
+    } else {
+        track.url.clone()
+    };
+
+    let clipboard = CLIPBOARD
+        .as_ref()
+        .ok_or_else(|| eyre!("there isn't a clipboard"))?;
+    clipboard
+        .lock()
+        .unwrap_or_else(|x| x.into_inner())
+        .set_text(text.as_str())?;
+
+    Ok(text)
+}
@@ -0,0 +1,232 @@
+//! Notices when the network goes down, like on a train or behind a captive portal, so that
+//! lowfi can show that it's offline & wait for the network to come back, rather than
+//! going through track after track that can't be downloaded.
+//!
+//! Losing the connection is noticed from downloads that can't connect, see [unreachable()].
+//! Captive portals get in the way of HTTPS, so they count as being offline too.
+//! While offline, the track that failed is checked for every so often, with a
+//! [backoff](download::backoff), until it's reachable again. On Linux, the network
+//! interfaces are also watched, so that it's checked straight away once one comes up.
+//!
+//! The player also [stalls](Connectivity::stall) while it's offline, so that it doesn't
+//! try to fetch another track until the network is back, see [Connectivity::unstalled].
+
+use std::{
+    future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use reqwest::Method;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, info};
+
+use super::Player;
+use crate::tracks::download;
+
+/// How often the network interfaces are looked at while offline.
+const INTERFACE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether lowfi can reach the internet, going by how the latest downloads went.
+#[derive(Debug)]
+pub struct Connectivity {
+    /// The URL that couldn't be reached, which is [Some] while offline.
+    lost: watch::Sender<Option<String>>,
+
+    /// Whether the player has nothing to play because the network went down,
+    /// & is waiting for it to come back before fetching anything else.
+    stalled: AtomicBool,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self {
+            lost: watch::Sender::new(None),
+            stalled: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Connectivity {
+    /// Whether the network seems to be down at the moment.
+    pub fn lost(&self) -> bool {
+        self.lost.borrow().is_some()
+    }
+
+    /// Marks the network as down because `url` couldn't be reached, which is what's
+    /// checked to see if it's back. Returns whether it was only just lost.
+    pub fn lose(&self, url: String) -> bool {
+        self.lost.send_if_modified(|lost| {
+            if lost.is_some() {
+                return false;
+            }
+
+            info!("lost the connection to {}", url);
+            *lost = Some(url);
+            true
+        })
+    }
+
+    /// Waits until the network is back, which is straight away if it was never lost.
+    pub async fn wait(&self) {
+        let _ = self.lost.subscribe().wait_for(Option::is_none).await;
+    }
+
+    /// Sets whether the player is stalled, see [Connectivity::unstalled].
+    pub fn stall(&self, stalled: bool) {
+        self.stalled.store(stalled, Ordering::Relaxed);
+    }
+
+    /// Whether the player is waiting for the network before fetching another track.
+    pub fn stalled(&self) -> bool {
+        self.stalled.load(Ordering::Relaxed)
+    }
+
+    /// Waits until the network is back if the player is stalled, & then lets it carry on.
+    ///
+    /// If it isn't stalled, this never finishes, so that it can sit in a `select!`.
+    pub async fn unstalled(&self) {
+        if !self.stalled() {
+            return future::pending().await;
+        }
+
+        self.wait().await;
+        self.stall(false);
+    }
+}
+
+/// Whether `error` came from not being able to reach the server at all,
+/// rather than something being wrong with the track itself.
+pub fn unreachable(error: &eyre::Report) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|x| x.is_connect() || x.is_timeout())
+}
+
+/// Gets the names of the network interfaces that are up, apart from the loopback one.
+///
+/// This is [None] on anything other than Linux, where it isn't known.
+#[cfg(target_os = "linux")]
+fn interfaces() -> Option<Vec<String>> {
+    let mut up: Vec<String> = std::fs::read_dir("/sys/class/net")
+        .ok()?
+        .flatten()
+        .filter(|x| {
+            std::fs::read_to_string(x.path().join("operstate")).is_ok_and(|x| x.trim() == "up")
+        })
+        .map(|x| x.file_name().to_string_lossy().into_owned())
+        .filter(|x| x != "lo")
+        .collect();
+    up.sort();
+
+    Some(up)
+}
+
+/// Gets the names of the network interfaces that are up, apart from the loopback one.
+///
+/// This is [None] on anything other than Linux, where it isn't known.
+#[cfg(not(target_os = "linux"))]
+fn interfaces() -> Option<Vec<String>> {
+    None
+}
+
+/// Waits for `delay`, unless the network interfaces change before then.
+async fn settle(delay: Duration, interfaces: &mut Option<Vec<String>>) {
+    let deadline = Instant::now() + delay;
+
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+
+        sleep(left.min(INTERFACE_INTERVAL)).await;
+        let current = self::interfaces();
+        if current != *interfaces {
+            debug!("the network interfaces changed to {:?}", current);
+            *interfaces = current;
+            return;
+        }
+    }
+}
+
+/// Waits for the network to go down, & then checks for it to come back,
+/// over & over. This is started along with the audio server.
+pub async fn watch(player: Arc<Player>) {
+    let mut lost = player.connectivity.lost.subscribe();
+
+    loop {
+        let Ok(url) = lost.wait_for(Option::is_some).await.map(|x| x.clone()) else {
+            return;
+        };
+        let Some(url) = url else {
+            continue;
+        };
+
+        player.publish();
+        player.notify("offline, waiting for the network".to_owned(), true);
+
+        let mut interfaces = interfaces();
+        let mut failures = 1;
+        loop {
+            settle(download::backoff(failures), &mut interfaces).await;
+
+            // Any response at all means that the server can be reached again, even an error.
+            if player
+                .network
+                .request(Method::HEAD, &url)
+                .send()
+                .await
+                .is_ok()
+            {
+                break;
+            }
+
+            failures += 1;
+        }
+
+        player.connectivity.lost.send_replace(None);
+        player.publish();
+        player.notify("back online".to_owned(), false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::Connectivity;
+
+    #[tokio::test]
+    async fn stalls_until_the_network_is_back() {
+        let connectivity = Connectivity::default();
+        assert!(!connectivity.stalled());
+
+        // A fetch failed because the network is unreachable.
+        assert!(connectivity.lose("https://example.com/track.mp3".to_owned()));
+        connectivity.stall(true);
+
+        // Nothing else should be fetched while offline, and failing again doesn't count twice.
+        assert!(timeout(Duration::from_millis(50), connectivity.unstalled())
+            .await
+            .is_err());
+        assert!(connectivity.stalled());
+        assert!(!connectivity.lose("https://example.com/track.mp3".to_owned()));
+
+        connectivity.lost.send_replace(None);
+        assert!(timeout(Duration::from_millis(50), connectivity.unstalled())
+            .await
+            .is_ok());
+        assert!(!connectivity.stalled());
+
+        // Once it's carried on, it stays put until the player stalls again.
+        assert!(timeout(Duration::from_millis(50), connectivity.unstalled())
+            .await
+            .is_err());
+    }
+}
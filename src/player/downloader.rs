@@ -1,19 +1,106 @@
-//! Contains the [`Downloader`] struct.
+//! Contains the [`Downloader`] struct, which schedules the downloads of the tracks
+//! that are buffered up ahead of time.
 
 use std::sync::Arc;
 
 use tokio::{
-    sync::mpsc::{self, Receiver, Sender},
-    task,
+    select,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        watch,
+    },
+    task::{self, JoinHandle},
+    time::sleep,
 };
+use tracing::{debug, warn};
 
-use crate::tracks::Track;
+use crate::tracks::{
+    download::{self, Progress, Reporter},
+    list::Entry,
+    Track,
+};
+
+use super::{connectivity, Player};
+
+/// A track that's being downloaded in the background.
+///
+/// The download is aborted as soon as this is dropped, like when the buffer is cleared.
+pub struct Pending {
+    /// The entry that's being downloaded.
+    pub entry: Entry,
+
+    /// The task that's doing the actual download.
+    handle: JoinHandle<eyre::Result<Track>>,
+
+    /// How far along the download is.
+    progress: watch::Receiver<Option<Progress>>,
+}
 
-use super::{Player, BUFFER_SIZE};
+impl Pending {
+    /// Starts downloading `entry`, and wakes up the [Downloader] through `wake` once it's done.
+    fn start(player: &Arc<Player>, entry: Entry, wake: Sender<()>) -> Self {
+        let (reporter, progress) = watch::channel(None);
+        let player = Arc::clone(player);
+        let target = entry.clone();
+
+        let handle = task::spawn(async move {
+            let track = Track::fetch(
+                &target,
+                &player.network,
+                player.cache.as_ref(),
+                Some(&reporter),
+            )
+            .await;
+
+            // If the channel is full, then the downloader is already going to wake up.
+            let _ = wake.try_send(());
+            track
+        });
+
+        Self {
+            entry,
+            handle,
+            progress,
+        }
+    }
+
+    /// Whether the download has finished, whether or not it worked.
+    fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Waits for the download to finish, passing its progress on to `reporter` along the way.
+    pub async fn finish(mut self, reporter: Option<&Reporter>) -> eyre::Result<Track> {
+        let result = loop {
+            if let Some(reporter) = reporter {
+                reporter.send_replace(*self.progress.borrow_and_update());
+            }
+
+            select! {
+                result = &mut self.handle => break result,
+                Ok(()) = self.progress.changed() => {}
+            }
+        };
+
+        if let Some(reporter) = reporter {
+            reporter.send_replace(None);
+        }
+
+        result?
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
 
 /// This struct is responsible for downloading tracks in the background.
 ///
-/// This is not used for the first track or a track when the buffer is currently empty.
+/// It keeps up to `prefetch` tracks either buffered or downloading, with at most
+/// `concurrency` of them downloading at once. The first track is only waited on
+/// by the player if the buffer is empty, see [Player::next].
 pub struct Downloader {
     /// The player for the downloader to download to & with.
     player: Arc<Player>,
@@ -21,6 +108,9 @@ pub struct Downloader {
     /// The internal reciever, which is used by the downloader to know
     /// when to begin downloading more tracks.
     rx: Receiver<()>,
+
+    /// The other end of `rx`, which downloads use to say that they're done.
+    tx: Sender<()>,
 }
 
 impl Downloader {
@@ -30,23 +120,114 @@ impl Downloader {
     /// when the downloader needs to begin downloading more tracks.
     pub fn new(player: Arc<Player>) -> (Self, Sender<()>) {
         let (tx, rx) = mpsc::channel(8);
-        (Self { player, rx }, tx)
+        (
+            Self {
+                player,
+                rx,
+                tx: tx.clone(),
+            },
+            tx,
+        )
     }
 
-    /// Actually starts & consumes the [Downloader].
-    pub async fn start(mut self) {
-        task::spawn(async move {
-            // Loop through each update notification.
-            while self.rx.recv().await == Some(()) {
-                //  For each update notification, we'll push tracks until the buffer is completely full.
-                while self.player.tracks.read().await.len() < BUFFER_SIZE {
-                    let Ok(track) = Track::random(&self.player.client).await else {
-                        continue;
-                    };
+    /// Moves the downloads that have finished into the buffer, returning whether any of them failed.
+    ///
+    /// Tracks go into the buffer in the order that they were picked in, rather than the order
+    /// that they finished in, so that something like sequential shuffling still works.
+    /// `failures` is how many downloads in a row have failed.
+    async fn collect(&self, failures: &mut u32) -> bool {
+        let mut failed = false;
+
+        loop {
+            let finished = {
+                let mut pending = self.player.pending();
+                match pending.front() {
+                    Some(x) if x.is_finished() => pending.pop_front(),
+                    _ => None,
+                }
+            };
 
+            let Some(finished) = finished else {
+                return failed;
+            };
+
+            let url = finished.entry.url.clone();
+            match finished.finish(None).await {
+                Ok(track) => {
+                    debug!("buffered {}", track.name);
+                    *failures = 0;
                     self.player.tracks.write().await.push_back(track);
                 }
+                // Failures are only shown by the player if they actually hold up playback.
+                Err(error) if connectivity::unreachable(&error) => {
+                    warn!("unable to buffer a track: {:#}", error);
+                    self.player.connectivity.lose(url);
+                    failed = true;
+                }
+                Err(error) => {
+                    warn!("unable to buffer a track: {:#}", error);
+                    self.player.failures.record(&url);
+                    *failures += 1;
+                    failed = true;
+                }
             }
-        });
+        }
+    }
+
+    /// Starts as many new downloads as there's room for.
+    async fn fill(&self) {
+        let buffered = self.player.tracks.read().await.len();
+        let list = self.player.list.load_full();
+
+        let mut pending = self.player.pending();
+        let downloading = pending.iter().filter(|x| !x.is_finished()).count();
+        let room = self
+            .player
+            .prefetch
+            .saturating_sub(buffered + pending.len())
+            .min(self.player.concurrency().saturating_sub(downloading));
+
+        for _ in 0..room {
+            let entry = list.pick(self.player.selector.as_ref()).clone();
+            debug!("downloading {} in the background", entry.url);
+            pending.push_back(Pending::start(&self.player, entry, self.tx.clone()));
+        }
+    }
+
+    /// Actually starts & consumes the [Downloader].
+    ///
+    /// The downloader keeps going until the returned [Handle] is dropped.
+    pub fn start(mut self) -> Handle {
+        Handle(task::spawn(async move {
+            // How many downloads in a row have failed.
+            let mut failures = 0;
+
+            // Every notification is either the player making room in the buffer, or a download finishing.
+            while self.rx.recv().await == Some(()) {
+                // Backing off stops the downloader from spinning when tracks keep failing,
+                // and there's no point trying again at all until the network is back.
+                if self.collect(&mut failures).await {
+                    if self.player.connectivity.lost() {
+                        self.player.connectivity.wait().await;
+                    } else {
+                        sleep(download::backoff(failures)).await;
+                    }
+                }
+
+                self.fill().await;
+            }
+        }))
+    }
+}
+
+/// Stops the [Downloader] once it's dropped, which is when the audio server stops.
+///
+/// Otherwise, it'd keep on downloading until the runtime shuts down, which could
+/// be right in the middle of writing a track to the cache.
+pub struct Handle(JoinHandle<()>);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.0.abort();
     }
 }
@@ -0,0 +1,355 @@
+//! Parses keybinds from the config, and maps key presses to the [Action]s they trigger.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use eyre::eyre;
+use serde::Deserialize;
+
+use crate::config::Keybinds;
+
+/// The names of keys which aren't just a single character.
+const NAMES: [(&str, KeyCode); 15] = [
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+/// A single key, along with the modifiers that have to be held down with it,
+/// which is written like `s`, `ctrl+right` or `alt+shift+f1`.
+///
+/// Shift is never stored for characters, since it's already part of the
+/// character itself, so `shift+s` is the same as `S`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    /// The key itself.
+    code: KeyCode,
+
+    /// Ctrl, Alt and/or Shift.
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Creates a key, making sure that it's consistent with the ones from [Key::from_str].
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(character) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(character.to_ascii_uppercase())
+            }
+            code => code,
+        };
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers =
+            event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+        // Windows reports AltGr as Ctrl+Alt, which is needed to type characters
+        // like `{` or `]` on a lot of keyboard layouts.
+        let altgr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        if cfg!(windows) && matches!(event.code, KeyCode::Char(_)) && modifiers.contains(altgr) {
+            modifiers.remove(altgr);
+        }
+
+        Self::new(event.code, modifiers)
+    }
+}
+
+impl FromStr for Key {
+    type Err = eyre::Report;
+
+    fn from_str(text: &str) -> eyre::Result<Self> {
+        // `+` is also the separator, so it needs some special treatment.
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => match text.rsplit_once('+') {
+                Some((modifiers, key)) if !key.is_empty() => (modifiers, key),
+                _ => ("", text),
+            },
+        };
+
+        let mut parsed = KeyModifiers::NONE;
+        for modifier in modifiers.split('+').filter(|x| !x.is_empty()) {
+            parsed |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(eyre!("unknown modifier '{}' in '{}'", modifier, text)),
+            };
+        }
+
+        let mut characters = key.chars();
+        let code = match (characters.next(), characters.next()) {
+            (Some(character), None) => KeyCode::Char(character),
+            _ => {
+                let lowercase = key.to_lowercase();
+                let named = NAMES
+                    .iter()
+                    .find(|(name, _)| *name == lowercase)
+                    .map(|(_, code)| *code);
+
+                match named {
+                    Some(code) => code,
+                    None => lowercase
+                        .strip_prefix('f')
+                        .and_then(|x| x.parse().ok())
+                        .filter(|x| (1..=12).contains(x))
+                        .map(KeyCode::F)
+                        .ok_or_else(|| eyre!("unknown key '{}'", text))?,
+                }
+            }
+        };
+
+        Ok(Self::new(code, parsed))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl"),
+            (KeyModifiers::ALT, "alt"),
+            (KeyModifiers::SHIFT, "shift"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(character) => write!(f, "{}", character),
+            KeyCode::F(number) => write!(f, "f{}", number),
+            code => {
+                let name = NAMES.iter().find(|(_, x)| *x == code).map(|(name, _)| name);
+                write!(f, "{}", name.unwrap_or(&"?"))
+            }
+        }
+    }
+}
+
+/// All of the keys bound to a single action, where the first one is what's shown in the UI.
+///
+/// In the config, this is either a single key like `"s"`, or a list like `["s", "ctrl+right"]`.
+#[derive(Debug, Clone)]
+pub struct Binding(Vec<Key>);
+
+impl Binding {
+    /// Parses each key, panicking if any are invalid.
+    /// This is only meant for the defaults.
+    pub fn of(keys: &[&str]) -> Self {
+        Self(keys.iter().map(|x| x.parse().unwrap()).collect())
+    }
+
+    /// The key that's shown in the UI, if there are any.
+    pub fn first(&self) -> Option<&Key> {
+        self.0.first()
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", key)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// What a binding can look like in the config.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let keys = match Raw::deserialize(deserializer)? {
+            Raw::One(key) => vec![key],
+            Raw::Many(keys) => keys,
+        };
+
+        keys.iter()
+            .map(|x| x.parse().map_err(serde::de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Everything that a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Skip,
+    Pause,
+    Quit,
+    Favorite,
+    VolumeUp,
+    VolumeDown,
+    VolumeUpFine,
+    VolumeDownFine,
+    SeekForward,
+    SeekBackward,
+    Timer,
+    Previous,
+    Like,
+    Block,
+    Repeat,
+    Device,
+    History,
+    Stations,
+    Visualizer,
+    Queue,
+    SpeedUp,
+    SpeedDown,
+    BassUp,
+    BassDown,
+    TrebleUp,
+    TrebleDown,
+    SetVolume,
+    Mute,
+    Copy,
+    Open,
+    Download,
+    Ambience,
+    AmbienceSelect,
+    Help,
+}
+
+impl Action {
+    /// A short description of what the action does, for the help overlay.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Pause => "pause",
+            Self::Quit => "quit",
+            Self::Favorite => "favorite",
+            Self::VolumeUp => "volume up",
+            Self::VolumeDown => "volume down",
+            Self::VolumeUpFine => "volume up 1%",
+            Self::VolumeDownFine => "volume down 1%",
+            Self::SeekForward => "seek forward",
+            Self::SeekBackward => "seek back",
+            Self::Timer => "sleep timer",
+            Self::Previous => "previous",
+            Self::Like => "like",
+            Self::Block => "block",
+            Self::Repeat => "repeat",
+            Self::Device => "next device",
+            Self::History => "history",
+            Self::Stations => "stations",
+            Self::Visualizer => "visualizer",
+            Self::Queue => "queue",
+            Self::SpeedUp => "speed up",
+            Self::SpeedDown => "slow down",
+            Self::BassUp => "bass up",
+            Self::BassDown => "bass down",
+            Self::TrebleUp => "treble up",
+            Self::TrebleDown => "treble down",
+            Self::SetVolume => "set volume",
+            Self::Mute => "mute",
+            Self::Copy => "copy url",
+            Self::Open => "open url",
+            Self::Download => "download",
+            Self::Ambience => "ambience volume",
+            Self::AmbienceSelect => "next ambience",
+            Self::Help => "help",
+        }
+    }
+}
+
+/// Pairs up every binding in `keybinds` with its [Action], in the order of [Keybinds].
+fn bindings(keybinds: &Keybinds) -> [(&Binding, Action); 34] {
+    [
+        (&keybinds.skip, Action::Skip),
+        (&keybinds.pause, Action::Pause),
+        (&keybinds.quit, Action::Quit),
+        (&keybinds.favorite, Action::Favorite),
+        (&keybinds.volume_up, Action::VolumeUp),
+        (&keybinds.volume_down, Action::VolumeDown),
+        (&keybinds.volume_up_fine, Action::VolumeUpFine),
+        (&keybinds.volume_down_fine, Action::VolumeDownFine),
+        (&keybinds.seek_forward, Action::SeekForward),
+        (&keybinds.seek_backward, Action::SeekBackward),
+        (&keybinds.timer, Action::Timer),
+        (&keybinds.previous, Action::Previous),
+        (&keybinds.like, Action::Like),
+        (&keybinds.block, Action::Block),
+        (&keybinds.repeat, Action::Repeat),
+        (&keybinds.device, Action::Device),
+        (&keybinds.history, Action::History),
+        (&keybinds.stations, Action::Stations),
+        (&keybinds.visualizer, Action::Visualizer),
+        (&keybinds.queue, Action::Queue),
+        (&keybinds.speed_up, Action::SpeedUp),
+        (&keybinds.speed_down, Action::SpeedDown),
+        (&keybinds.bass_up, Action::BassUp),
+        (&keybinds.bass_down, Action::BassDown),
+        (&keybinds.treble_up, Action::TrebleUp),
+        (&keybinds.treble_down, Action::TrebleDown),
+        (&keybinds.set_volume, Action::SetVolume),
+        (&keybinds.mute, Action::Mute),
+        (&keybinds.copy, Action::Copy),
+        (&keybinds.open, Action::Open),
+        (&keybinds.download, Action::Download),
+        (&keybinds.ambience, Action::Ambience),
+        (&keybinds.ambience_select, Action::AmbienceSelect),
+        (&keybinds.help, Action::Help),
+    ]
+}
+
+/// Lists every action that has a key bound to it, as the keys & what the action does.
+pub fn help(keybinds: &Keybinds) -> Vec<(String, &'static str)> {
+    bindings(keybinds)
+        .into_iter()
+        .filter(|(binding, _)| !binding.0.is_empty())
+        .map(|(binding, action)| (binding.to_string(), action.describe()))
+        .collect()
+}
+
+/// Maps every bound key to its [Action].
+pub struct Keymap(HashMap<Key, Action>);
+
+impl Keymap {
+    /// Builds the keymap from the keybinds in the config.
+    ///
+    /// If a key is bound to more than one action, then the one that comes first in [Keybinds] wins.
+    pub fn new(keybinds: &Keybinds) -> Self {
+        let mut map = HashMap::new();
+        for (binding, action) in bindings(keybinds) {
+            for key in &binding.0 {
+                map.entry(*key).or_insert(action);
+            }
+        }
+
+        Self(map)
+    }
+
+    /// Gets the action that a key press triggers, if any.
+    pub fn get(&self, event: KeyEvent) -> Option<Action> {
+        self.0.get(&Key::from(event)).copied()
+    }
+}
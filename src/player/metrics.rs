@@ -0,0 +1,53 @@
+//! Counts what the [Player](super::Player) gets up to over a session, like how much it's downloaded,
+//! which the web remote serves for monitoring at `/metrics`.
+//!
+//! Everything starts from zero whenever lowfi does, since they're only kept in memory.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The counters, which are shared between the [Player](super::Player), its
+/// [Network](crate::tracks::download::Network) & any streams.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// How many bytes of tracks & streams have been downloaded.
+    downloaded: AtomicU64,
+
+    /// How many tracks have started playing.
+    played: AtomicU64,
+
+    /// How many times playback had to wait for something to download, see [Metrics::underrun].
+    underruns: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts `bytes` more as having been downloaded.
+    pub fn download(&self, bytes: u64) {
+        self.downloaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts another track as having started playing.
+    pub fn play(&self) {
+        self.played.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts another time that playback was held up, because the next track
+    /// wasn't buffered yet, or a stream ran dry.
+    pub fn underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How many bytes of tracks & streams have been downloaded.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded.load(Ordering::Relaxed)
+    }
+
+    /// How many tracks have started playing.
+    pub fn played(&self) -> u64 {
+        self.played.load(Ordering::Relaxed)
+    }
+
+    /// How many times playback has been held up.
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
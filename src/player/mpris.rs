@@ -0,0 +1,274 @@
+//! Contains the code for the MPRIS server & other helper functions.
+
+use std::{sync::Arc, time::Duration};
+
+use mpris_server::{
+    zbus::{fdo, Result},
+    LoopStatus, Metadata, PlaybackRate, PlaybackStatus, PlayerInterface, Property, RootInterface,
+    Time, TrackId, Volume,
+};
+use tokio::sync::mpsc::Sender;
+
+use super::Messages;
+use crate::audio::speed::{MAX_SPEED, MIN_SPEED};
+
+/// A small helper that checks whether the [Sender] succeeded,
+/// and converts the error into a [fdo::Error] if it didn't.
+async fn send(sender: &Sender<Messages>, message: Messages) -> fdo::Result<()> {
+    sender
+        .send(message)
+        .await
+        .map_err(|error| fdo::Error::Failed(error.to_string()))
+}
+
+/// The actual MPRIS player, which implements both the
+/// `org.mpris.MediaPlayer2` & `org.mpris.MediaPlayer2.Player` interfaces.
+pub struct Player {
+    /// The main lowfi [super::Player], which is used to read the current state.
+    pub player: Arc<super::Player>,
+
+    /// The sender, which is used to forward commands to the audio server.
+    sender: Sender<Messages>,
+}
+
+impl RootInterface for Player {
+    async fn raise(&self) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("Unsupported".into()))
+    }
+
+    async fn quit(&self) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("Unsupported".into()))
+    }
+
+    async fn can_quit(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn set_fullscreen(&self, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    async fn can_set_fullscreen(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn can_raise(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn has_track_list(&self) -> fdo::Result<bool> {
+        Ok(false)
+    }
+
+    async fn identity(&self) -> fdo::Result<String> {
+        Ok("lowfi".to_owned())
+    }
+
+    async fn desktop_entry(&self) -> fdo::Result<String> {
+        Ok("lowfi".to_owned())
+    }
+
+    async fn supported_uri_schemes(&self) -> fdo::Result<Vec<String>> {
+        Ok(vec!["https".to_owned()])
+    }
+
+    async fn supported_mime_types(&self) -> fdo::Result<Vec<String>> {
+        Ok(vec![
+            "audio/mpeg".to_owned(),
+            "audio/ogg".to_owned(),
+            #[cfg(feature = "flac")]
+            "audio/flac".to_owned(),
+            #[cfg(feature = "opus")]
+            "audio/opus".to_owned(),
+        ])
+    }
+}
+
+impl PlayerInterface for Player {
+    async fn next(&self) -> fdo::Result<()> {
+        send(&self.sender, Messages::Next).await
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        send(&self.sender, Messages::Previous).await
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        send(&self.sender, Messages::Pause).await
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        send(&self.sender, Messages::PlayPauseToggle).await
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        self.pause().await
+    }
+
+    async fn play(&self) -> fdo::Result<()> {
+        send(&self.sender, Messages::Resume).await
+    }
+
+    async fn seek(&self, offset: Time) -> fdo::Result<()> {
+        send(&self.sender, Messages::Seek(offset.as_secs())).await
+    }
+
+    async fn set_position(&self, _: TrackId, position: Time) -> fdo::Result<()> {
+        let position = Duration::from_micros(position.as_micros().max(0) as u64);
+        send(&self.sender, Messages::SeekTo(position)).await
+    }
+
+    async fn open_uri(&self, _: String) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported("Unsupported".into()))
+    }
+
+    async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
+        Ok(self.status())
+    }
+
+    async fn loop_status(&self) -> fdo::Result<LoopStatus> {
+        Err(fdo::Error::NotSupported("Unsupported".into()))
+    }
+
+    async fn set_loop_status(&self, _: LoopStatus) -> Result<()> {
+        Ok(())
+    }
+
+    async fn rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(self.player.speed.factor().into())
+    }
+
+    async fn set_rate(&self, rate: PlaybackRate) -> Result<()> {
+        let change = rate as f32 - self.player.speed.factor();
+        send(&self.sender, Messages::ChangeSpeed(change)).await?;
+
+        Ok(())
+    }
+
+    async fn shuffle(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn set_shuffle(&self, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    async fn metadata(&self) -> fdo::Result<Metadata> {
+        Ok(self.metadata())
+    }
+
+    async fn volume(&self) -> fdo::Result<Volume> {
+        Ok(self.player.sink.volume().into())
+    }
+
+    async fn set_volume(&self, volume: Volume) -> Result<()> {
+        self.sender
+            .send(Messages::SetVolume(volume as f32))
+            .await
+            .map_err(|error| fdo::Error::Failed(error.to_string()).into())
+    }
+
+    async fn position(&self) -> fdo::Result<Time> {
+        Ok(Time::from_micros(self.player.elapsed().as_micros() as i64))
+    }
+
+    async fn minimum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(MIN_SPEED.into())
+    }
+
+    async fn maximum_rate(&self) -> fdo::Result<PlaybackRate> {
+        Ok(MAX_SPEED.into())
+    }
+
+    async fn can_go_next(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_go_previous(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_play(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_pause(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_seek(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+
+    async fn can_control(&self) -> fdo::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl Player {
+    /// Gets the current [PlaybackStatus], which is [PlaybackStatus::Stopped]
+    /// while lowfi is loading a track.
+    fn status(&self) -> PlaybackStatus {
+        if self.player.current.load().is_none() {
+            PlaybackStatus::Stopped
+        } else if self.player.sink.is_paused() {
+            PlaybackStatus::Paused
+        } else {
+            PlaybackStatus::Playing
+        }
+    }
+
+    /// Builds the [Metadata] of the current track, which
+    /// is empty if there isn't one.
+    fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+
+        if let Some(current) = self.player.current.load().as_ref() {
+            metadata.set_title(Some(current.name.clone()));
+            metadata.set_artist(current.artist.clone().map(|x| vec![x]));
+            metadata.set_album(current.album.clone());
+            metadata.set_length(
+                current
+                    .duration
+                    .map(|x| Time::from_micros(x.as_micros() as i64)),
+            );
+        }
+
+        metadata
+    }
+}
+
+/// A wrapper around the [mpris_server::Server], which
+/// also has some helpers to notify clients of changes.
+pub struct Server {
+    /// The inner MPRIS server.
+    inner: mpris_server::Server<Player>,
+}
+
+impl Server {
+    /// Creates & registers the MPRIS server on the session bus.
+    pub async fn new(player: Arc<super::Player>, sender: Sender<Messages>) -> eyre::Result<Self> {
+        let inner = mpris_server::Server::new("lowfi", Player { player, sender }).await?;
+
+        Ok(Self { inner })
+    }
+
+    /// Notifies clients that the playback status, metadata & volume may have changed.
+    pub async fn changed(&self) -> eyre::Result<()> {
+        let imp = self.inner.imp();
+
+        self.inner
+            .properties_changed([
+                Property::PlaybackStatus(imp.status()),
+                Property::Metadata(imp.metadata()),
+                Property::Volume(imp.player.sink.volume().into()),
+            ])
+            .await?;
+
+        Ok(())
+    }
+}
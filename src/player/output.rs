@@ -0,0 +1,435 @@
+//! Responsible for the audio output, which can be moved to another device while lowfi is running.
+//!
+//! Normally, a [Sink] is tied to the device it's created on. To get around that,
+//! the sink is created without a device, and its output is relayed to whichever
+//! device is open at the moment. That way, switching devices doesn't stop the
+//! current track, or lose its position.
+//!
+//! Other sinks, like the ones for [ambience](super::ambience), can be added as layers
+//! with [Output::layer], which are relayed to the same device & mixed in with the music.
+//!
+//! This also means that if a device disappears, like when headphones are unplugged,
+//! playback can just be moved over to the default device, see [watch].
+//! The same goes for when the default device changes while lowfi is playing on it.
+//!
+//! With the `jack` feature, lowfi can also play through JACK, see [Backend].
+//! JACK doesn't have devices, so instead there's just the one, which is a client
+//! called `lowfi` that's connected up to the system's outputs.
+//!
+//! Whichever backend is used, the stream is labelled as lowfi playing music, so that
+//! mixers like pavucontrol don't just show it as "ALSA plug-in", see [label].
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use cpal::{traits::HostTrait, Device};
+use eyre::{bail, eyre};
+use rodio::{
+    queue::SourcesQueueOutput, DeviceTrait, OutputStream, OutputStreamHandle, Sink, Source,
+};
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use super::{Messages, Player};
+use crate::config::Backend;
+
+/// How often to check whether the device is still playing.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The queue of everything that the [Sink] plays, shared between all of the [Relay]s.
+type Queue = Arc<Mutex<SourcesQueueOutput<f32>>>;
+
+/// The name that the stream is shown under in mixers.
+#[cfg(any(target_os = "linux", feature = "jack"))]
+const NAME: &str = "lowfi";
+
+/// The freedesktop icon that mixers show next to the stream.
+#[cfg(target_os = "linux")]
+const ICON: &str = "multimedia-audio-player";
+
+/// The name of the JACK client, which is also the only device on JACK.
+#[cfg(feature = "jack")]
+const CLIENT: &str = NAME;
+
+/// Sets the name, icon & role of the streams that lowfi opens, for the sound server to show.
+///
+/// There's no way to pass these through cpal, but both PulseAudio & PipeWire read extra
+/// properties from the environment when a stream is made, which also covers going through
+/// their ALSA plugins & pipewire-jack. Anything that's already been set by the user is
+/// left alone. Elsewhere, the stream is already shown under the name of the process.
+///
+/// Since this changes the environment, it has to be called before any other threads
+/// are started, like the tokio runtime's, which is why lowfi does it first thing in `main`.
+/// It also has to be before the first [Output] is made, which is when the sound server is
+/// connected to.
+pub fn label() {
+    #[cfg(target_os = "linux")]
+    {
+        let pulse = format!(
+            "application.name={} application.icon_name={} media.role=music",
+            NAME, ICON
+        );
+        let pipewire = format!(
+            "{{ application.name = {} application.icon_name = {} node.description = {} media.role = Music }}",
+            NAME, ICON, NAME
+        );
+
+        for (key, value) in [("PULSE_PROP", pulse), ("PIPEWIRE_PROPS", pipewire)] {
+            if std::env::var_os(key).is_none() {
+                std::env::set_var(key, value);
+            }
+        }
+    }
+}
+
+/// Gets the names of all of the output devices.
+pub fn devices(backend: Backend) -> eyre::Result<Vec<String>> {
+    match backend {
+        Backend::Default => Ok(cpal::default_host()
+            .output_devices()?
+            .filter_map(|device| device.name().ok())
+            .collect()),
+        #[cfg(feature = "jack")]
+        Backend::Jack => Ok(vec![CLIENT.to_owned()]),
+    }
+}
+
+/// Gets the name of the default output device, if there is one.
+pub fn default_device(backend: Backend) -> Option<String> {
+    match backend {
+        Backend::Default => cpal::default_host().default_output_device()?.name().ok(),
+        #[cfg(feature = "jack")]
+        Backend::Jack => Some(CLIENT.to_owned()),
+    }
+}
+
+/// Finds a device by its name, or the default device if it's [None], returning it along with its name.
+fn find(backend: Backend, name: Option<&str>) -> eyre::Result<(Device, String)> {
+    let unknown = |name: &str| -> eyre::Result<(Device, String)> {
+        bail!(
+            "unknown device '{}', the devices are: {}",
+            name,
+            devices(backend)?.join(", ")
+        )
+    };
+
+    match backend {
+        Backend::Default => {
+            let host = cpal::default_host();
+            let device = match name {
+                Some(name) => match host
+                    .output_devices()?
+                    .find(|x| x.name().is_ok_and(|x| x == name))
+                {
+                    Some(device) => device,
+                    None => return unknown(name),
+                },
+                None => host
+                    .default_output_device()
+                    .ok_or_else(|| eyre!("there isn't an audio output device"))?,
+            };
+
+            let name = device.name()?;
+            Ok((device, name))
+        }
+        #[cfg(feature = "jack")]
+        Backend::Jack => {
+            if let Some(name) = name.filter(|x| *x != CLIENT) {
+                return unknown(name);
+            }
+
+            // This doesn't start a JACK server, since one that's started for lowfi would go away with it.
+            let device = cpal::platform::JackDevice::default_output_device(CLIENT, true, false)
+                .map_err(|error| eyre!("unable to connect to JACK, {}", error))?;
+            Ok((device.into(), CLIENT.to_owned()))
+        }
+    }
+}
+
+/// The stream of a device, which stops playing as soon as it's dropped.
+struct Stream {
+    /// The stream itself.
+    _stream: OutputStream,
+
+    /// What new layers are played through.
+    handle: OutputStreamHandle,
+
+    /// The name of the device.
+    name: String,
+}
+
+/// The audio output, which keeps the stream of the current device alive.
+pub struct Output {
+    /// Everything that the [Sink] plays.
+    queue: Queue,
+
+    /// The queues of the other sinks which are mixed in with the main one, see [Output::layer].
+    layers: Mutex<Vec<Queue>>,
+
+    /// Which [Relay] is allowed to play, so that the old one stops straight away when switching.
+    generation: Arc<AtomicUsize>,
+
+    /// How many samples have been played in total, which stops going up if the device is gone.
+    played: Arc<AtomicU64>,
+
+    /// What `played` was the last time [Output::alive] was checked.
+    checked: AtomicU64,
+
+    /// Whether the output is on the default device because no other device was chosen,
+    /// in which case it follows the default device when that changes.
+    following: AtomicBool,
+
+    /// The audio system that the devices are on.
+    backend: Backend,
+
+    /// The stream of the current device.
+    stream: Mutex<Stream>,
+}
+
+impl Output {
+    /// Creates a [Sink] & opens the output on `device` through `backend`,
+    /// or on the default device if it's [None].
+    pub fn new(device: Option<&str>, backend: Backend) -> eyre::Result<(Self, Sink)> {
+        let (sink, queue) = Sink::new_idle();
+        let queue = Arc::new(Mutex::new(queue));
+        let generation = Arc::new(AtomicUsize::new(0));
+        let played = Arc::new(AtomicU64::new(0));
+
+        let stream = Self::open(backend, device, &queue, &[], &generation, &played, 0)?;
+        let output = Self {
+            queue,
+            layers: Mutex::new(Vec::new()),
+            generation,
+            played,
+            checked: AtomicU64::new(0),
+            following: AtomicBool::new(device.is_none()),
+            backend,
+            stream: Mutex::new(stream),
+        };
+
+        Ok((output, sink))
+    }
+
+    /// Opens a stream on a device, and starts relaying the queue & the layers to it.
+    fn open(
+        backend: Backend,
+        device: Option<&str>,
+        queue: &Queue,
+        layers: &[Queue],
+        current: &Arc<AtomicUsize>,
+        played: &Arc<AtomicU64>,
+        generation: usize,
+    ) -> eyre::Result<Stream> {
+        let (device, name) = find(backend, device)?;
+        let (stream, handle) = OutputStream::try_from_device(&device)?;
+        handle.play_raw(Relay {
+            queue: Arc::clone(queue),
+            current: Arc::clone(current),
+            played: Some(Arc::clone(played)),
+            generation,
+        })?;
+
+        for layer in layers {
+            handle.play_raw(Relay {
+                queue: Arc::clone(layer),
+                current: Arc::clone(current),
+                played: None,
+                generation,
+            })?;
+        }
+
+        Ok(Stream {
+            _stream: stream,
+            handle,
+            name,
+        })
+    }
+
+    /// Creates another [Sink], which plays on the same device as the main one,
+    /// alongside it, and follows it when it moves to another device.
+    pub fn layer(&self) -> eyre::Result<Sink> {
+        let (sink, queue) = Sink::new_idle();
+        let queue = Arc::new(Mutex::new(queue));
+
+        // The stream is locked first, so that it can't be switched halfway through this.
+        let stream = self.stream.lock().unwrap_or_else(|x| x.into_inner());
+        stream.handle.play_raw(Relay {
+            queue: Arc::clone(&queue),
+            current: Arc::clone(&self.generation),
+            played: None,
+            generation: self.generation.load(Ordering::Relaxed),
+        })?;
+
+        self.layers
+            .lock()
+            .unwrap_or_else(|x| x.into_inner())
+            .push(queue);
+
+        Ok(sink)
+    }
+
+    /// Moves the output over to another device, or the default device if it's [None].
+    ///
+    /// If this fails, then the output stays on the current device.
+    pub fn switch(&self, device: Option<&str>) -> eyre::Result<()> {
+        let mut current = self.stream.lock().unwrap_or_else(|x| x.into_inner());
+
+        let generation = self.generation.load(Ordering::Relaxed) + 1;
+        let layers = self.layers.lock().unwrap_or_else(|x| x.into_inner());
+        let stream = Self::open(
+            self.backend,
+            device,
+            &self.queue,
+            &layers,
+            &self.generation,
+            &self.played,
+            generation,
+        )?;
+        self.generation.store(generation, Ordering::Relaxed);
+
+        // This drops the old stream, which closes the old device.
+        *current = stream;
+
+        // The new device gets a moment to start up before it's checked.
+        self.checked
+            .store(self.played.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.following.store(device.is_none(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Gets the name of the device that's being played on.
+    pub fn device(&self) -> String {
+        let current = self.stream.lock().unwrap_or_else(|x| x.into_inner());
+        current.name.clone()
+    }
+
+    /// Whether the device has played anything since the last time this was called.
+    ///
+    /// Devices keep playing silence even while paused, so this is only false if the device stopped working.
+    fn alive(&self) -> bool {
+        let played = self.played.load(Ordering::Relaxed);
+        self.checked.swap(played, Ordering::Relaxed) != played
+    }
+
+    /// Moves the output over to the device after the current one,
+    /// returning the name of the new device.
+    pub fn cycle(&self) -> eyre::Result<String> {
+        let devices = devices(self.backend)?;
+        let current = self.device();
+
+        let index = devices.iter().position(|x| *x == current);
+        let next = index.map_or(0, |x| (x + 1) % devices.len().max(1));
+        let Some(next) = devices.get(next) else {
+            bail!("there aren't any other devices");
+        };
+
+        self.switch(Some(next))?;
+        Ok(next.clone())
+    }
+}
+
+/// Plays the shared [Queue] on a single device, until a newer one takes over.
+struct Relay {
+    /// The queue being played.
+    queue: Queue,
+
+    /// The newest generation, see [Output::generation].
+    current: Arc<AtomicUsize>,
+
+    /// The amount of samples played, see [Output::played], which only the main relay counts.
+    played: Option<Arc<AtomicU64>>,
+
+    /// The generation of this relay, which stops once there's a newer one.
+    generation: usize,
+}
+
+impl Relay {
+    /// Runs `f` on the queue.
+    fn with<T>(&self, f: impl FnOnce(&mut SourcesQueueOutput<f32>) -> T) -> T {
+        f(&mut self.queue.lock().unwrap_or_else(|x| x.into_inner()))
+    }
+}
+
+impl Iterator for Relay {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // Ending the source makes the old device's mixer drop it.
+        if self.generation < self.current.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        if let Some(played) = &self.played {
+            played.fetch_add(1, Ordering::Relaxed);
+        }
+        self.with(Iterator::next)
+    }
+}
+
+impl Source for Relay {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.with(|x| x.current_frame_len())
+    }
+
+    fn channels(&self) -> u16 {
+        self.with(|x| x.channels())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.with(|x| x.sample_rate())
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Keeps an eye on the device, moving playback over to the default device if it stops working,
+/// or if the default device changes while it's being played on.
+///
+/// Since the [Sink] is left alone, the current track just picks up where it left off.
+/// However, if `pause` is true and the old device is gone, like with unplugged headphones,
+/// then playback is paused so that it doesn't suddenly come out of the speakers.
+pub async fn watch(player: Arc<Player>, tx: Sender<Messages>, pause: bool) {
+    let backend = player.output.backend;
+    let mut default = default_device(backend);
+
+    loop {
+        sleep(CHECK_INTERVAL).await;
+
+        let alive = player.output.alive();
+
+        // Plugging in headphones also changes the default, in which case they're switched to.
+        let previous = std::mem::replace(&mut default, default_device(backend));
+        let changed = player.output.following.load(Ordering::Relaxed)
+            && default.is_some()
+            && default != previous;
+
+        if alive && !changed {
+            continue;
+        }
+
+        let old = player.output.device();
+        let gone = !alive || !devices(backend).is_ok_and(|x| x.contains(&old));
+
+        if let Err(error) = player.output.switch(None) {
+            player.report(format!("lost {}, {}", old, error));
+            continue;
+        }
+
+        let new = player.output.device();
+        if gone && pause && !player.sink.is_paused() {
+            player.notify(format!("lost {}, paused on {}", old, new), false);
+            let _ = tx.send(Messages::Pause).await;
+        } else if gone {
+            player.notify(format!("lost {}, playing on {}", old, new), false);
+        } else {
+            player.notify(format!("playing on {}", new), false);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Pomodoro mode, which alternates between focusing & taking a break, like with `--pomodoro 25/5`.
+//!
+//! A chime plays whenever it switches, and depending on [Break], the music can
+//! also be paused for breaks, or swapped out for the [ambience](super::ambience).
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rodio::{source::SineWave, Sink, Source};
+use serde::Deserialize;
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use super::{timer, Messages, Player};
+
+/// How loud the chime is compared to the music.
+const CHIME_VOLUME: f32 = 0.15;
+
+/// The notes of the chime that plays when it's time to focus, which goes up.
+/// It's played backwards for breaks, so that it goes down instead.
+const CHIME: [(f32, u64); 2] = [(523.25, 250), (783.99, 600)];
+
+/// Which part of the cycle it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Time to get things done.
+    Focus,
+
+    /// Time to take a break.
+    Break,
+}
+
+impl Phase {
+    /// The name of the phase, like `focus`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Focus => "focus",
+            Self::Break => "break",
+        }
+    }
+}
+
+/// What happens to the music during breaks.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Break {
+    /// The music keeps playing, and only the chime marks the break.
+    #[default]
+    Continue,
+
+    /// The music pauses until it's time to focus again.
+    Pause,
+
+    /// The music pauses, but the ambient sounds keep going.
+    Ambience,
+}
+
+/// How long each part of the cycle lasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intervals {
+    /// How long to focus for.
+    pub focus: Duration,
+
+    /// How long each break is.
+    pub rest: Duration,
+}
+
+/// Parses intervals like `25/5`, where each side is a duration like `--timer` takes,
+/// so a plain number is in minutes.
+pub fn parse(text: &str) -> Result<Intervals, String> {
+    let (focus, rest) = text
+        .split_once('/')
+        .ok_or_else(|| format!("'{}' should be focus & break lengths, like 25/5", text))?;
+
+    Ok(Intervals {
+        focus: timer::parse(focus.trim())?,
+        rest: timer::parse(rest.trim())?,
+    })
+}
+
+/// The pomodoro cycle, which is worked out from when it started, so that it never drifts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pomodoro {
+    /// How long each part of the cycle lasts.
+    pub intervals: Intervals,
+
+    /// When the first focus started.
+    pub start: Instant,
+}
+
+impl Pomodoro {
+    /// Starts focusing from now.
+    pub fn new(intervals: Intervals) -> Self {
+        Self {
+            intervals,
+            start: Instant::now(),
+        }
+    }
+
+    /// Gets the current phase, along with how long is left of it.
+    pub fn phase(&self) -> (Phase, Duration) {
+        let Intervals { focus, rest } = self.intervals;
+        let cycle = (focus + rest).as_millis().max(1);
+        let into = Duration::from_millis((self.start.elapsed().as_millis() % cycle) as u64);
+
+        if into < focus {
+            (Phase::Focus, focus - into)
+        } else {
+            (Phase::Break, focus + rest - into)
+        }
+    }
+}
+
+/// Plays the chime for the start of a phase on `sink`, relative to the music's `volume`,
+/// so that it's silent while muted.
+pub fn chime(sink: &Sink, phase: Phase, volume: f32) {
+    sink.set_volume(volume * CHIME_VOLUME);
+
+    let mut notes = CHIME.to_vec();
+    if phase == Phase::Break {
+        notes.reverse();
+    }
+
+    for (frequency, length) in notes {
+        let mut note = SineWave::new(frequency).take_duration(Duration::from_millis(length));
+        note.set_filter_fadeout();
+        sink.append(note.fade_in(Duration::from_millis(20)));
+    }
+}
+
+/// Keeps an eye on the cycle, and lets the player know whenever the phase changes.
+pub async fn watch(player: Arc<Player>, sender: Sender<Messages>) {
+    let Some(pomodoro) = player.pomodoro else {
+        return;
+    };
+
+    let mut last = pomodoro.phase().0;
+    loop {
+        sleep(Duration::from_secs(1)).await;
+
+        let (phase, _) = pomodoro.phase();
+        if phase == last {
+            continue;
+        }
+
+        last = phase;
+        if sender.send(Messages::Pomodoro(phase)).await.is_err() {
+            break;
+        }
+    }
+}
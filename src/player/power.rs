@@ -0,0 +1,202 @@
+//! Saves power while a laptop is running low on its battery, by lowering the frame rate,
+//! pausing the visualizer & downloading fewer tracks at once, see [crate::config::Power].
+//!
+//! How the battery is read depends on the platform. On Linux it's `/sys/class/power_supply`,
+//! on macOS it's `pmset`, and on Windows it's `GetSystemPowerStatus`. Anywhere else,
+//! or on computers without a battery at all, lowfi just never saves power.
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use tokio::{task, time::sleep};
+
+use super::Player;
+
+/// How often the battery is checked, since it doesn't drain all that quickly.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the battery is up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// Whether the computer is running on its battery, rather than being plugged in.
+    pub on_battery: bool,
+
+    /// How charged the battery is as a percentage, if that's known.
+    pub charge: Option<u8>,
+}
+
+impl Status {
+    /// Whether the battery is being used & has gone below `threshold` percent.
+    pub fn low(&self, threshold: u8) -> bool {
+        self.on_battery && self.charge.is_some_and(|x| x < threshold)
+    }
+}
+
+/// Reads the battery, which is [None] if there isn't one or it can't be read.
+///
+/// This blocks, so it shouldn't be called directly from async code.
+pub fn status() -> Option<Status> {
+    backend::status()
+}
+
+/// Checks the battery every minute, turning [Player::saving] on while it's
+/// below `threshold` percent, & showing a message whenever that changes.
+///
+/// If there's no battery to begin with, this gives up straight away.
+pub async fn watch(player: Arc<Player>, threshold: u8) {
+    let mut first = true;
+
+    loop {
+        let status = task::spawn_blocking(status).await.ok().flatten();
+        if first && status.is_none() {
+            return;
+        }
+        first = false;
+
+        let saving = status.is_some_and(|x| x.low(threshold));
+        if player.saving.swap(saving, Ordering::Relaxed) != saving {
+            if saving {
+                player.notify("battery low, saving power".to_owned(), false);
+            } else {
+                player.notify("no longer saving power".to_owned(), false);
+            }
+        }
+
+        sleep(CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod backend {
+    use std::{fs, path::Path};
+
+    use super::Status;
+
+    /// Where the kernel lists the batteries & chargers.
+    const SUPPLIES: &str = "/sys/class/power_supply";
+
+    /// Reads one of the files that describe a power supply, without any trailing newline.
+    fn read(supply: &Path, name: &str) -> Option<String> {
+        fs::read_to_string(supply.join(name))
+            .ok()
+            .map(|x| x.trim().to_owned())
+    }
+
+    pub fn status() -> Option<Status> {
+        let mut charges: Vec<u32> = Vec::new();
+        let mut discharging = false;
+        let mut plugged = false;
+
+        for supply in fs::read_dir(SUPPLIES).ok()?.flatten() {
+            let supply = supply.path();
+            match read(&supply, "type").as_deref() {
+                Some("Mains") => plugged |= read(&supply, "online").as_deref() == Some("1"),
+                // Wireless mice & the like also show up as batteries, but with a `Device` scope.
+                Some("Battery") if read(&supply, "scope").as_deref() != Some("Device") => {
+                    discharging |= read(&supply, "status").as_deref() == Some("Discharging");
+                    if let Some(charge) = read(&supply, "capacity").and_then(|x| x.parse().ok()) {
+                        charges.push(charge);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if charges.is_empty() && !discharging {
+            return None;
+        }
+
+        // Laptops with more than one battery drain them one after the other,
+        // so it's the charge across all of them that matters, rather than any single one.
+        let charge = (!charges.is_empty())
+            .then(|| charges.iter().sum::<u32>() / charges.len() as u32)
+            .map(|x| x.min(100) as u8);
+
+        Some(Status {
+            on_battery: discharging && !plugged,
+            charge,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod backend {
+    use std::process::{Command, Stdio};
+
+    use super::Status;
+
+    /// Parses the output of `pmset -g batt`, which looks like this:
+    ///
+    /// ```text
+    /// Now drawing from 'Battery Power'
+    ///  -InternalBattery-0 (id=1234567)	85%; discharging; 3:12 remaining present: true
+    /// ```
+    fn parse(output: &str) -> Option<Status> {
+        let battery = output.lines().find(|x| x.contains("InternalBattery"))?;
+        let charge = battery
+            .split_whitespace()
+            .find_map(|x| x.strip_suffix("%;")?.parse().ok());
+
+        Some(Status {
+            on_battery: output.contains("'Battery Power'"),
+            charge,
+        })
+    }
+
+    pub fn status() -> Option<Status> {
+        let output = Command::new("pmset")
+            .args(["-g", "batt"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+#[cfg(windows)]
+mod backend {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    use super::Status;
+
+    /// The battery flag for when the computer doesn't have a battery.
+    const NO_BATTERY: u8 = 128;
+
+    /// What the charge & AC line status are set to when they aren't known.
+    const UNKNOWN: u8 = 255;
+
+    pub fn status() -> Option<Status> {
+        // SAFETY: The status is plain data, and is only read if the call succeeds.
+        let status = unsafe {
+            let mut status: SYSTEM_POWER_STATUS = std::mem::zeroed();
+            if GetSystemPowerStatus(&mut status) == 0 {
+                return None;
+            }
+
+            status
+        };
+
+        if status.BatteryFlag == NO_BATTERY || status.BatteryFlag == UNKNOWN {
+            return None;
+        }
+
+        Some(Status {
+            on_battery: status.ACLineStatus == 0,
+            charge: (status.BatteryLifePercent != UNKNOWN)
+                .then_some(status.BatteryLifePercent.min(100)),
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod backend {
+    use super::Status;
+
+    pub fn status() -> Option<Status> {
+        None
+    }
+}
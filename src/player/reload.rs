@@ -0,0 +1,43 @@
+//! Reloads a custom track list whenever its file changes, so that it can be edited while lowfi is running.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use tokio::{fs, sync::mpsc::Sender, time::sleep};
+use tracing::warn;
+
+use crate::tracks::list::List;
+
+use super::{Messages, Player};
+
+/// How often the file is checked for changes.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the track list at `path`, and sends it to the player again whenever it's modified.
+///
+/// A list that can't be loaded, like one with a typo in it, is reported and the old one is kept.
+pub async fn watch(player: Arc<Player>, sender: Sender<Messages>, path: PathBuf) {
+    let modified = || async { fs::metadata(&path).await.and_then(|x| x.modified()).ok() };
+    let mut last = modified().await;
+
+    loop {
+        sleep(INTERVAL).await;
+
+        let current = modified().await;
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        match List::load(&path) {
+            Ok(list) => {
+                if sender.send(Messages::ReloadList(list)).await.is_err() {
+                    break;
+                }
+            }
+            Err(error) => {
+                warn!("unable to reload the track list: {:#}", error);
+                player.report(format!("unable to reload the track list: {:#}", error));
+            }
+        }
+    }
+}
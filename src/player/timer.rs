@@ -0,0 +1,119 @@
+//! Contains the [`Timer`] struct, which is lowfi's sleep timer.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use super::{Messages, Player};
+
+/// The lengths that the timer keybind cycles through, after which it's turned off.
+const PRESETS: [Duration; 3] = [
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(30 * 60),
+    Duration::from_secs(60 * 60),
+];
+
+/// A sleep timer, which stops lowfi once it runs out.
+#[derive(Debug, Clone, Copy)]
+pub struct Timer {
+    /// How long the timer was originally set to.
+    pub length: Duration,
+
+    /// When the timer runs out.
+    pub end: Instant,
+}
+
+impl Timer {
+    /// Starts a new timer from now.
+    pub fn new(length: Duration) -> Self {
+        Self {
+            length,
+            end: Instant::now() + length,
+        }
+    }
+
+    /// How much time is left on the timer.
+    pub fn remaining(&self) -> Duration {
+        self.end.saturating_duration_since(Instant::now())
+    }
+
+    /// Gets the timer that comes after `current` when cycling through the presets,
+    /// which is [None] once it's gone past the longest one.
+    pub fn cycle(current: Option<&Self>) -> Option<Self> {
+        let next = match current {
+            Some(current) => PRESETS.into_iter().find(|x| *x > current.length)?,
+            None => PRESETS[0],
+        };
+
+        Some(Self::new(next))
+    }
+}
+
+/// Parses a duration like `45m`, `1h30m` or `90s`.
+/// A plain number without any unit is treated as minutes.
+pub fn parse(text: &str) -> Result<Duration, String> {
+    if let Ok(minutes) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(minutes * 60));
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+
+    for character in text.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+
+        let unit = match character {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("unknown unit '{}'", character)),
+        };
+
+        let amount: u64 = number
+            .parse()
+            .map_err(|_| format!("missing number before '{}'", character))?;
+        total += amount * unit;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(format!("invalid duration '{}'", text));
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Watches the player's timer, and quits once it runs out.
+///
+/// If `pause` is true, then this just pauses playback instead of quitting.
+pub async fn watch(player: Arc<Player>, sender: Sender<Messages>, pause: bool) {
+    loop {
+        sleep(Duration::from_secs(1)).await;
+
+        let expired = player
+            .timer
+            .load()
+            .as_ref()
+            .is_some_and(|x| x.remaining().is_zero());
+
+        if expired {
+            player.timer.store(None);
+
+            let message = if pause {
+                Messages::Pause
+            } else {
+                Messages::Quit
+            };
+
+            if sender.send(message).await.is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! An optional Discord Rich Presence integration, which shows what's playing on your profile.
+//!
+//! Just like [crate::scrobble], it's entirely driven by polling the [Player],
+//! and if Discord isn't running then it'll quietly keep trying to connect.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use discord_rich_presence::{
+    activity::{Activity, ActivityType, Timestamps},
+    DiscordIpc, DiscordIpcClient,
+};
+use tokio::{task, time::sleep};
+use tracing::{debug, warn};
+
+use lowfi::{player::Player, tracks::TrackInfo};
+
+/// How often the presence checks up on the player.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait before trying to connect to Discord again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How far off the shown elapsed time can be before it's corrected, which happens after seeking.
+const TOLERANCE: i64 = 2000;
+
+/// What's currently being shown on Discord.
+struct Shown {
+    /// The track that's playing.
+    track: Arc<TrackInfo>,
+
+    /// The UNIX timestamp in milliseconds of when the track would have started,
+    /// or [None] if it's paused.
+    started: Option<i64>,
+
+    /// The playback speed, which changes how long the track takes to finish.
+    speed: f32,
+}
+
+/// Gets the current UNIX timestamp in milliseconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis() as i64)
+}
+
+/// Sends the activity for `shown` to Discord.
+fn update(client: &mut DiscordIpcClient, shown: &Shown) -> eyre::Result<()> {
+    let mut activity = Activity::new()
+        .activity_type(ActivityType::Listening)
+        .details(&shown.track.name);
+
+    activity = match shown.started {
+        Some(started) => {
+            let mut timestamps = Timestamps::new().start(started);
+            if let Some(duration) = shown.track.duration {
+                let length = duration.as_millis() as f32 / shown.speed;
+                timestamps = timestamps.end(started + length as i64);
+            }
+
+            activity.state("lowfi").timestamps(timestamps)
+        }
+        None => activity.state("paused"),
+    };
+
+    task::block_in_place(|| client.set_activity(activity))?;
+    Ok(())
+}
+
+/// Starts publishing the current track to Discord in the background.
+///
+/// `client_id` is the ID of the Discord application that the presence is shown under.
+pub fn start(player: Arc<Player>, client_id: String) {
+    task::spawn(async move {
+        let mut client = DiscordIpcClient::new(client_id);
+        let mut connected = false;
+        let mut shown: Option<Shown> = None;
+
+        loop {
+            if !connected {
+                connected = match task::block_in_place(|| client.connect()) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        debug!("unable to connect to discord: {}", error);
+                        false
+                    }
+                };
+
+                if !connected {
+                    sleep(RECONNECT_INTERVAL).await;
+                    continue;
+                }
+
+                // Anything shown before was lost along with the connection.
+                shown = None;
+            }
+
+            let speed = player.speed.factor();
+            let current = player.current.load_full().map(|track| Shown {
+                started: (!player.sink.is_paused()).then(|| {
+                    let elapsed = player.elapsed().as_millis() as f32 / speed;
+                    now() - elapsed as i64
+                }),
+                track,
+                speed,
+            });
+
+            let changed = match (&current, &shown) {
+                (Some(current), Some(shown)) => {
+                    !Arc::ptr_eq(&current.track, &shown.track)
+                        || current.speed != shown.speed
+                        || match (current.started, shown.started) {
+                            (Some(a), Some(b)) => (a - b).abs() > TOLERANCE,
+                            (a, b) => a.is_some() != b.is_some(),
+                        }
+                }
+                (None, None) => false,
+                _ => true,
+            };
+
+            if changed {
+                let result = match &current {
+                    Some(current) => update(&mut client, current),
+                    None => task::block_in_place(|| client.clear_activity()).map_err(Into::into),
+                };
+
+                if let Err(error) = result {
+                    warn!("lost the connection to discord: {:#}", error);
+                    let _ = client.close();
+                    connected = false;
+                    continue;
+                }
+
+                shown = current;
+            }
+
+            sleep(INTERVAL).await;
+        }
+    });
+}
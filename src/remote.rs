@@ -0,0 +1,68 @@
+//! The different ways that lowfi can be controlled remotely, which all share
+//! the same commands & status, see [parse] & [status].
+//!
+//! The commands are `status`, `skip`, `previous`, `pause`, `resume`, `toggle`,
+//! `favorite`, `seek <seconds>`, `volume <percentage>` and `quit`.
+
+use serde_json::{json, Value};
+
+use lowfi::player::{Messages, Player};
+
+pub mod http;
+pub mod mpd;
+
+/// Gets the status of the player as JSON.
+pub fn status(player: &Player) -> Value {
+    let current = player.current.load();
+
+    let state = match current.as_ref() {
+        None if player.connectivity.lost() => "offline",
+        None => "loading",
+        Some(_) if player.sink.is_paused() => "paused",
+        Some(_) => "playing",
+    };
+
+    json!({
+        "state": state,
+        "track": current.as_ref().map(|x| &x.name),
+        "artist": current.as_ref().and_then(|x| x.artist.as_ref()),
+        "album": current.as_ref().and_then(|x| x.album.as_ref()),
+        "url": current.as_ref().map(|x| &x.url),
+        "elapsed": current.as_ref().map(|_| player.elapsed().as_secs()),
+        "duration": current.as_ref().and_then(|x| x.duration).map(|x| x.as_secs()),
+        "volume": player.volume_percentage(),
+    })
+}
+
+/// Parses a command into the message that should be sent to the audio server.
+///
+/// This returns `Ok(None)` for commands which don't do anything, like `status`.
+pub fn parse(command: &str) -> Result<Option<Messages>, String> {
+    let mut words = command.split_whitespace();
+    let (Some(command), argument) = (words.next(), words.next()) else {
+        return Err("empty command".to_owned());
+    };
+
+    let number = || -> Result<i64, String> {
+        argument
+            .ok_or_else(|| format!("{} needs a number", command))?
+            .parse()
+            .map_err(|_| format!("{} needs a number", command))
+    };
+
+    let message = match command {
+        "status" => return Ok(None),
+        "skip" | "next" => Messages::Next,
+        "previous" | "back" => Messages::Previous,
+        "pause" => Messages::Pause,
+        "resume" | "play" => Messages::Resume,
+        "toggle" => Messages::PlayPauseToggle,
+        "favorite" => Messages::Favorite,
+        "quit" => Messages::Quit,
+        "seek" => Messages::Seek(number()?),
+        "volume" => Messages::SetVolume(number()?.clamp(0, 100) as f32 / 100.0),
+        _ => return Err(format!("unknown command: {}", command)),
+    };
+
+    Ok(Some(message))
+}
@@ -0,0 +1,311 @@
+//! A tiny web remote, which is served with `--web 127.0.0.1:8080` so that lowfi
+//! can be controlled from a phone or anything else on the same network.
+//!
+//! `GET /` is a page with buttons for everything, `GET /status` is the status as JSON,
+//! and `POST /<command>` runs a command, like `POST /skip` or `POST /volume/50`,
+//! which responds with the status just like the control socket does.
+//!
+//! `GET /events` is a stream of [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events),
+//! which starts with a `status` event, and is followed by `track_start`, `pause`,
+//! `resume` & `volume_change` events as they happen, each with some JSON as its data.
+//!
+//! With `metrics = true` in the config, `GET /metrics` is a few counters in the
+//! [Prometheus](https://prometheus.io/docs/instrumenting/exposition_formats/) text format,
+//! like how much has been downloaded & how many tracks have been played,
+//! see [Metrics](lowfi::player::metrics::Metrics).
+//!
+//! There's no authentication at all, so this should only ever be served on a trusted network.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    select,
+    sync::mpsc::Sender,
+    task,
+    time::sleep,
+};
+use tracing::{debug, info};
+
+use lowfi::player::{Event, Messages, Player};
+
+use super::{parse, status};
+
+/// The web page, which talks to the rest of the routes.
+const PAGE: &str = include_str!("index.html");
+
+/// The most that's read of a request, which is plenty since none of them have a body.
+const MAX_REQUEST: u64 = 8 * 1024;
+
+/// How long the event stream can go without anything being sent, before a comment
+/// is sent to keep it open, which is also how closed connections are noticed.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The parts of a request that are actually used.
+struct Request {
+    /// The method, like `GET`.
+    method: String,
+
+    /// The path, without the query string.
+    path: String,
+}
+
+/// A response to a [Request].
+struct Response {
+    /// The status line, like `200 OK`.
+    status: &'static str,
+
+    /// The type of `body`.
+    content_type: &'static str,
+
+    /// The body itself.
+    body: String,
+}
+
+impl Response {
+    /// A response that's some JSON.
+    fn json(status: &'static str, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Formats the [Metrics](lowfi::player::metrics::Metrics) of `player` for Prometheus.
+fn metrics(player: &Player) -> String {
+    let metrics = &player.metrics;
+    let (volume, muted) = {
+        let state = player.state.borrow();
+        (state.volume, state.muted)
+    };
+    let values = [
+        (
+            "lowfi_downloaded_bytes_total",
+            "counter",
+            "How many bytes of tracks & streams have been downloaded.",
+            metrics.downloaded(),
+        ),
+        (
+            "lowfi_tracks_played_total",
+            "counter",
+            "How many tracks have started playing.",
+            metrics.played(),
+        ),
+        (
+            "lowfi_buffer_underruns_total",
+            "counter",
+            "How many times playback had to wait for something to download.",
+            metrics.underruns(),
+        ),
+        (
+            "lowfi_volume_percent",
+            "gauge",
+            "The volume as a percentage, which is 0 while muted.",
+            if muted { 0 } else { u64::from(volume) },
+        ),
+    ];
+
+    values
+        .iter()
+        .map(|(name, kind, help, value)| {
+            format!(
+                "# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+                name, help, name, kind, name, value
+            )
+        })
+        .collect()
+}
+
+/// Reads the request line & headers, skipping over any body.
+///
+/// This returns [None] if the connection is closed before there's a full request.
+async fn read(reader: impl AsyncRead + Unpin) -> eyre::Result<Option<Request>> {
+    let mut reader = BufReader::new(reader.take(MAX_REQUEST));
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        eyre::bail!("invalid request line: {}", line.trim_end());
+    };
+
+    let request = Request {
+        method: method.to_owned(),
+        path: target.split('?').next().unwrap_or_default().to_owned(),
+    };
+
+    // The headers aren't needed for anything, but they still have to be read.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    Ok(Some(request))
+}
+
+/// Works out the response to a request, sending off any command that's in it.
+///
+/// `/metrics` is only there if `serve_metrics` is true, see [metrics].
+async fn route(
+    request: &Request,
+    player: &Player,
+    sender: &Sender<Messages>,
+    serve_metrics: bool,
+) -> eyre::Result<Response> {
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            body: PAGE.to_owned(),
+        },
+        ("GET", "/status") => Response::json("200 OK", status(player)),
+        ("GET", "/metrics") if serve_metrics => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics(player),
+        },
+        ("POST", path) => {
+            // `/volume/50` is the same as the command `volume 50`.
+            match parse(&path.trim_matches('/').replace('/', " ")) {
+                Ok(message) => {
+                    if let Some(message) = message {
+                        sender.send(message).await?;
+                    }
+
+                    Response::json("200 OK", status(player))
+                }
+                Err(error) => Response::json("400 Bad Request", json!({ "error": error })),
+            }
+        }
+        ("GET", _) => Response::json("404 Not Found", json!({ "error": "not found" })),
+        _ => Response::json(
+            "405 Method Not Allowed",
+            json!({ "error": "method not allowed" }),
+        ),
+    };
+
+    Ok(response)
+}
+
+/// Writes out a response, after which the connection is closed.
+async fn write(mut writer: impl AsyncWrite + Unpin, response: Response) -> eyre::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(response.body.as_bytes()).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// Turns an event into the JSON that's sent along with it.
+fn data(event: &Event) -> Value {
+    match event {
+        Event::TrackStart(track) => json!({
+            "track": track.name,
+            "artist": track.artist,
+            "album": track.album,
+            "url": track.url,
+            "duration": track.duration.map(|x| x.as_secs()),
+        }),
+        Event::Pause | Event::Resume => json!({}),
+        Event::VolumeChange { volume, muted } => json!({ "volume": volume, "muted": muted }),
+    }
+}
+
+/// Sends events down the connection as they happen, until it's closed.
+async fn events(mut writer: impl AsyncWrite + Unpin, player: &Player) -> eyre::Result<()> {
+    writer
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n")
+        .await?;
+
+    // The status comes first, so that clients know where things are at before anything changes.
+    let status = format!("event: status\ndata: {}\n\n", status(player));
+    writer.write_all(status.as_bytes()).await?;
+
+    let mut state = player.state.subscribe();
+    let mut last = state.borrow_and_update().clone();
+
+    loop {
+        select! {
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+
+                let current = state.borrow_and_update().clone();
+                for event in current.events(&last) {
+                    let message = format!("event: {}\ndata: {}\n\n", event.name(), data(&event));
+                    writer.write_all(message.as_bytes()).await?;
+                }
+
+                last = current;
+            }
+            () = sleep(KEEP_ALIVE) => writer.write_all(b": keep-alive\n\n").await?,
+        }
+    }
+}
+
+/// Handles a single connection, which is only ever one request, or the event stream.
+async fn handle(
+    mut stream: TcpStream,
+    player: Arc<Player>,
+    sender: Sender<Messages>,
+    serve_metrics: bool,
+) -> eyre::Result<()> {
+    let (reader, writer) = stream.split();
+    let Some(request) = read(reader).await? else {
+        return Ok(());
+    };
+
+    debug!("web remote: {} {}", request.method, request.path);
+    if (request.method.as_str(), request.path.as_str()) == ("GET", "/events") {
+        return events(writer, &player).await;
+    }
+
+    let response = route(&request, &player, &sender, serve_metrics).await?;
+    write(writer, response).await
+}
+
+/// Starts serving the web remote on `address` in the background,
+/// along with the metrics if `serve_metrics` is true.
+pub async fn start(
+    address: SocketAddr,
+    serve_metrics: bool,
+    player: Arc<Player>,
+    sender: Sender<Messages>,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|error| eyre::eyre!("unable to serve the web remote on {}: {}", address, error))?;
+    info!("serving the web remote on http://{}", address);
+
+    task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let player = Arc::clone(&player);
+            let sender = sender.clone();
+
+            task::spawn(async move {
+                if let Err(error) = handle(stream, player, sender, serve_metrics).await {
+                    debug!("web remote connection failed: {:#}", error);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
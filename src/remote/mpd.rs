@@ -0,0 +1,476 @@
+//! Speaks enough of the [MPD protocol](https://mpd.readthedocs.io/en/latest/protocol.html)
+//! for MPD clients like `mpc`, `ncmpcpp` & phone apps to be able to control lowfi.
+//!
+//! lowfi doesn't have a playlist in the same way as MPD, so it always looks like a
+//! playlist of just the current track. Other than that, the supported commands are
+//! `status`, `currentsong`, `playlistinfo`, `plchanges`, `play`, `pause`, `stop`,
+//! `next`, `previous`, `setvol`, `volume`, `seekcur`, `repeat`, `idle` & `noidle`,
+//! along with command lists and a few commands that just keep clients happy.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines},
+    net::{tcp::OwnedReadHalf, TcpListener, TcpStream},
+    select,
+    sync::{mpsc::Sender, watch},
+    task,
+};
+use tracing::{debug, info};
+
+use lowfi::player::{Event, Messages, Player, PlayerState};
+
+/// The version of the protocol that lowfi claims to speak.
+const VERSION: &str = "0.23.0";
+
+/// The error code for a command with bad arguments.
+const ACK_ERROR_ARG: u8 = 2;
+
+/// The error code for a command that isn't supported.
+const ACK_ERROR_UNKNOWN: u8 = 5;
+
+/// The error code for something that doesn't exist, like the current song while loading.
+const ACK_ERROR_NO_EXIST: u8 = 50;
+
+/// A command that failed, which is sent back as an `ACK`.
+struct Failure {
+    /// The MPD error code.
+    code: u8,
+
+    /// What went wrong.
+    message: String,
+}
+
+impl Failure {
+    /// A failure because of the command's arguments.
+    fn argument(message: impl Into<String>) -> Self {
+        Self {
+            code: ACK_ERROR_ARG,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a connection should do after a command.
+enum Outcome {
+    /// Carry on, with whatever the command responded with, not including the `OK`.
+    Respond(String),
+
+    /// Wait for something to change, only caring about the given subsystems if there are any.
+    Idle(Vec<String>),
+
+    /// Close the connection.
+    Close,
+}
+
+/// Splits a command into its words, which can be quoted with backslash escapes.
+fn split(line: &str) -> Result<Vec<String>, Failure> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(&char) = chars.peek() {
+        if char.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        if char == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => word.extend(chars.next()),
+                    Some(char) => word.push(char),
+                    None => return Err(Failure::argument("missing closing quote")),
+                }
+            }
+        } else {
+            while let Some(char) = chars.next_if(|x| !x.is_whitespace()) {
+                word.push(char);
+            }
+        }
+
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+/// A number that identifies the current track, which MPD uses as the song ID & playlist version.
+fn id(state: &PlayerState) -> u32 {
+    state.current.as_ref().map_or(0, |track| {
+        let digest = md5::compute(&track.url);
+        u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]]) >> 1
+    })
+}
+
+/// The response to `currentsong`, which is empty while loading.
+fn song(state: &PlayerState) -> String {
+    let Some(track) = &state.current else {
+        return String::new();
+    };
+
+    let mut song = format!("file: {}\nTitle: {}\n", track.url, track.name);
+    if let Some(artist) = &track.artist {
+        song += &format!("Artist: {}\n", artist);
+    }
+    if let Some(album) = &track.album {
+        song += &format!("Album: {}\n", album);
+    }
+    if let Some(duration) = track.duration {
+        song += &format!(
+            "Time: {}\nduration: {:.3}\n",
+            duration.as_secs(),
+            duration.as_secs_f64()
+        );
+    }
+
+    song + &format!("Pos: 0\nId: {}\n", id(state))
+}
+
+/// The response to `status`.
+fn status(player: &Player, state: &PlayerState) -> String {
+    let volume = if state.muted { 0 } else { state.volume };
+    let mut status = format!(
+        "volume: {}\nrepeat: {}\nrandom: 1\nsingle: 0\nconsume: 1\nplaylist: {}\nplaylistlength: {}\n",
+        volume,
+        u8::from(state.repeat),
+        id(state),
+        u8::from(state.current.is_some()),
+    );
+
+    let Some(track) = &state.current else {
+        return status + "state: stop\n";
+    };
+
+    let playing = if state.paused { "pause" } else { "play" };
+    let elapsed = player.elapsed();
+    status += &format!(
+        "state: {}\nsong: 0\nsongid: {}\nelapsed: {:.3}\n",
+        playing,
+        id(state),
+        elapsed.as_secs_f64()
+    );
+
+    if let Some(duration) = track.duration {
+        status += &format!(
+            "time: {}:{}\nduration: {:.3}\n",
+            elapsed.as_secs(),
+            duration.as_secs(),
+            duration.as_secs_f64()
+        );
+    }
+
+    status
+}
+
+/// The subsystems that changed between two states, as they're named in `idle`.
+fn subsystems(last: &PlayerState, state: &PlayerState) -> Vec<&'static str> {
+    let mut subsystems = Vec::new();
+    for event in state.events(last) {
+        let changed: &[&str] = match event {
+            Event::TrackStart(_) => &["playlist", "player"],
+            Event::Pause | Event::Resume => &["player"],
+            Event::VolumeChange { .. } => &["mixer"],
+        };
+
+        for subsystem in changed {
+            if !subsystems.contains(subsystem) {
+                subsystems.push(subsystem);
+            }
+        }
+    }
+
+    if state.repeat != last.repeat {
+        subsystems.push("options");
+    }
+
+    subsystems
+}
+
+/// Parses the single argument that a command needs.
+fn argument<T: std::str::FromStr>(command: &str, args: &[String]) -> Result<T, Failure> {
+    args.first()
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| Failure::argument(format!("{} needs a number", command)))
+}
+
+/// Runs a single command.
+async fn run(
+    words: &[String],
+    player: &Player,
+    sender: &Sender<Messages>,
+) -> eyre::Result<Result<Outcome, Failure>> {
+    let Some((command, args)) = words.split_first() else {
+        return Ok(Err(Failure {
+            code: ACK_ERROR_UNKNOWN,
+            message: "no command given".to_owned(),
+        }));
+    };
+
+    let state = player.state.borrow().clone();
+    let message = match command.as_str() {
+        "status" => return Ok(Ok(Outcome::Respond(status(player, &state)))),
+        "currentsong" | "playlistinfo" | "playlistid" | "plchanges" => {
+            return Ok(Ok(Outcome::Respond(song(&state))))
+        }
+        "idle" => return Ok(Ok(Outcome::Idle(args.to_vec()))),
+        "close" => return Ok(Ok(Outcome::Close)),
+        "ping" | "clearerror" | "noidle" | "tagtypes" | "listplaylists" | "urlhandlers"
+        | "decoders" => return Ok(Ok(Outcome::Respond(String::new()))),
+        "outputs" => {
+            let output = "outputid: 0\noutputname: lowfi\noutputenabled: 1\n";
+            return Ok(Ok(Outcome::Respond(output.to_owned())));
+        }
+        "commands" => {
+            let commands = [
+                "close",
+                "currentsong",
+                "idle",
+                "next",
+                "noidle",
+                "pause",
+                "ping",
+                "play",
+                "playlistinfo",
+                "plchanges",
+                "previous",
+                "repeat",
+                "seekcur",
+                "setvol",
+                "status",
+                "stop",
+                "volume",
+            ];
+
+            let response = commands.map(|x| format!("command: {}\n", x)).concat();
+            return Ok(Ok(Outcome::Respond(response)));
+        }
+
+        "play" | "playid" => Messages::Resume,
+        "stop" => Messages::Pause,
+        "pause" => match args.first().map(String::as_str) {
+            None => Messages::PlayPauseToggle,
+            Some("1") => Messages::Pause,
+            Some("0") => Messages::Resume,
+            Some(_) => return Ok(Err(Failure::argument("pause needs 0 or 1"))),
+        },
+        "next" => Messages::Next,
+        "previous" => Messages::Previous,
+        "setvol" => match argument::<u8>(command, args) {
+            Ok(volume) => Messages::SetVolume(f32::from(volume.min(100)) / 100.0),
+            Err(failure) => return Ok(Err(failure)),
+        },
+        "volume" => match argument::<i8>(command, args) {
+            Ok(change) => Messages::ChangeVolume(f32::from(change) / 100.0),
+            Err(failure) => return Ok(Err(failure)),
+        },
+        "seekcur" => {
+            let Some(target) = args.first() else {
+                return Ok(Err(Failure::argument("seekcur needs a time")));
+            };
+
+            // A sign makes it relative, otherwise it's from the start of the track.
+            let Ok(seconds) = target.parse::<f64>() else {
+                return Ok(Err(Failure::argument("seekcur needs a time")));
+            };
+
+            if state.current.is_none() {
+                return Ok(Err(Failure {
+                    code: ACK_ERROR_NO_EXIST,
+                    message: "nothing is playing".to_owned(),
+                }));
+            }
+
+            if target.starts_with(['+', '-']) {
+                Messages::Seek(seconds as i64)
+            } else {
+                Messages::SeekTo(Duration::from_secs_f64(seconds.max(0.0)))
+            }
+        }
+        "repeat" => match args.first().map(String::as_str) {
+            Some("0") if !state.repeat => return Ok(Ok(Outcome::Respond(String::new()))),
+            Some("1") if state.repeat => return Ok(Ok(Outcome::Respond(String::new()))),
+            Some("0" | "1") => Messages::ToggleRepeat,
+            _ => return Ok(Err(Failure::argument("repeat needs 0 or 1"))),
+        },
+        _ => {
+            return Ok(Err(Failure {
+                code: ACK_ERROR_UNKNOWN,
+                message: format!("unknown command \"{}\"", command),
+            }))
+        }
+    };
+
+    sender.send(message).await?;
+    Ok(Ok(Outcome::Respond(String::new())))
+}
+
+/// Waits until one of `filter`'s subsystems changes, or any of them if it's empty.
+///
+/// Changes from since the last time the connection was idle are included, which is why
+/// `seen` is the state it saw last. This returns `false` if the connection should be closed.
+async fn idle(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+    writer: &mut (impl AsyncWrite + Unpin),
+    state: &mut watch::Receiver<PlayerState>,
+    seen: &mut PlayerState,
+    filter: &[String],
+) -> eyre::Result<bool> {
+    loop {
+        let current = state.borrow_and_update().clone();
+        let changed: Vec<&str> = subsystems(seen, &current)
+            .into_iter()
+            .filter(|x| filter.is_empty() || filter.iter().any(|y| y == x))
+            .collect();
+        *seen = current;
+
+        if !changed.is_empty() {
+            let response = changed.iter().map(|x| format!("changed: {}\n", x));
+            writer
+                .write_all((response.collect::<String>() + "OK\n").as_bytes())
+                .await?;
+
+            return Ok(true);
+        }
+
+        select! {
+            line = lines.next_line() => {
+                // Anything other than `noidle` while idle isn't allowed, so it just closes the connection.
+                return match line? {
+                    Some(line) if line.trim() == "noidle" => {
+                        writer.write_all(b"OK\n").await?;
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                };
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
+
+/// Handles a single connection until it's closed.
+async fn handle(
+    stream: TcpStream,
+    player: Arc<Player>,
+    sender: Sender<Messages>,
+) -> eyre::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let mut state = player.state.subscribe();
+    let mut seen = state.borrow_and_update().clone();
+
+    writer
+        .write_all(format!("OK MPD {}\n", VERSION).as_bytes())
+        .await?;
+
+    // The commands in the current command list, and whether each one should be followed by `list_OK`.
+    let mut list: Option<(Vec<String>, bool)> = None;
+
+    while let Some(line) = lines.next_line().await? {
+        let commands = match (line.trim(), &mut list) {
+            ("command_list_begin", None) => {
+                list = Some((Vec::new(), false));
+                continue;
+            }
+            ("command_list_ok_begin", None) => {
+                list = Some((Vec::new(), true));
+                continue;
+            }
+            ("command_list_end", Some(_)) => list.take().unwrap_or_default(),
+            (_, Some((commands, _))) => {
+                commands.push(line);
+                continue;
+            }
+            _ => (vec![line], false),
+        };
+
+        let (commands, ok) = commands;
+        let mut response = String::new();
+        // Whether the response has already been ended, either by an `ACK` or by `idle`.
+        let mut ended = false;
+
+        for (i, command) in commands.iter().enumerate() {
+            let words = match split(command) {
+                Ok(words) => words,
+                Err(failure) => {
+                    response += &format!("ACK [{}@{}] {{}} {}\n", failure.code, i, failure.message);
+                    ended = true;
+                    break;
+                }
+            };
+
+            let name = words.first().map_or("", String::as_str);
+            match run(&words, &player, &sender).await? {
+                Ok(Outcome::Respond(output)) => {
+                    response += &output;
+                    if ok {
+                        response += "list_OK\n";
+                    }
+                }
+                Ok(Outcome::Idle(filter)) => {
+                    writer.write_all(response.as_bytes()).await?;
+                    response.clear();
+
+                    if !idle(&mut lines, &mut writer, &mut state, &mut seen, &filter).await? {
+                        return Ok(());
+                    }
+
+                    ended = true;
+                    break;
+                }
+                Ok(Outcome::Close) => return Ok(()),
+                Err(failure) => {
+                    response += &format!(
+                        "ACK [{}@{}] {{{}}} {}\n",
+                        failure.code, i, name, failure.message
+                    );
+                    ended = true;
+                    break;
+                }
+            }
+        }
+
+        if !ended {
+            response += "OK\n";
+        }
+
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Starts serving the MPD protocol on `address` in the background.
+pub async fn start(
+    address: SocketAddr,
+    player: Arc<Player>,
+    sender: Sender<Messages>,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|error| eyre::eyre!("unable to serve MPD on {}: {}", address, error))?;
+    info!("serving MPD on {}", address);
+
+    task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let player = Arc::clone(&player);
+            let sender = sender.clone();
+
+            task::spawn(async move {
+                if let Err(error) = handle(stream, player, sender).await {
+                    debug!("MPD connection failed: {:#}", error);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Runs the user's Lua scripts, which can react to what lowfi is doing and control it.
+//!
+//! Every `.lua` file in the `scripts` directory next to the config is ran when lowfi
+//! starts, in alphabetical order. Scripts register handlers with `lowfi.on(event, handler)`,
+//! where the events are:
+//!
+//! - `track_start`, with a table of the track's `name`, `url`, `artist`, `album` & `duration`.
+//! - `pause` & `resume`.
+//! - `volume_change`, with the volume as a percentage, and whether it's muted.
+//!
+//! Handlers, and the scripts themselves, can then call `lowfi.next()`, `lowfi.pause()`,
+//! `lowfi.resume()`, `lowfi.toggle()`, `lowfi.set_volume(percentage)` & `lowfi.notify(message)`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use eyre::eyre;
+use mlua::{Function, IntoLuaMulti, Lua, Table};
+use tokio::{sync::mpsc::Sender, task};
+use tracing::{debug, warn};
+
+use lowfi::{
+    player::{Event, Messages, Player},
+    tracks::TrackInfo,
+};
+
+/// The events that scripts can register handlers for.
+const EVENTS: &[&str] = &["track_start", "pause", "resume", "volume_change"];
+
+/// The key in the Lua registry that holds the handlers, as a table of events to lists of functions.
+const HANDLERS: &str = "lowfi_handlers";
+
+/// The messages that scripts have asked for, which are sent once they're done running.
+///
+/// Lua can't wait on the channel, so this is how commands get out of it.
+type Commands = Arc<Mutex<Vec<Messages>>>;
+
+/// Gets the directory that scripts are loaded from, which might not exist.
+fn dir() -> eyre::Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| eyre!("unable to find the config directory"))?
+        .join("lowfi")
+        .join("scripts"))
+}
+
+/// Finds all of the scripts in `dir`, sorted by their name.
+fn scripts(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut scripts: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| x.is_file() && x.extension().is_some_and(|x| x == "lua"))
+        .collect();
+
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// Sets up the `lowfi` table that scripts use to talk to lowfi.
+fn prepare(lua: &Lua, player: Arc<Player>, commands: Commands) -> mlua::Result<()> {
+    lua.set_named_registry_value(HANDLERS, lua.create_table()?)?;
+
+    let api = lua.create_table()?;
+    api.set(
+        "on",
+        lua.create_function(|lua, (event, handler): (String, Function)| {
+            if !EVENTS.contains(&event.as_str()) {
+                return Err(mlua::Error::runtime(format!("unknown event '{}'", event)));
+            }
+
+            let handlers: Table = lua.named_registry_value(HANDLERS)?;
+            let list = match handlers.get::<Option<Table>>(event.as_str())? {
+                Some(list) => list,
+                None => {
+                    let list = lua.create_table()?;
+                    handlers.set(event, &list)?;
+                    list
+                }
+            };
+
+            list.push(handler)
+        })?,
+    )?;
+
+    // Each of these just queues up a message for the player.
+    let command = |message: fn() -> Messages| {
+        let commands = Arc::clone(&commands);
+        lua.create_function(move |_, ()| {
+            commands
+                .lock()
+                .unwrap_or_else(|x| x.into_inner())
+                .push(message());
+            Ok(())
+        })
+    };
+
+    api.set("next", command(|| Messages::Next)?)?;
+    api.set("pause", command(|| Messages::Pause)?)?;
+    api.set("resume", command(|| Messages::Resume)?)?;
+    api.set("toggle", command(|| Messages::PlayPauseToggle)?)?;
+
+    let volume = Arc::clone(&commands);
+    api.set(
+        "set_volume",
+        lua.create_function(move |_, percentage: f32| {
+            volume
+                .lock()
+                .unwrap_or_else(|x| x.into_inner())
+                .push(Messages::SetVolume(percentage.clamp(0.0, 100.0) / 100.0));
+            Ok(())
+        })?,
+    )?;
+
+    api.set(
+        "notify",
+        lua.create_function(move |_, message: String| {
+            player.notify(message, false);
+            Ok(())
+        })?,
+    )?;
+
+    lua.globals().set("lowfi", api)
+}
+
+/// Calls all of the handlers for `event`, carrying on past any that fail.
+fn dispatch(lua: &Lua, event: &str, args: impl IntoLuaMulti + Clone) -> mlua::Result<()> {
+    let handlers: Table = lua.named_registry_value(HANDLERS)?;
+    let Some(list) = handlers.get::<Option<Table>>(event)? else {
+        return Ok(());
+    };
+
+    for handler in list.sequence_values::<Function>() {
+        if let Err(error) = handler?.call::<()>(args.clone()) {
+            warn!("a script's {} handler failed: {}", event, error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns a track into the table that's passed to `track_start` handlers.
+fn track(lua: &Lua, track: &TrackInfo) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    table.set("name", track.name.as_str())?;
+    table.set("url", track.url.as_str())?;
+    table.set("artist", track.artist.as_deref())?;
+    table.set("album", track.album.as_deref())?;
+    table.set("duration", track.duration.map(|x| x.as_secs()))?;
+
+    Ok(table)
+}
+
+/// Runs the handlers for an event.
+fn run(lua: &Lua, event: &Event) -> mlua::Result<()> {
+    match event {
+        Event::TrackStart(current) => dispatch(lua, event.name(), track(lua, current)?),
+        Event::Pause | Event::Resume => dispatch(lua, event.name(), ()),
+        Event::VolumeChange { volume, muted } => dispatch(lua, event.name(), (*volume, *muted)),
+    }
+}
+
+/// Sends everything that the scripts have asked for to the player.
+async fn flush(commands: &Commands, tx: &Sender<Messages>) -> eyre::Result<()> {
+    let pending = std::mem::take(&mut *commands.lock().unwrap_or_else(|x| x.into_inner()));
+    for message in pending {
+        tx.send(message).await?;
+    }
+
+    Ok(())
+}
+
+/// Loads the scripts, and then keeps running their handlers in the background until lowfi exits.
+///
+/// Nothing happens if there aren't any scripts, and a script which fails to
+/// load is reported & skipped, without affecting the others.
+pub async fn start(player: Arc<Player>, tx: Sender<Messages>) -> eyre::Result<()> {
+    let dir = dir()?;
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let scripts = scripts(&dir)?;
+    if scripts.is_empty() {
+        return Ok(());
+    }
+
+    let lua = Lua::new();
+    let commands = Commands::default();
+    prepare(&lua, Arc::clone(&player), Arc::clone(&commands))?;
+
+    for script in scripts {
+        debug!("loading the script {}", script.display());
+        let source = std::fs::read_to_string(&script)?;
+        let name = script.file_name().unwrap_or_default().to_string_lossy();
+
+        if let Err(error) = lua.load(&source).set_name(format!("@{}", name)).exec() {
+            warn!("unable to load the script {}: {}", script.display(), error);
+            player.report(format!("unable to load the script {}", name));
+        }
+    }
+
+    flush(&commands, &tx).await?;
+
+    let mut state = player.state.subscribe();
+    task::spawn(async move {
+        let mut last = state.borrow_and_update().clone();
+
+        while state.changed().await.is_ok() {
+            let current = state.borrow_and_update().clone();
+            for event in current.events(&last) {
+                if let Err(error) = run(&lua, &event) {
+                    warn!("unable to run the scripts: {}", error);
+                }
+            }
+
+            last = current;
+            if flush(&commands, &tx).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! An opt-in scrobbling subsystem, which submits listens to Last.fm and/or ListenBrainz.
+//!
+//! It's entirely driven by polling the [Player], so that a slow or broken
+//! connection to a scrobbling service can never interfere with playback.
+
+use std::{
+    env,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+use serde_json::json;
+use tokio::{task, time::sleep};
+use tracing::warn;
+
+use lowfi::{config, player::Player, tracks::TrackInfo};
+
+/// How often the scrobbler checks up on the player.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// The artist that is sent when the track's tags don't have one.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+
+/// A single scrobbling service, along with its credentials.
+#[derive(Clone)]
+pub enum Service {
+    /// ListenBrainz, which only needs a user token.
+    ListenBrainz {
+        /// The user token from <https://listenbrainz.org/settings/>.
+        token: String,
+    },
+
+    /// Last.fm, which needs an API account & an authenticated session key.
+    LastFm {
+        /// The API key of the Last.fm API account.
+        api_key: String,
+
+        /// The shared secret of the Last.fm API account, used to sign requests.
+        secret: String,
+
+        /// The session key of the user being scrobbled to.
+        session_key: String,
+    },
+}
+
+/// The two different kinds of submissions.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Submission {
+    /// The track just started playing.
+    NowPlaying,
+
+    /// The track was listened to for long enough to count, and it started at the given UNIX timestamp.
+    Scrobble(u64),
+}
+
+impl Service {
+    /// Reads all of the configured services from the config & environment variables,
+    /// where the environment variables take priority.
+    ///
+    /// ListenBrainz needs `LOWFI_LISTENBRAINZ_TOKEN`, while Last.fm needs
+    /// `LOWFI_LASTFM_API_KEY`, `LOWFI_LASTFM_SECRET` & `LOWFI_LASTFM_SESSION_KEY`.
+    pub fn load(config: &config::Scrobble) -> Vec<Self> {
+        let var = |name: &str, fallback: &Option<String>| env::var(name).ok().or(fallback.clone());
+        let mut services = Vec::new();
+
+        if let Some(token) = var("LOWFI_LISTENBRAINZ_TOKEN", &config.listenbrainz_token) {
+            services.push(Self::ListenBrainz { token });
+        }
+
+        if let (Some(api_key), Some(secret), Some(session_key)) = (
+            var("LOWFI_LASTFM_API_KEY", &config.lastfm_api_key),
+            var("LOWFI_LASTFM_SECRET", &config.lastfm_secret),
+            var("LOWFI_LASTFM_SESSION_KEY", &config.lastfm_session_key),
+        ) {
+            services.push(Self::LastFm {
+                api_key,
+                secret,
+                session_key,
+            });
+        }
+
+        services
+    }
+
+    /// Gets the name of the service, for logging.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ListenBrainz { .. } => "ListenBrainz",
+            Self::LastFm { .. } => "Last.fm",
+        }
+    }
+
+    /// Submits a single event to the service.
+    async fn submit(
+        &self,
+        client: &Client,
+        track: &TrackInfo,
+        submission: Submission,
+    ) -> eyre::Result<()> {
+        match self {
+            Self::ListenBrainz { token } => {
+                let mut listen = json!({
+                    "track_metadata": {
+                        "artist_name": track.artist.as_deref().unwrap_or(UNKNOWN_ARTIST),
+                        "track_name": track.name,
+                        "additional_info": {
+                            "media_player": "lowfi",
+                            "origin_url": track.url,
+                            "duration_ms": track.duration.map(|x| x.as_millis() as u64),
+                        }
+                    }
+                });
+
+                if let Some(album) = &track.album {
+                    listen["track_metadata"]["release_name"] = json!(album);
+                }
+
+                let listen_type = match submission {
+                    Submission::NowPlaying => "playing_now",
+                    Submission::Scrobble(timestamp) => {
+                        listen["listened_at"] = json!(timestamp);
+                        "single"
+                    }
+                };
+
+                client
+                    .post("https://api.listenbrainz.org/1/submit-listens")
+                    .header("Authorization", format!("Token {}", token))
+                    .json(&json!({ "listen_type": listen_type, "payload": [listen] }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Self::LastFm {
+                api_key,
+                secret,
+                session_key,
+            } => {
+                let mut params = vec![
+                    ("api_key", api_key.clone()),
+                    (
+                        "artist",
+                        track.artist.as_deref().unwrap_or(UNKNOWN_ARTIST).to_owned(),
+                    ),
+                    ("sk", session_key.clone()),
+                    ("track", track.name.clone()),
+                ];
+
+                if let Some(duration) = track.duration {
+                    params.push(("duration", duration.as_secs().to_string()));
+                }
+
+                if let Some(album) = &track.album {
+                    params.push(("album", album.clone()));
+                }
+
+                match submission {
+                    Submission::NowPlaying => {
+                        params.push(("method", "track.updateNowPlaying".to_owned()))
+                    }
+                    Submission::Scrobble(timestamp) => {
+                        params.push(("method", "track.scrobble".to_owned()));
+                        params.push(("timestamp", timestamp.to_string()));
+                    }
+                }
+
+                // Last.fm wants every request to be signed with an md5 hash of all of the
+                // parameters in alphabetical order, followed by the shared secret.
+                params.sort_by_key(|(key, _)| *key);
+                let signature: String = params
+                    .iter()
+                    .map(|(key, value)| format!("{}{}", key, value))
+                    .collect();
+                let signature = format!("{:x}", md5::compute(signature + secret));
+
+                params.push(("api_sig", signature));
+                params.push(("format", "json".to_owned()));
+
+                client
+                    .post("https://ws.audioscrobbler.com/2.0/")
+                    .form(&params)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Submits an event to every service in the background, only logging any errors.
+fn submit_all(
+    client: &Client,
+    services: &Arc<[Service]>,
+    track: &Arc<TrackInfo>,
+    submission: Submission,
+) {
+    for service in services.iter() {
+        let service = service.clone();
+        let client = client.clone();
+        let track = Arc::clone(track);
+
+        task::spawn(async move {
+            if let Err(error) = service.submit(&client, &track, submission).await {
+                warn!("unable to submit to {}: {:#}", service.name(), error);
+            }
+        });
+    }
+}
+
+/// Gets the current UNIX timestamp in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+/// Starts the scrobbler in the background, which will keep going until lowfi exits.
+///
+/// This does nothing if `services` is empty. The player's client is
+/// reused, so that scrobbles go through the same proxy as the tracks.
+pub fn start(player: Arc<Player>, services: Vec<Service>) {
+    if services.is_empty() {
+        return;
+    }
+
+    let services: Arc<[Service]> = services.into();
+    let client = player.network.client().clone();
+
+    task::spawn(async move {
+        // The track that is currently being tracked, when it started & whether it's been scrobbled.
+        let mut tracked: Option<(Arc<TrackInfo>, u64, bool)> = None;
+
+        loop {
+            let current = player.current.load_full();
+
+            match (&current, &mut tracked) {
+                (Some(current), Some((track, _, _))) if Arc::ptr_eq(current, track) => (),
+                (Some(current), _) => {
+                    submit_all(&client, &services, current, Submission::NowPlaying);
+                    tracked = Some((Arc::clone(current), now(), false));
+                }
+                (None, _) => tracked = None,
+            }
+
+            if let Some((track, started, scrobbled)) = &mut tracked {
+                // Tracks count as listened to once they pass the halfway mark,
+                // although those without a known duration never can be.
+                let halfway = track.duration.map(|x| x / 2);
+
+                if !*scrobbled && halfway.is_some_and(|x| player.elapsed() >= x) {
+                    submit_all(&client, &services, track, Submission::Scrobble(*started));
+                    *scrobbled = true;
+                }
+            }
+
+            sleep(INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,48 @@
+//! Makes sure that the terminal is always put back to normal when lowfi stops,
+//! even if it's killed by a signal or panics, rather than leaving it in raw mode.
+
+use std::{panic, sync::Arc};
+
+use tracing::info;
+
+use lowfi::config::Config;
+
+use crate::ui;
+
+/// Waits until lowfi is told to stop by a signal, which is either `SIGINT`, `SIGTERM`,
+/// or `SIGHUP` when the terminal is closed. On Windows, this is just Ctrl+C.
+pub async fn signal() -> eyre::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut hangup = signal(SignalKind::hangup())?;
+
+        tokio::select! {
+            _ = interrupt.recv() => info!("stopping after SIGINT"),
+            _ = terminate.recv() => info!("stopping after SIGTERM"),
+            _ = hangup.recv() => info!("stopping after SIGHUP"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        info!("stopping after Ctrl+C");
+    }
+
+    Ok(())
+}
+
+/// Restores the terminal before a panic message is printed, since it'd
+/// otherwise be mangled by raw mode & the terminal would stay broken.
+pub fn restore_on_panic(config: Arc<Config>) {
+    let default = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let _ = ui::stop(&config);
+        default(info);
+    }));
+}
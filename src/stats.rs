@@ -0,0 +1,147 @@
+//! Keeps track of how much each track has been listened to, which is shown with `lowfi stats`.
+//!
+//! Everything is kept in `stats.json` in the data directory. Days are counted
+//! in UTC, since that's all that's available without pulling in a timezone database.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{format_duration, storage, tracks::TrackInfo};
+
+/// How long a track has to be listened to for it to count as a play, if it didn't play all the way through.
+const MIN_PLAY: Duration = Duration::from_secs(30);
+
+/// The stats of a single track.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Track {
+    /// The name of the track, from the last time it was played.
+    name: String,
+
+    /// How many times the track has been played.
+    plays: u64,
+
+    /// How many seconds the track has been listened to in total.
+    seconds: u64,
+}
+
+/// Everything that's been listened to.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Stats {
+    /// Every track, by URL.
+    tracks: HashMap<String, Track>,
+
+    /// How many seconds were listened to on each day, which is the amount of days since the UNIX epoch.
+    days: BTreeMap<u64, u64>,
+}
+
+/// Gets the current day, as the amount of days since the UNIX epoch.
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs() / (24 * 60 * 60))
+}
+
+impl Stats {
+    /// Loads the stats, which are empty if they haven't been saved yet.
+    pub async fn load() -> eyre::Result<Self> {
+        let path = storage::data_dir().await?.join("stats.json");
+        let Ok(text) = fs::read_to_string(&path).await else {
+            return Ok(Self::default());
+        };
+
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Saves the stats, replacing whatever was saved before.
+    async fn save(&self) -> eyre::Result<()> {
+        let path = storage::data_dir().await?.join("stats.json");
+        fs::write(path, serde_json::to_string(self)?).await?;
+
+        Ok(())
+    }
+
+    /// Records that a track was listened to for `listened`, where
+    /// `ended` is whether it played all the way through.
+    pub async fn record(track: &TrackInfo, listened: Duration, ended: bool) -> eyre::Result<()> {
+        let mut stats = Self::load().await?;
+        let seconds = listened.as_secs();
+
+        let entry = stats.tracks.entry(track.url.clone()).or_default();
+        entry.name.clone_from(&track.name);
+        entry.seconds += seconds;
+        if ended || listened >= MIN_PLAY {
+            entry.plays += 1;
+        }
+
+        *stats.days.entry(today()).or_default() += seconds;
+        stats.save().await
+    }
+
+    /// Gets the current & longest streaks of days in a row that something was listened to.
+    ///
+    /// The current streak still counts if nothing has been listened to yet today.
+    fn streaks(&self) -> (u64, u64) {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut last: Option<u64> = None;
+
+        for day in self.days.keys() {
+            streak = if last == Some(day.saturating_sub(1)) {
+                streak + 1
+            } else {
+                1
+            };
+
+            longest = longest.max(streak);
+            last = Some(*day);
+        }
+
+        let current = match last {
+            Some(last) if last + 1 >= today() => streak,
+            _ => 0,
+        };
+
+        (current, longest)
+    }
+
+    /// Prints the total time listened, the streaks & the `top` most played tracks.
+    pub fn print(&self, top: usize) {
+        if self.tracks.is_empty() {
+            println!("nothing has been listened to yet");
+            return;
+        }
+
+        let total: u64 = self.days.values().sum();
+        println!(
+            "listened for {:.1} hours across {} days",
+            total as f64 / 3600.0,
+            self.days.len()
+        );
+
+        let (current, longest) = self.streaks();
+        println!(
+            "current streak: {} days, longest: {} days",
+            current, longest
+        );
+
+        let mut tracks: Vec<&Track> = self.tracks.values().collect();
+        tracks.sort_by(|a, b| b.plays.cmp(&a.plays).then(b.seconds.cmp(&a.seconds)));
+
+        println!("\ntop tracks:");
+        for (i, track) in tracks.iter().take(top).enumerate() {
+            println!(
+                "{:>3}. {} ({} plays, {})",
+                i + 1,
+                track.name,
+                track.plays,
+                format_duration(&Duration::from_secs(track.seconds))
+            );
+        }
+    }
+}
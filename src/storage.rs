@@ -0,0 +1,295 @@
+//! Handles lowfi's data directory, and the files that are kept inside of it.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use eyre::{bail, eyre};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::tracks::{
+    list::{Entry, List},
+    ratings::Rating,
+    TrackInfo,
+};
+
+/// Gets lowfi's data directory, which is usually `~/.local/share/lowfi`.
+///
+/// This will also create the directory if it doesn't exist yet.
+pub async fn data_dir() -> eyre::Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| eyre!("unable to find the data directory"))?
+        .join("lowfi");
+
+    fs::create_dir_all(&dir).await?;
+
+    Ok(dir)
+}
+
+/// Gets the path to the favorites file, which uses the same format as
+/// the custom track lists, so it can be passed directly to [crate::tracks::list::List::load].
+pub async fn favorites() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("favorites.txt"))
+}
+
+/// Appends a track to the favorites file, unless it's already in there.
+pub async fn add_favorite(info: &TrackInfo) -> eyre::Result<()> {
+    let path = favorites().await?;
+
+    let existing = fs::read_to_string(&path).await.unwrap_or_default();
+    let duplicate = existing
+        .lines()
+        .any(|line| line.split('\t').next() == Some(info.url.as_str()));
+
+    if duplicate {
+        return Ok(());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+
+    file.write_all(format!("{}\t{}\n", info.url, info.name).as_bytes())
+        .await?;
+
+    Ok(())
+}
+
+/// Gets the directory that tracks are downloaded to by default, which is `~/Music/lowfi`.
+fn downloads() -> eyre::Result<PathBuf> {
+    Ok(dirs::audio_dir()
+        .or_else(|| dirs::home_dir().map(|x| x.join("Music")))
+        .ok_or_else(|| eyre!("unable to find the music directory"))?
+        .join("lowfi"))
+}
+
+/// Saves a track that's already been downloaded to `dir`, or to `~/Music/lowfi`
+/// if there isn't one, returning where it was saved.
+///
+/// The file is named after the track, and keeps the extension from its URL.
+pub async fn save_track(
+    info: &TrackInfo,
+    data: &Bytes,
+    dir: Option<&Path>,
+) -> eyre::Result<PathBuf> {
+    let dir = match dir {
+        Some(dir) => dir.to_owned(),
+        None => downloads()?,
+    };
+    fs::create_dir_all(&dir).await?;
+
+    let file = info.url.rsplit('/').next().unwrap_or_default();
+    let extension = file
+        .rsplit_once('.')
+        .map(|(_, x)| x.to_ascii_lowercase())
+        .filter(|x| !x.is_empty() && x.len() <= 4 && x.chars().all(|x| x.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "mp3".to_owned());
+
+    // Anything that can't be in a file name on some system or another is replaced.
+    let name: String = info
+        .display_name()
+        .chars()
+        .map(|x| match x {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .collect();
+
+    let path = dir.join(format!("{}.{}", name.trim(), extension));
+    if fs::try_exists(&path).await? {
+        bail!("{} already exists", path.display());
+    }
+
+    fs::write(&path, data).await?;
+
+    Ok(path)
+}
+
+/// Gets the path to the history file, which uses the same format as the favorites.
+async fn history() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("history.txt"))
+}
+
+/// Appends a track to the history file.
+pub async fn add_history(info: &TrackInfo) -> eyre::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history().await?)
+        .await?;
+
+    file.write_all(format!("{}\t{}\n", info.url, info.name).as_bytes())
+        .await?;
+
+    Ok(())
+}
+
+/// Loads the last `limit` tracks from the history file, with the most recent one first.
+///
+/// This also trims the file down to `limit` tracks, so that it doesn't grow forever.
+pub async fn load_history(limit: usize) -> eyre::Result<Vec<TrackInfo>> {
+    let path = history().await?;
+    let Ok(text) = fs::read_to_string(&path).await else {
+        return Ok(Vec::new());
+    };
+
+    let lines: Vec<&str> = text.lines().filter(|x| !x.trim().is_empty()).collect();
+    let kept = &lines[lines.len().saturating_sub(limit)..];
+    if kept.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if kept.len() < lines.len() {
+        fs::write(&path, kept.join("\n") + "\n").await?;
+    }
+
+    let list = List::parse(&kept.join("\n"))?;
+    Ok(list
+        .entries()
+        .iter()
+        .rev()
+        .map(|entry| TrackInfo {
+            name: entry
+                .name
+                .clone()
+                .unwrap_or_else(|| TrackInfo::format_name(&entry.url)),
+            url: entry.url.clone(),
+            duration: None,
+            artist: None,
+            album: None,
+            artwork: None,
+        })
+        .collect())
+}
+
+/// Gets the path to the default log file, which is used when lowfi
+/// runs as a daemon or with `-v` but without `--log-file`.
+///
+/// On Windows, the data directory is in the roaming profile, which logs
+/// don't belong in, so they go in `%LOCALAPPDATA%\lowfi` instead.
+pub async fn log() -> eyre::Result<PathBuf> {
+    if cfg!(windows) {
+        let dir = dirs::data_local_dir()
+            .ok_or_else(|| eyre!("unable to find the local data directory"))?
+            .join("lowfi");
+        fs::create_dir_all(&dir).await?;
+
+        return Ok(dir.join("lowfi.log"));
+    }
+
+    Ok(data_dir().await?.join("lowfi.log"))
+}
+
+/// Gets the path to the file which stores the liked & blocked tracks, see [crate::tracks::ratings].
+async fn ratings_path() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("ratings.txt"))
+}
+
+/// Loads every rated track, skipping any lines that are invalid.
+pub async fn load_ratings() -> eyre::Result<HashMap<String, Rating>> {
+    let Ok(text) = fs::read_to_string(ratings_path().await?).await else {
+        return Ok(HashMap::new());
+    };
+
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (url, rating) = line.split_once('\t')?;
+            Some((url.to_owned(), Rating::parse(rating.trim())?))
+        })
+        .collect())
+}
+
+/// Saves every rated track, replacing whatever was saved before.
+pub async fn save_ratings(ratings: &HashMap<String, Rating>) -> eyre::Result<()> {
+    let mut lines: Vec<String> = ratings
+        .iter()
+        .map(|(url, rating)| format!("{}\t{}\n", url, rating.as_str()))
+        .collect();
+    lines.sort();
+
+    fs::write(ratings_path().await?, lines.concat()).await?;
+
+    Ok(())
+}
+
+/// Gets the path to the file which stores the volume from the last session.
+async fn volume_path() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("volume.txt"))
+}
+
+/// Loads the volume from the last session as a percentage,
+/// which is [None] if it was never saved or is invalid.
+pub async fn load_volume() -> Option<u8> {
+    let text = fs::read_to_string(volume_path().await.ok()?).await.ok()?;
+    let volume: u8 = text.trim().parse().ok()?;
+
+    Some(volume.min(100))
+}
+
+/// Saves the volume as a percentage, so that it can be restored next session.
+pub async fn save_volume(volume: u8) -> eyre::Result<()> {
+    fs::write(volume_path().await?, volume.to_string()).await?;
+
+    Ok(())
+}
+
+/// Gets the path to the file which stores the equalizer settings from the last session.
+async fn equalizer_path() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("equalizer.txt"))
+}
+
+/// Loads the bass & treble gains from the last session,
+/// or [None] if they haven't been saved yet.
+pub async fn load_equalizer() -> Option<(i8, i8)> {
+    let text = fs::read_to_string(equalizer_path().await.ok()?)
+        .await
+        .ok()?;
+    let (bass, treble) = text.trim().split_once(' ')?;
+
+    Some((bass.parse().ok()?, treble.parse().ok()?))
+}
+
+/// Saves the bass & treble gains, so that they can be restored next session.
+pub async fn save_equalizer(bass: i8, treble: i8) -> eyre::Result<()> {
+    fs::write(equalizer_path().await?, format!("{} {}", bass, treble)).await?;
+
+    Ok(())
+}
+
+/// Gets the path to the file which stores the track that was playing when lowfi last quit.
+async fn resume_path() -> eyre::Result<PathBuf> {
+    Ok(data_dir().await?.join("resume.txt"))
+}
+
+/// Loads the track that was playing when lowfi last quit, along with how far into it it was,
+/// or [None] if there isn't one.
+pub async fn load_resume() -> Option<(Entry, Duration)> {
+    let text = fs::read_to_string(resume_path().await.ok()?).await.ok()?;
+    let mut lines = text.lines();
+
+    let (url, name) = lines.next()?.split_once('\t')?;
+    let position: u64 = lines.next()?.trim().parse().ok()?;
+
+    Some((
+        Entry {
+            url: url.to_owned(),
+            name: Some(name.to_owned()),
+        },
+        Duration::from_secs(position),
+    ))
+}
+
+/// Saves the current track & how far into it playback is, so that `--resume` can pick up from there.
+pub async fn save_resume(info: &TrackInfo, position: Duration) -> eyre::Result<()> {
+    let text = format!("{}\t{}\n{}\n", info.url, info.name, position.as_secs());
+    fs::write(resume_path().await?, text).await?;
+
+    Ok(())
+}
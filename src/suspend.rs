@@ -0,0 +1,267 @@
+//! Pauses playback right before the system suspends, so that nothing is blasted
+//! out of the speakers when it wakes up again, which needs the `suspend` feature.
+//!
+//! How the system going to sleep is noticed depends on the platform. On macOS, it's
+//! IOKit's `IORegisterForSystemPower`, which holds off sleeping until lowfi has paused.
+//! Everywhere else, it's logind's `PrepareForSleep` signal, along with a "delay"
+//! inhibitor lock which does the same, so that only works on Linux with systemd or elogind.
+
+use std::sync::Arc;
+
+use tracing::info;
+
+use lowfi::player::Player;
+
+/// Pauses playback since the system is about to suspend, returning whether lowfi was the one who paused it.
+///
+/// The sink is paused directly, since this has to happen before the system is let go to sleep.
+fn sleep(player: &Player) -> bool {
+    let paused = !player.sink.is_paused();
+    if paused {
+        info!("pausing for suspend");
+        player.sink.pause();
+        if let Some(ambience) = &player.ambience {
+            ambience.pause();
+        }
+    }
+
+    paused
+}
+
+/// Picks back up after the system wakes up, where `paused` is what [sleep] returned.
+fn wake(player: &Player, resume: bool, paused: bool) {
+    if resume && paused {
+        info!("resuming after suspend");
+        player.sink.play();
+        if let Some(ambience) = &player.ambience {
+            ambience.resume();
+        }
+    }
+
+    player.publish();
+}
+
+/// Starts listening for the system suspending in the background.
+///
+/// If `resume` is true, then playback is resumed after waking up, but only if lowfi was the one who paused it.
+pub async fn start(player: Arc<Player>, resume: bool) -> eyre::Result<()> {
+    backend::start(player, resume).await
+}
+
+#[cfg(not(target_os = "macos"))]
+mod backend {
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use tokio::task;
+    use tracing::{debug, warn};
+    use zbus::{proxy, zvariant::OwnedFd, Connection};
+
+    use lowfi::player::Player;
+
+    /// The parts of logind's manager interface that lowfi uses.
+    #[proxy(
+        interface = "org.freedesktop.login1.Manager",
+        default_service = "org.freedesktop.login1",
+        default_path = "/org/freedesktop/login1"
+    )]
+    trait Manager {
+        /// Takes an inhibitor lock, which lasts until the file descriptor is closed.
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+        /// Sent right before the system suspends with `true`, and right after it wakes up with `false`.
+        #[zbus(signal)]
+        fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+    }
+
+    /// Takes a lock which delays suspending until it's dropped.
+    async fn inhibit(manager: &ManagerProxy<'_>) -> Option<OwnedFd> {
+        match manager
+            .inhibit("sleep", "lowfi", "Pausing playback", "delay")
+            .await
+        {
+            Ok(lock) => Some(lock),
+            Err(error) => {
+                debug!("unable to take an inhibitor lock: {}", error);
+                None
+            }
+        }
+    }
+
+    pub async fn start(player: Arc<Player>, resume: bool) -> eyre::Result<()> {
+        let connection = Connection::system().await?;
+        let manager = ManagerProxy::new(&connection).await?;
+        let mut signals = manager.receive_prepare_for_sleep().await?;
+
+        task::spawn(async move {
+            let mut lock = inhibit(&manager).await;
+            let mut paused = false;
+
+            while let Some(signal) = signals.next().await {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+
+                if args.start {
+                    paused = super::sleep(&player);
+                    drop(lock.take());
+                } else {
+                    super::wake(&player, resume, paused);
+                    lock = inhibit(&manager).await;
+                }
+            }
+
+            warn!("stopped listening for suspends");
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod backend {
+    use std::{
+        ffi::c_void,
+        ptr,
+        sync::{
+            atomic::{AtomicBool, AtomicU32, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use tokio::sync::oneshot;
+    use tracing::warn;
+
+    use lowfi::player::Player;
+
+    /// IOKit's handle for the root power domain, which is a mach port.
+    type Port = u32;
+
+    /// The message that's sent when the system is asking whether it can go to sleep.
+    const CAN_SLEEP: u32 = 0xe000_0270;
+
+    /// The message that's sent right before the system goes to sleep.
+    const WILL_SLEEP: u32 = 0xe000_0280;
+
+    /// The message that's sent once the system has woken up.
+    const POWERED_ON: u32 = 0xe000_0300;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut *mut c_void,
+            callback: extern "C" fn(*mut c_void, u32, u32, *mut c_void),
+            notifier: *mut u32,
+        ) -> Port;
+
+        fn IONotificationPortGetRunLoopSource(port: *mut c_void) -> *mut c_void;
+
+        fn IOAllowPowerChange(root: Port, notification: isize) -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFRunLoopDefaultMode: *const c_void;
+
+        fn CFRunLoopGetCurrent() -> *mut c_void;
+        fn CFRunLoopAddSource(run_loop: *mut c_void, source: *mut c_void, mode: *const c_void);
+        fn CFRunLoopRun();
+    }
+
+    /// What the callback needs, which lives for as long as lowfi does.
+    struct State {
+        player: Arc<Player>,
+        resume: bool,
+
+        /// Whether lowfi was the one who paused playback for the latest suspend.
+        paused: AtomicBool,
+
+        /// The root power domain, which is only known once the callback has been registered.
+        root: AtomicU32,
+    }
+
+    /// Called by IOKit on the run loop's thread whenever the system is going to sleep or waking up.
+    extern "C" fn callback(refcon: *mut c_void, _: u32, message: u32, argument: *mut c_void) {
+        // SAFETY: The refcon is the leaked `State` from `start`, so it's never freed.
+        let state = unsafe { &*(refcon as *const State) };
+        let root = state.root.load(Ordering::Relaxed);
+
+        match message {
+            // Sleeping is never stopped, it's only ever delayed until lowfi has paused.
+            CAN_SLEEP => {
+                // SAFETY: The argument is the notification ID, which is what IOKit expects back.
+                unsafe { IOAllowPowerChange(root, argument as isize) };
+            }
+            WILL_SLEEP => {
+                state
+                    .paused
+                    .store(super::sleep(&state.player), Ordering::Relaxed);
+
+                // SAFETY: Same as above.
+                unsafe { IOAllowPowerChange(root, argument as isize) };
+            }
+            POWERED_ON => {
+                super::wake(
+                    &state.player,
+                    state.resume,
+                    state.paused.load(Ordering::Relaxed),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    pub async fn start(player: Arc<Player>, resume: bool) -> eyre::Result<()> {
+        let state: &'static State = Box::leak(Box::new(State {
+            player,
+            resume,
+            paused: AtomicBool::new(false),
+            root: AtomicU32::new(0),
+        }));
+
+        let (registered, result) = oneshot::channel();
+
+        // IOKit delivers the notifications through a run loop, which needs a thread all to itself.
+        thread::Builder::new()
+            .name("suspend".to_owned())
+            .spawn(move || {
+                let mut port = ptr::null_mut();
+                let mut notifier = 0;
+
+                // SAFETY: The state outlives the callback, and the run loop is only used on this thread.
+                unsafe {
+                    let root = IORegisterForSystemPower(
+                        state as *const State as *mut c_void,
+                        &mut port,
+                        callback,
+                        &mut notifier,
+                    );
+
+                    if root == 0 {
+                        let _ = registered.send(false);
+                        return;
+                    }
+
+                    state.root.store(root, Ordering::Relaxed);
+                    CFRunLoopAddSource(
+                        CFRunLoopGetCurrent(),
+                        IONotificationPortGetRunLoopSource(port),
+                        kCFRunLoopDefaultMode,
+                    );
+
+                    let _ = registered.send(true);
+                    CFRunLoopRun();
+                }
+
+                warn!("stopped listening for suspends");
+            })?;
+
+        if !result.await.unwrap_or(false) {
+            eyre::bail!("unable to register for system power notifications");
+        }
+
+        Ok(())
+    }
+}
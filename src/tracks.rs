@@ -6,33 +6,35 @@ use std::{io::Cursor, time::Duration};
 
 use bytes::Bytes;
 use inflector::Inflector;
-use rand::Rng;
-use reqwest::Client;
+use reqwest::Url;
 use rodio::{Decoder, Source};
+use tracing::{debug, warn};
 
-/// Downloads a raw track, but doesn't decode it.
-async fn download(track: &str, client: &Client) -> eyre::Result<Bytes> {
-    let url = format!("https://lofigirl.com/wp-content/uploads/{}", track);
-    let response = client.get(url).send().await?;
-    let data = response.bytes().await?;
+pub mod cache;
+pub mod download;
+pub mod hls;
+pub mod list;
+pub mod local;
+#[cfg(feature = "opus")]
+pub mod opus;
+pub mod playlist;
+pub mod ratings;
+pub mod selector;
+pub mod source;
+pub mod station;
+pub mod tags;
+pub mod template;
 
-    Ok(data)
-}
-
-/// Gets a random track from `tracks.txt` and returns it.
-fn random() -> &'static str {
-    let tracks: Vec<&str> = include_str!("../data/tracks.txt")
-        .split_ascii_whitespace()
-        .collect();
+use cache::Cache;
+use download::{Network, Reporter};
+use list::{Entry, List};
+use selector::Selector;
+use tags::Tags;
 
-    let random = rand::thread_rng().gen_range(0..tracks.len());
-    tracks[random]
-}
+/// A decoded track, which is either decoded from its [Bytes] or a [Live](hls::Live) stream.
+pub type DecodedData = Box<dyn Source<Item = f32> + Send>;
 
-/// Just a shorthand for a decoded [Bytes].
-pub type DecodedData = Decoder<Cursor<Bytes>>;
-
-/// The TrackInfo struct, which has the name and duration of a track.
+/// The TrackInfo struct, which has the name, url, duration & tags of a track.
 ///
 /// This is not included in [Track] as the duration has to be acquired
 /// from the decoded data and not from the raw data.
@@ -41,22 +43,32 @@ pub struct TrackInfo {
     /// This is a formatted name, so it doesn't include the full path.
     pub name: String,
 
+    /// The full URL that the track was downloaded from.
+    pub url: String,
+
     /// The duration of the track, this is an [Option] because there are
     /// cases where the duration of a track is unknown.
     pub duration: Option<Duration>,
+
+    /// The artist, read from the track's tags.
+    pub artist: Option<String>,
+
+    /// The album, read from the track's tags.
+    pub album: Option<String>,
+
+    /// The embedded album art, read from the track's tags.
+    pub artwork: Option<Bytes>,
 }
 
 impl TrackInfo {
     /// Formats a name with [Inflector].
     /// This will also strip the first few numbers that are
     /// usually present on most lofi tracks.
-    fn format_name(name: &'static str) -> String {
-        let mut formatted = name
-            .split("/")
-            .nth(2)
-            .unwrap()
-            .strip_suffix(".mp3")
-            .unwrap()
+    pub fn format_name(url: &str) -> String {
+        let file = url.rsplit('/').next().unwrap_or(url);
+        let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+
+        let formatted = stem
             .to_title_case()
             // Inflector doesn't like contractions...
             // Replaces a few very common ones.
@@ -67,28 +79,46 @@ impl TrackInfo {
             .replace(" Ve ", "'ve ")
             .replace(" M ", "'m ");
 
-        let mut skip = 0;
+        formatted
+            .trim_start_matches(|x: char| x.is_ascii_digit())
+            .to_owned()
+    }
 
-        // SAFETY: All of the track names originate with the `'static` lifetime,
-        // SAFETY: so basically this has already been checked.
-        for character in unsafe { formatted.as_bytes_mut() } {
-            if character.is_ascii_digit() {
-                skip += 1;
-            } else {
-                break;
-            }
+    /// Gets the [Entry] that this track came from, so that it can be fetched again.
+    pub fn entry(&self) -> Entry {
+        Entry {
+            url: self.url.clone(),
+            name: Some(self.name.clone()),
         }
-
-        String::from(&formatted[skip..])
     }
 
-    /// Creates a new [`TrackInfo`] from a raw name & decoded track data.
-    pub fn new(name: &'static str, decoded: &DecodedData) -> Self {
+    /// Creates a new [`TrackInfo`] from a formatted name, url, decoded track data & tags.
+    pub fn new(name: String, url: String, decoded: &DecodedData, tags: Tags) -> Self {
         Self {
             duration: decoded.total_duration(),
-            name: Self::format_name(name),
+            name,
+            url,
+            artist: tags.artist,
+            album: tags.album,
+            artwork: tags.artwork,
         }
     }
+
+    /// Gets the name to show for the track, which is "artist – name" if the artist is known.
+    pub fn display_name(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{} – {}", artist, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Gets the website that the track came from, like `lofigirl.com`, which is [None] for local files.
+    pub fn source(&self) -> Option<String> {
+        let url = Url::parse(&self.url).ok()?;
+        let host = url.host_str()?;
+
+        Some(host.strip_prefix("www.").unwrap_or(host).to_owned())
+    }
 }
 
 /// This struct is seperate from [Track] since it is generated lazily from
@@ -99,36 +129,150 @@ pub struct DecodedTrack {
 
     /// The decoded data, which is able to be played by [rodio].
     pub data: DecodedData,
+
+    /// The track's ReplayGain, if it's tagged with one.
+    pub replay_gain: Option<f32>,
 }
 
 impl DecodedTrack {
     /// Creates a new track.
     /// This is equivalent to [Track::decode].
     pub fn new(track: Track) -> eyre::Result<Self> {
-        let data = Decoder::new(Cursor::new(track.data))?;
-        let info = TrackInfo::new(track.name, &data);
+        let (data, tags): (DecodedData, Tags) = match track.stream {
+            Some(stream) => (Box::new(stream.play()), Tags::default()),
+            None => {
+                let tags = Tags::read(&track.data);
+
+                #[cfg(feature = "opus")]
+                if opus::is_opus(&track.data) {
+                    let data: DecodedData = Box::new(opus::Opus::new(track.data)?);
+                    let replay_gain = tags.replay_gain;
+                    let info = TrackInfo::new(track.name, track.url, &data, tags);
+                    return Ok(Self {
+                        info,
+                        data,
+                        replay_gain,
+                    });
+                }
 
-        Ok(Self { info, data })
+                let decoder = Decoder::new(Cursor::new(track.data))?;
+                (Box::new(decoder.convert_samples()), tags)
+            }
+        };
+        let replay_gain = tags.replay_gain;
+        let info = TrackInfo::new(track.name, track.url, &data, tags);
+
+        Ok(Self {
+            info,
+            data,
+            replay_gain,
+        })
     }
 }
 
-/// The main track struct, which only includes data & the track name.
+/// The main track struct, which only includes data, the track name & where it's from.
+///
+/// This is cheap to clone, since the data is reference counted.
+#[derive(Clone)]
 pub struct Track {
-    /// This name is not formatted, and also includes the month & year of the track.
-    pub name: &'static str,
+    /// The formatted name of the track, which is either custom
+    /// or derived from the URL with [TrackInfo::format_name].
+    pub name: String,
+
+    /// The full URL of the track.
+    pub url: String,
 
     /// The raw data of the track, which is not decoded and
     /// therefore much more memory efficient.
+    ///
+    /// This is empty for streams, which are decoded as they're downloaded instead.
     pub data: Bytes,
+
+    /// The HLS stream that's played instead of `data`, if this is one, see [hls].
+    pub stream: Option<hls::Stream>,
 }
 
 impl Track {
-    /// Fetches and downloads a random track from the tracklist.
-    pub async fn random(client: &Client) -> eyre::Result<Self> {
-        let name = random();
-        let data = download(name, client).await?;
+    /// Fetches and downloads a track from the tracklist, which is picked by `selector`.
+    pub async fn pick(
+        list: &List,
+        selector: &dyn Selector,
+        network: &Network,
+        cache: Option<&Cache>,
+        progress: Option<&Reporter>,
+    ) -> eyre::Result<Self> {
+        Self::fetch(list.pick(selector), network, cache, progress).await
+    }
 
-        Ok(Self { data, name })
+    /// Fetches and downloads a specific track.
+    ///
+    /// Local tracks are just read from the disk, and streams are only checked to work.
+    /// Otherwise, if there's a [Cache], then it's checked first, and the track is
+    /// added to it if it had to be downloaded, in which case its progress goes to `progress`.
+    pub async fn fetch(
+        entry: &Entry,
+        network: &Network,
+        cache: Option<&Cache>,
+        progress: Option<&Reporter>,
+    ) -> eyre::Result<Self> {
+        let mut stream = None;
+        let data = if local::is_local(&entry.url) {
+            local::read(&entry.url).await?
+        } else if hls::is_stream(&entry.url) {
+            stream = Some(hls::Stream::open(&entry.url, network).await?);
+            Bytes::new()
+        } else {
+            Self::download(&entry.url, network, cache, progress).await?
+        };
+
+        Ok(Self {
+            data,
+            name: entry
+                .name
+                .clone()
+                .unwrap_or_else(|| TrackInfo::format_name(&entry.url)),
+            url: entry.url.clone(),
+            stream,
+        })
+    }
+
+    /// Downloads a track, going through the [Cache] if there is one.
+    async fn download(
+        url: &str,
+        network: &Network,
+        cache: Option<&Cache>,
+        progress: Option<&Reporter>,
+    ) -> eyre::Result<Bytes> {
+        let cached = match cache {
+            Some(cache) => cache.get(url).await,
+            None => None,
+        };
+
+        Ok(match cached {
+            Some(data) => {
+                debug!("found {} in the cache", url);
+                data
+            }
+            None => {
+                debug!("downloading {}", url);
+
+                // Some URLs only point to where the track actually is, which changes every so often,
+                // so the track is still cached under the URL that doesn't change.
+                let location = source::locate(url, network).await?;
+
+                let data = network
+                    .download(location.as_deref().unwrap_or(url), progress)
+                    .await?;
+                if let Some(cache) = cache {
+                    // A full disk shouldn't stop the music.
+                    if let Err(error) = cache.insert(url, &data).await {
+                        warn!("unable to cache {}: {:#}", url, error);
+                    }
+                }
+
+                data
+            }
+        })
     }
 
     /// This will actually decode and format the track,
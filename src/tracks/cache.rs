@@ -0,0 +1,113 @@
+//! Contains the [`Cache`] struct, which keeps downloaded tracks on disk.
+
+use std::{path::PathBuf, time::SystemTime};
+
+use bytes::Bytes;
+use eyre::eyre;
+use tokio::fs;
+
+/// An on-disk cache of downloaded tracks, which evicts the
+/// least recently played tracks once it gets too big.
+pub struct Cache {
+    /// The directory that all of the tracks are stored in.
+    dir: PathBuf,
+
+    /// The maximum size of all of the tracks in bytes.
+    max_size: u64,
+}
+
+impl Cache {
+    /// Gets the default cache directory, which is usually `~/.cache/lowfi`.
+    ///
+    /// On Windows, the cache directory is just `%LOCALAPPDATA%`, which is where lowfi's
+    /// logs are too, so the cache gets its own directory inside of it. Otherwise, old
+    /// tracks being evicted would take the logs along with them.
+    pub fn default_dir() -> eyre::Result<PathBuf> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| eyre!("unable to find the cache directory"))?
+            .join("lowfi");
+
+        Ok(if cfg!(windows) {
+            dir.join("cache")
+        } else {
+            dir
+        })
+    }
+
+    /// Opens a cache, creating the directory if needed.
+    ///
+    /// `max_size` is in bytes.
+    pub async fn new(dir: PathBuf, max_size: u64) -> eyre::Result<Self> {
+        fs::create_dir_all(&dir).await?;
+
+        Ok(Self { dir, max_size })
+    }
+
+    /// Gets the path that a track with a given URL would be stored at.
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:x}", md5::compute(url)))
+    }
+
+    /// Whether the track is in the cache.
+    pub fn contains(&self, url: &str) -> bool {
+        self.path(url).exists()
+    }
+
+    /// Reads a track from the cache, if it's there.
+    ///
+    /// This also marks the track as recently used, so that it's evicted last.
+    pub async fn get(&self, url: &str) -> Option<Bytes> {
+        let path = self.path(url);
+        let data = fs::read(&path).await.ok()?;
+
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(Bytes::from(data))
+    }
+
+    /// Writes a track to the cache, evicting old tracks if the cache is full.
+    pub async fn insert(&self, url: &str, data: &Bytes) -> eyre::Result<()> {
+        if data.len() as u64 > self.max_size {
+            return Ok(());
+        }
+
+        // The track is only moved into place once it's all there, so that quitting
+        // halfway through can't leave a broken track in the cache.
+        let path = self.path(url);
+        let partial = path.with_extension("part");
+        fs::write(&partial, data).await?;
+        fs::rename(&partial, &path).await?;
+
+        self.evict().await
+    }
+
+    /// Removes the least recently used tracks until the cache fits within `max_size`.
+    async fn evict(&self) -> eyre::Result<()> {
+        let mut files: Vec<(PathBuf, u64, SystemTime)> = Vec::new();
+
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                files.push((entry.path(), metadata.len(), metadata.modified()?));
+            }
+        }
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, size, _) in files {
+            if total <= self.max_size {
+                break;
+            }
+
+            // Another instance of lowfi could've removed it already, which is fine.
+            let _ = fs::remove_file(&path).await;
+            total -= size;
+        }
+
+        Ok(())
+    }
+}
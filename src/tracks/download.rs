@@ -0,0 +1,407 @@
+//! Everything to do with actually downloading tracks, like retrying & rate limiting.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+use eyre::{bail, eyre};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, Client, Method, Proxy, RequestBuilder,
+};
+use rodio::decoder::DecoderError;
+use serde::Deserialize;
+use tokio::{
+    sync::{broadcast, watch},
+    time::sleep,
+};
+use tracing::debug;
+
+use crate::{
+    config,
+    player::{metrics::Metrics, UiMessage},
+};
+
+/// Adds credentials to a request, for something like a [source](super::source) that has to be logged into.
+pub trait Authorize: Send + Sync {
+    /// Adds the credentials to `request`, which is for `url`.
+    fn authorize(&self, request: RequestBuilder, url: &str) -> RequestBuilder;
+}
+
+/// How long to wait after the first failure, which then doubles with every failure after it.
+const BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest that [backoff] will ever wait.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Gets how long to wait before trying again, after failing `failures` times in a row.
+pub fn backoff(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    (BACKOFF * 2u32.pow(doublings)).min(MAX_BACKOFF)
+}
+
+/// Explains why a track couldn't be played, in a way that fits on a single line.
+pub fn describe(error: &eyre::Report) -> String {
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        if error.is_timeout() {
+            "the download timed out".to_owned()
+        } else if error.is_connect() {
+            "couldn't connect to the server".to_owned()
+        } else if let Some(status) = error.status() {
+            format!("the server responded with {}", status)
+        } else {
+            "the download failed".to_owned()
+        }
+    } else if error.downcast_ref::<DecoderError>().is_some() {
+        "the track couldn't be decoded".to_owned()
+    } else {
+        error.to_string()
+    }
+}
+
+/// How far along a download is, which is shown while it's holding up playback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// How many bytes have been downloaded so far.
+    pub downloaded: u64,
+
+    /// How big the track is in bytes, if the server said.
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// How many percent of the track have been downloaded, if its size is known.
+    pub fn percentage(&self) -> Option<u8> {
+        self.total
+            .filter(|x| *x > 0)
+            .map(|total| (self.downloaded * 100 / total).min(100) as u8)
+    }
+
+    /// What's actually shown, which is either the percentage or every tenth of a megabyte,
+    /// so that the UI isn't redrawn for every single chunk.
+    fn step(&self) -> u64 {
+        self.percentage()
+            .map_or(self.downloaded / (1024 * 1024 / 10), u64::from)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.percentage() {
+            Some(percentage) => write!(f, "{}%", percentage),
+            None => write!(f, "{:.1} MB", self.downloaded as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+/// Where the [Progress] of a download is sent, which is [None] while nothing's downloading.
+pub type Reporter = watch::Sender<Option<Progress>>;
+
+/// A download speed in bytes per second, which is written like `256k` or `1.5m`.
+///
+/// The suffixes are powers of 1024, and a plain number is just bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Bandwidth(u64);
+
+impl FromStr for Bandwidth {
+    type Err = eyre::Report;
+
+    fn from_str(text: &str) -> eyre::Result<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        let text = text.strip_suffix("/s").unwrap_or(&text);
+        let text = text.strip_suffix('b').unwrap_or(text);
+
+        let (number, multiplier) = match text.char_indices().last() {
+            Some((i, 'k')) => (&text[..i], 1024.0),
+            Some((i, 'm')) => (&text[..i], 1024.0 * 1024.0),
+            Some((i, 'g')) => (&text[..i], 1024.0 * 1024.0 * 1024.0),
+            _ => (text, 1.0),
+        };
+
+        let number: f64 = number
+            .trim()
+            .parse()
+            .map_err(|_| eyre!("invalid bandwidth '{}', try something like 256k", text))?;
+
+        let bytes = (number * multiplier) as u64;
+        if bytes == 0 {
+            bail!("the bandwidth has to be more than zero");
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl TryFrom<String> for Bandwidth {
+    type Error = eyre::Report;
+
+    fn try_from(text: String) -> eyre::Result<Self> {
+        text.parse()
+    }
+}
+
+/// Limits how fast everything downloads put together, by making each
+/// chunk wait for its turn.
+struct Limiter {
+    /// The limit itself.
+    bandwidth: Bandwidth,
+
+    /// When everything that's been downloaded so far would've finished
+    /// downloading if it had gone at exactly the limit.
+    free: Mutex<Instant>,
+}
+
+impl Limiter {
+    /// Waits until `bytes` more can be downloaded without going over the limit.
+    async fn take(&self, bytes: usize) {
+        let wait = {
+            let mut free = self.free.lock().unwrap_or_else(|x| x.into_inner());
+            let now = Instant::now();
+            let duration = Duration::from_secs_f64(bytes as f64 / self.bandwidth.0 as f64);
+
+            *free = (*free).max(now) + duration;
+            *free - now
+        };
+
+        sleep(wait).await;
+    }
+}
+
+/// The HTTP client & settings that are used for every download.
+pub struct Network {
+    /// The web client, which can contain a UserAgent & some
+    /// settings that help lowfi work more effectively.
+    client: Client,
+
+    /// How many times to try downloading a track before giving up.
+    attempts: u32,
+
+    /// The download speed limit, if there is one.
+    limiter: Option<Limiter>,
+
+    /// How much of a stream is decoded before it starts playing.
+    buffer: Duration,
+
+    /// Where to say that a download is being retried, if anywhere, see [Network::with_messages].
+    messages: Option<broadcast::Sender<UiMessage>>,
+
+    /// Where the downloaded bytes are counted, if anywhere, see [Network::with_metrics].
+    metrics: Option<Arc<Metrics>>,
+
+    /// Every URL prefix that needs credentials, along with what adds them, see [Network::authorize].
+    credentials: RwLock<Vec<(String, Arc<dyn Authorize>)>>,
+}
+
+impl Network {
+    /// Builds the client from the network settings in the config.
+    ///
+    /// The `user_agent` & `headers` from the config are sent with every request,
+    /// where a `User-Agent` in `headers` wins over `user_agent`.
+    ///
+    /// Without a `proxy` in the config, the usual `HTTP_PROXY`, `HTTPS_PROXY`
+    /// & `NO_PROXY` environment variables are used instead.
+    pub fn new(config: &config::Network) -> eyre::Result<Self> {
+        let timeout = Duration::from_secs(config.timeout);
+        let user_agent = config.user_agent.as_deref().unwrap_or(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ));
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| eyre!("invalid header name '{}'", name))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| eyre!("invalid value for the {} header", name))?;
+
+            headers.insert(name, value);
+        }
+
+        let mut builder = Client::builder()
+            .user_agent(user_agent)
+            .default_headers(headers)
+            .connect_timeout(timeout)
+            .read_timeout(timeout);
+
+        if let Some(proxy) = &config.proxy {
+            let proxy = Proxy::all(proxy).map_err(|_| eyre!("invalid proxy url: {}", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+
+        // The certificates are added on top of the system's, rather than replacing them.
+        if let Some(path) = &config.ca_certificates {
+            let pem = std::fs::read(path)
+                .map_err(|error| eyre!("unable to read {}: {}", path.display(), error))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|_| eyre!("{} isn't a valid PEM file", path.display()))?;
+
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder.build()?;
+
+        Ok(Self {
+            client,
+            attempts: config.attempts.max(1),
+            buffer: Duration::from_secs_f32(config.buffer_size),
+            limiter: config.max_bandwidth.map(|bandwidth| Limiter {
+                bandwidth,
+                free: Mutex::new(Instant::now()),
+            }),
+            messages: None,
+            metrics: None,
+            credentials: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Sends a [UiMessage] to `messages` whenever a download fails & is retried.
+    pub fn with_messages(mut self, messages: broadcast::Sender<UiMessage>) -> Self {
+        self.messages = Some(messages);
+        self
+    }
+
+    /// Counts every byte that's downloaded in `metrics`, including the ones from streams.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Where the downloaded bytes are counted, if anywhere.
+    pub fn metrics(&self) -> Option<&Arc<Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// How much of a stream is decoded before it starts playing, and whenever it runs dry.
+    ///
+    /// Everything else is downloaded in full before it's played, so this only matters for streams.
+    pub fn buffer(&self) -> Duration {
+        self.buffer
+    }
+
+    /// Gets the underlying client, so that other requests go through the same proxy.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Makes every download from a URL that starts with `prefix` go through `authorize`.
+    ///
+    /// Authorizing the same prefix again replaces the old credentials, so that a source
+    /// that's loaded again after they've been changed in the config uses the new ones.
+    pub fn authorize(&self, prefix: String, authorize: Arc<dyn Authorize>) {
+        let mut credentials = self.credentials.write().unwrap_or_else(|x| x.into_inner());
+        match credentials.iter_mut().find(|(x, _)| *x == prefix) {
+            Some((_, existing)) => *existing = authorize,
+            None => credentials.push((prefix, authorize)),
+        }
+    }
+
+    /// Starts building a request, which has any credentials that were [authorized](Network::authorize) for the URL.
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        let credentials = self.credentials.read().unwrap_or_else(|x| x.into_inner());
+        match credentials.iter().find(|(x, _)| url.starts_with(x)) {
+            Some((_, authorize)) => authorize.authorize(request, url),
+            None => request,
+        }
+    }
+
+    /// Downloads a single attempt, going no faster than the limit.
+    async fn attempt(&self, url: &str, progress: Option<&Reporter>) -> reqwest::Result<Bytes> {
+        let mut response = self
+            .request(Method::GET, url)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        if self.limiter.is_none() && progress.is_none() {
+            let data = response.bytes().await?;
+            if let Some(metrics) = &self.metrics {
+                metrics.download(data.len() as u64);
+            }
+
+            return Ok(data);
+        }
+
+        let total = response.content_length();
+        let mut data = BytesMut::new();
+        while let Some(chunk) = response.chunk().await? {
+            if let Some(limiter) = &self.limiter {
+                limiter.take(chunk.len()).await;
+            }
+            data.extend_from_slice(&chunk);
+            if let Some(metrics) = &self.metrics {
+                metrics.download(chunk.len() as u64);
+            }
+
+            if let Some(progress) = progress {
+                let new = Progress {
+                    downloaded: data.len() as u64,
+                    total,
+                };
+
+                progress.send_if_modified(|x| {
+                    let changed = x.map(|x| x.step()) != Some(new.step());
+                    *x = Some(new);
+                    changed
+                });
+            }
+        }
+
+        Ok(data.freeze())
+    }
+
+    /// Downloads a raw track, but doesn't decode it.
+    ///
+    /// Failures which might just be a bad connection are retried with a [backoff],
+    /// up to `attempts` times in total. If there's a `progress` [Reporter], then
+    /// the progress is sent to it along the way, and cleared once it's done.
+    pub async fn download(&self, url: &str, progress: Option<&Reporter>) -> eyre::Result<Bytes> {
+        let result = self.retry(url, progress).await;
+        if let Some(progress) = progress {
+            progress.send_replace(None);
+        }
+
+        result
+    }
+
+    /// Keeps on trying to download a track, see [Network::download].
+    async fn retry(&self, url: &str, progress: Option<&Reporter>) -> eyre::Result<Bytes> {
+        let mut failures = 0;
+
+        loop {
+            let error = match self.attempt(url, progress).await {
+                Ok(data) => return Ok(data),
+                Err(error) => error,
+            };
+
+            // Something like a missing track isn't going to fix itself.
+            let temporary = error.is_timeout()
+                || error.is_connect()
+                || error.is_body()
+                || error.status().is_some_and(|x| x.is_server_error());
+
+            failures += 1;
+            if !temporary || failures >= self.attempts {
+                return Err(error.into());
+            }
+
+            let delay = backoff(failures);
+            debug!("retrying in {}s after: {}", delay.as_secs(), error);
+            if let Some(messages) = &self.messages {
+                let _ = messages.send(UiMessage {
+                    message: "download failed, retrying...".to_owned(),
+                    problem: true,
+                });
+            }
+            sleep(delay).await;
+        }
+    }
+}
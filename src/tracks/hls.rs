@@ -0,0 +1,587 @@
+//! Live HLS streams, which are `.m3u8` playlists of short segments that keep getting added to.
+//!
+//! Since a stream never ends, it can't be downloaded up front like a normal track.
+//! Instead, a task fetches the segments one after another, re-fetching the playlist
+//! for new ones, and a thread decodes them as they come in. The segments have to be
+//! plain MP3 or AAC, since there isn't anything that can decode MPEG-TS.
+//!
+//! So that a slow connection doesn't make the stream stutter, a few seconds are
+//! decoded before it starts playing, and again whenever it runs dry.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{
+        mpsc::{self as std_mpsc, Receiver, SyncSender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use eyre::bail;
+use reqwest::{Client, Url};
+use rodio::{source::SeekError, Decoder, Source};
+use tokio::{sync::mpsc, time::sleep};
+use tracing::{debug, warn};
+
+use super::download::Network;
+use crate::player::metrics::Metrics;
+
+/// How many segments are downloaded ahead of the one that's being decoded.
+const SEGMENT_BUFFER: usize = 2;
+
+/// How many chunks of samples are decoded ahead of the one that's being played.
+const CHUNK_BUFFER: usize = 8;
+
+/// How many frames are in a single chunk of samples.
+const CHUNK_FRAMES: usize = 2048;
+
+/// How many segments from the end of a live playlist to start at,
+/// which is what the HLS spec recommends.
+const LIVE_EDGE: usize = 3;
+
+/// How long the stream can go without a new segment before it's given up on.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before trying again after the playlist couldn't be fetched.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Whether a URL points to an HLS playlist.
+pub fn is_stream(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|x| x.path().ends_with(".m3u8"))
+}
+
+/// A parsed HLS playlist.
+#[derive(Debug, Default)]
+struct Playlist {
+    /// How long each segment is at most, which is about how often new ones show up.
+    target: Duration,
+
+    /// The sequence number of the first segment.
+    sequence: u64,
+
+    /// The URLs of the segments, in order.
+    segments: Vec<Url>,
+
+    /// The other playlists that this one points to, along with
+    /// their bandwidth, if this is a master playlist.
+    variants: Vec<(u64, Url)>,
+
+    /// Whether the stream has ended, and no more segments will be added.
+    ended: bool,
+}
+
+impl Playlist {
+    /// Parses a playlist, resolving every URL in it relative to `base`.
+    fn parse(text: &str, base: &Url) -> eyre::Result<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|x| !x.is_empty());
+        if lines.next() != Some("#EXTM3U") {
+            bail!("{} isn't an HLS playlist", base);
+        }
+
+        let mut playlist = Self {
+            target: Duration::from_secs(6),
+            ..Self::default()
+        };
+
+        // The bandwidth of the variant on the next line, if there is one.
+        let mut variant = None;
+        for line in lines {
+            if let Some(tag) = line.strip_prefix('#') {
+                let (name, value) = tag.split_once(':').unwrap_or((tag, ""));
+                match name {
+                    "EXT-X-TARGETDURATION" => {
+                        playlist.target = Duration::from_secs(value.parse().unwrap_or(6));
+                    }
+                    "EXT-X-MEDIA-SEQUENCE" => playlist.sequence = value.parse().unwrap_or(0),
+                    "EXT-X-ENDLIST" => playlist.ended = true,
+                    "EXT-X-KEY" if !value.contains("METHOD=NONE") => {
+                        bail!("{} is encrypted, which isn't supported", base);
+                    }
+                    "EXT-X-STREAM-INF" => {
+                        let bandwidth = value
+                            .split(',')
+                            .find_map(|x| x.strip_prefix("BANDWIDTH="))
+                            .and_then(|x| x.parse().ok());
+                        variant = Some(bandwidth.unwrap_or(0));
+                    }
+                    _ => (),
+                }
+
+                continue;
+            }
+
+            let url = base.join(line)?;
+            match variant.take() {
+                Some(bandwidth) => playlist.variants.push((bandwidth, url)),
+                None => playlist.segments.push(url),
+            }
+        }
+
+        Ok(playlist)
+    }
+
+    /// Fetches & parses a playlist.
+    async fn fetch(client: &Client, url: &Url) -> eyre::Result<Self> {
+        let text = client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Self::parse(&text, url)
+    }
+}
+
+/// An HLS stream which has been checked to work, but hasn't started playing yet.
+///
+/// This is cheap to clone, and each [Stream::play] starts from the live edge again.
+#[derive(Clone, Debug)]
+pub struct Stream {
+    /// The URL of the media playlist, which is the one with the actual segments.
+    url: Url,
+
+    /// The client that the playlist & segments are fetched with.
+    client: Client,
+
+    /// Whether the segments are AAC rather than MP3.
+    aac: bool,
+
+    /// How much is decoded before playing, see [Network::buffer].
+    buffer: Duration,
+
+    /// Where the downloaded segments & any times that the stream runs dry are counted.
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Stream {
+    /// Fetches the playlist at `url` to make sure that it can be played.
+    ///
+    /// If it's a master playlist, then the variant with the most bandwidth is used.
+    /// Once it's playing, the network's [buffer](Network::buffer) is how much is
+    /// decoded before anything is heard.
+    pub async fn open(url: &str, network: &Network) -> eyre::Result<Self> {
+        let client = network.client();
+        let mut url = Url::parse(url)?;
+        let mut playlist = Playlist::fetch(client, &url).await?;
+
+        if let Some((_, variant)) = playlist.variants.iter().max_by_key(|(x, _)| *x) {
+            url = variant.clone();
+            playlist = Playlist::fetch(client, &url).await?;
+        }
+
+        if playlist.segments.is_empty() && playlist.ended {
+            bail!("{} doesn't have any segments", url);
+        }
+
+        if playlist.segments.iter().any(|x| x.path().ends_with(".ts")) {
+            bail!("{} uses MPEG-TS, only MP3 & AAC streams are supported", url);
+        }
+
+        // The segment URLs usually say what they are, and MP3 is the more common of the two.
+        let aac = playlist.segments.iter().any(|x| x.path().ends_with(".aac"));
+
+        Ok(Self {
+            url,
+            client: client.clone(),
+            aac,
+            buffer: network.buffer(),
+            metrics: network.metrics().cloned(),
+        })
+    }
+
+    /// Starts fetching & decoding the stream.
+    ///
+    /// This has to be called from within the tokio runtime.
+    pub fn play(self) -> Live {
+        let (segments, receiver) = mpsc::channel(SEGMENT_BUFFER);
+        let (sender, chunks) = std_mpsc::sync_channel(CHUNK_BUFFER);
+
+        let (aac, buffer, metrics) = (self.aac, self.buffer, self.metrics.clone());
+        tokio::spawn(self.fetch(segments));
+        thread::spawn(move || decode(Segments::new(receiver), aac, sender));
+
+        Live {
+            chunks,
+            chunk: Chunk::silence(2, 44100),
+            position: 0,
+            buffered: VecDeque::new(),
+            length: Duration::ZERO,
+            target: buffer,
+            buffering: true,
+            ended: false,
+            metrics,
+        }
+    }
+
+    /// Fetches segments one by one, until the stream ends or stalls,
+    /// or until nothing is listening anymore.
+    async fn fetch(self, segments: mpsc::Sender<Bytes>) {
+        let mut next = None;
+        let mut progress = Instant::now();
+
+        loop {
+            if segments.is_closed() {
+                return;
+            }
+
+            let playlist = match Playlist::fetch(&self.client, &self.url).await {
+                Ok(playlist) => playlist,
+                Err(error) => {
+                    debug!("unable to fetch the playlist {}: {:#}", self.url, error);
+                    if progress.elapsed() > STALL_TIMEOUT {
+                        warn!("gave up on the stream {}, it stalled", self.url);
+                        return;
+                    }
+
+                    sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            // Anything that's dropped off of the playlist in the meantime is just skipped.
+            let last = playlist.sequence + playlist.segments.len() as u64;
+            let start = next
+                .unwrap_or_else(|| {
+                    if playlist.ended {
+                        playlist.sequence
+                    } else {
+                        last.saturating_sub(LIVE_EDGE as u64)
+                    }
+                })
+                .max(playlist.sequence);
+
+            let skip = (start - playlist.sequence) as usize;
+            for (i, url) in playlist.segments.iter().enumerate().skip(skip) {
+                let data = match self.segment(url).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        debug!("unable to fetch the segment {}: {:#}", url, error);
+                        break;
+                    }
+                };
+
+                if segments.send(data).await.is_err() {
+                    return;
+                }
+
+                next = Some(playlist.sequence + i as u64 + 1);
+                progress = Instant::now();
+            }
+
+            if playlist.ended && next.is_some_and(|x| x >= last) {
+                return;
+            }
+
+            if progress.elapsed() > STALL_TIMEOUT {
+                warn!("gave up on the stream {}, it stalled", self.url);
+                return;
+            }
+
+            // New segments show up about once every target duration.
+            sleep(playlist.target).await;
+        }
+    }
+
+    /// Downloads a single segment, without any ID3 tag at the start.
+    async fn segment(&self, url: &Url) -> eyre::Result<Bytes> {
+        let data = self
+            .client
+            .get(url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        if let Some(metrics) = &self.metrics {
+            metrics.download(data.len() as u64);
+        }
+
+        // MPEG-TS packets always start with the same sync byte.
+        if data.first() == Some(&0x47) && data.get(188) == Some(&0x47) {
+            bail!("the segment is MPEG-TS, which isn't supported");
+        }
+
+        Ok(strip_id3(data))
+    }
+}
+
+/// Removes the ID3 tag that packed audio segments start with, which would otherwise
+/// end up in the middle of the stream once the segments are joined together.
+fn strip_id3(data: Bytes) -> Bytes {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return data;
+    }
+
+    // The size doesn't include the header, or the footer if there is one.
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |size, x| (size << 7) | usize::from(x & 0x7f));
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+
+    data.slice((10 + size + footer).min(data.len())..)
+}
+
+/// Reads the segments one after the other, as if they were one big file.
+struct Segments {
+    /// Where the segments come from.
+    receiver: mpsc::Receiver<Bytes>,
+
+    /// The segment that's being read.
+    current: Bytes,
+
+    /// How far into the current segment has been read.
+    position: usize,
+
+    /// How much was read before the current segment.
+    offset: u64,
+}
+
+impl Segments {
+    fn new(receiver: mpsc::Receiver<Bytes>) -> Self {
+        Self {
+            receiver,
+            current: Bytes::new(),
+            position: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl Read for Segments {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.current.len() {
+            // This blocks, but it's only ever read from the decoding thread.
+            let Some(segment) = self.receiver.blocking_recv() else {
+                return Ok(0);
+            };
+
+            self.offset += self.current.len() as u64;
+            self.current = segment;
+            self.position = 0;
+        }
+
+        let read = buffer.len().min(self.current.len() - self.position);
+        buffer[..read].copy_from_slice(&self.current[self.position..self.position + read]);
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+impl Seek for Segments {
+    /// Only seeking within the current segment works, since the old ones are gone.
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let current = self.offset + self.position as u64;
+        let target = match position {
+            SeekFrom::Start(x) => Some(x),
+            SeekFrom::Current(x) => current.checked_add_signed(x),
+            SeekFrom::End(_) => None,
+        };
+
+        let Some(target) = target
+            .and_then(|x| x.checked_sub(self.offset))
+            .filter(|x| *x <= self.current.len() as u64)
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "a stream can only be seeked within the current segment",
+            ));
+        };
+
+        self.position = target as usize;
+        Ok(self.offset + target)
+    }
+}
+
+/// Decodes the segments into chunks of samples, until either side stops.
+fn decode(segments: Segments, aac: bool, chunks: SyncSender<Chunk>) {
+    let decoder = if aac {
+        Decoder::new_aac(segments)
+    } else {
+        Decoder::new_mp3(segments)
+    };
+
+    let mut decoder = match decoder {
+        Ok(decoder) => decoder.convert_samples::<f32>(),
+        Err(error) => {
+            warn!("unable to decode the stream: {}", error);
+            return;
+        }
+    };
+
+    loop {
+        let (channels, rate) = (decoder.channels(), decoder.sample_rate());
+
+        // A chunk doesn't go past the end of a frame, since the format could change after it.
+        let length = decoder
+            .current_frame_len()
+            .unwrap_or(usize::MAX)
+            .min(CHUNK_FRAMES * usize::from(channels));
+        let samples: Vec<f32> = decoder.by_ref().take(length).collect();
+        if samples.is_empty() {
+            return;
+        }
+
+        let chunk = Chunk {
+            channels,
+            rate,
+            samples,
+        };
+        if chunks.send(chunk).is_err() {
+            return;
+        }
+    }
+}
+
+/// Some decoded samples, which all have the same format.
+struct Chunk {
+    channels: u16,
+    rate: u32,
+    samples: Vec<f32>,
+}
+
+impl Chunk {
+    /// How long the chunk takes to play.
+    fn duration(&self) -> Duration {
+        let frames = self.samples.len() / usize::from(self.channels.max(1));
+        Duration::from_secs_f64(frames as f64 / f64::from(self.rate.max(1)))
+    }
+
+    /// A short bit of silence, which is played while waiting for the stream.
+    fn silence(channels: u16, rate: u32) -> Self {
+        Self {
+            channels,
+            rate,
+            samples: vec![0.0; CHUNK_FRAMES * usize::from(channels)],
+        }
+    }
+}
+
+/// A [Source] that plays a [Stream] as it's being decoded.
+///
+/// If the stream falls behind, then this plays silence until it's buffered
+/// up again, and once the stream ends or is given up on, so does this.
+pub struct Live {
+    /// Where the decoded samples come from.
+    chunks: Receiver<Chunk>,
+
+    /// The chunk that's being played.
+    chunk: Chunk,
+
+    /// How far into the chunk has been played.
+    position: usize,
+
+    /// The chunks that have been decoded ahead of time, which are kept
+    /// here rather than in `chunks` so that it's known how long they are.
+    buffered: VecDeque<Chunk>,
+
+    /// How long all of the `buffered` chunks are put together.
+    length: Duration,
+
+    /// How much should be buffered before playing.
+    target: Duration,
+
+    /// Whether the stream is waiting to buffer up to the `target`, playing silence meanwhile.
+    buffering: bool,
+
+    /// Whether the decoder has stopped, so nothing else is going to be buffered.
+    ended: bool,
+
+    /// Where any times that the stream runs dry are counted.
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Live {
+    /// Picks the chunk to play next, buffering up as many as possible first.
+    fn advance(&mut self) -> Chunk {
+        // There's always at least one chunk taken, so that no buffer at all still plays.
+        while self.length < self.target || self.buffered.is_empty() {
+            match self.chunks.try_recv() {
+                Ok(chunk) => {
+                    self.length += chunk.duration();
+                    self.buffered.push_back(chunk);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.ended = true;
+                    break;
+                }
+            }
+        }
+
+        if self.buffering && (self.length >= self.target || self.ended) {
+            self.buffering = false;
+        }
+
+        let (channels, rate) = (self.chunk.channels, self.chunk.rate);
+        if self.buffering {
+            return Chunk::silence(channels, rate);
+        }
+
+        match self.buffered.pop_front() {
+            Some(chunk) => {
+                self.length = self.length.saturating_sub(chunk.duration());
+                chunk
+            }
+            None if self.ended => Chunk {
+                channels,
+                rate,
+                samples: Vec::new(),
+            },
+            None => {
+                debug!("the stream ran dry, buffering again");
+                self.buffering = true;
+                if let Some(metrics) = &self.metrics {
+                    metrics.underrun();
+                }
+                Chunk::silence(channels, rate)
+            }
+        }
+    }
+}
+
+impl Iterator for Live {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = *self.chunk.samples.get(self.position)?;
+        self.position += 1;
+
+        // The next chunk is loaded straight away, so that its format is known before it plays.
+        if self.position >= self.chunk.samples.len() {
+            self.chunk = self.advance();
+            self.position = 0;
+        }
+
+        Some(sample)
+    }
+}
+
+impl Source for Live {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.chunk.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.chunk.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.chunk.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, _: Duration) -> Result<(), SeekError> {
+        Err(SeekError::NotSupported {
+            underlying_source: "a live stream",
+        })
+    }
+}
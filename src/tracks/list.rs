@@ -0,0 +1,221 @@
+//! Contains the [`List`] struct, which is the list of tracks that lowfi picks from.
+
+use std::{ops::Range, path::Path};
+
+use eyre::{bail, eyre, Context};
+use rand::{distributions::WeightedIndex, prelude::Distribution};
+use reqwest::Url;
+
+use super::{local, playlist, selector::Selector};
+
+/// The base URL of all of the tracks in the built-in list.
+const BASE_URL: &str = "https://lofigirl.com/wp-content/uploads/";
+
+/// A single track in a [List], which hasn't been downloaded yet.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+    /// The full URL of the track.
+    pub url: String,
+
+    /// An optional custom name, which is used instead of
+    /// formatting the name from the URL.
+    pub name: Option<String>,
+}
+
+/// A list of tracks, which can either be the built-in one or a user supplied file,
+/// or a mix of several [sources](super::source).
+pub struct List {
+    /// All of the entries in the list, which is guaranteed to never be empty.
+    entries: Vec<Entry>,
+
+    /// The parts of `entries` that came from each source, along with their weights,
+    /// which is empty unless the list is a [mix](List::mix).
+    groups: Vec<(Range<usize>, f32)>,
+}
+
+impl List {
+    /// Gets the built-in list, which is from `tracks.txt`.
+    pub fn builtin() -> Self {
+        let entries = include_str!("../../data/tracks.txt")
+            .split_ascii_whitespace()
+            .map(|path| Entry {
+                url: format!("{}{}", BASE_URL, path),
+                name: None,
+            })
+            .collect();
+
+        Self::new(entries)
+    }
+
+    /// Creates a list which doesn't go through any validation, from `entries` which aren't empty.
+    fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Creates a list out of some entries, failing if there aren't any.
+    pub fn from_entries(entries: Vec<Entry>) -> eyre::Result<Self> {
+        if entries.is_empty() {
+            bail!("track list doesn't contain any tracks");
+        }
+
+        Ok(Self::new(entries))
+    }
+
+    /// Mixes several lists together, where each list is picked from as
+    /// often as its weight says, no matter how many tracks are in it.
+    ///
+    /// So with weights of `2.0` & `1.0`, two thirds of the tracks come from the first list.
+    pub fn mix(lists: Vec<(List, f32)>) -> eyre::Result<Self> {
+        let mut entries = Vec::new();
+        let mut groups = Vec::new();
+
+        for (list, weight) in lists {
+            let start = entries.len();
+            entries.extend(list.entries);
+            groups.push((start..entries.len(), weight));
+        }
+
+        let mut list = Self::from_entries(entries)?;
+
+        // A single list doesn't need to be weighed against anything.
+        if groups.len() > 1 {
+            list.groups = groups;
+        }
+
+        Ok(list)
+    }
+
+    /// Parses a list from text, where each line is a URL optionally
+    /// followed by a tab & a custom name. Empty lines are ignored.
+    ///
+    /// This also validates every URL, so that a typo is caught at startup
+    /// and not halfway through a listening session.
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let mut entries = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (url, name) = match line.split_once('\t') {
+                Some((url, name)) => (url.trim(), Some(name.trim().to_owned())),
+                None => (line, None),
+            };
+
+            let parsed =
+                Url::parse(url).with_context(|| format!("invalid url on line {}", i + 1))?;
+            if !matches!(parsed.scheme(), "http" | "https" | "file") {
+                bail!(
+                    "unsupported url scheme on line {}: {}",
+                    i + 1,
+                    parsed.scheme()
+                );
+            }
+
+            entries.push(Entry {
+                url: url.to_owned(),
+                name: name.filter(|x| !x.is_empty()),
+            });
+        }
+
+        if entries.is_empty() {
+            bail!("track list doesn't contain any tracks");
+        }
+
+        Ok(Self::new(entries))
+    }
+
+    /// Reads & parses a list from a file. See [List::parse] for the format,
+    /// unless it's an M3U or PLS playlist, see [playlist].
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| eyre!("unable to read {}: {}", path.display(), error))?;
+
+        if !playlist::is_playlist(path) {
+            return Self::parse(&text);
+        }
+
+        let entries = playlist::parse(&text, path)
+            .with_context(|| format!("invalid playlist {}", path.display()))?;
+        if entries.is_empty() {
+            bail!("playlist doesn't contain any tracks");
+        }
+
+        Ok(Self::new(entries))
+    }
+
+    /// Builds a list out of the audio files in a directory, see [local::scan].
+    pub fn scan(dir: &Path) -> eyre::Result<Self> {
+        let entries = local::scan(dir)?;
+        if entries.is_empty() {
+            bail!(
+                "there aren't any audio files in {}, lowfi can play {}",
+                dir.display(),
+                local::EXTENSIONS.join(", ")
+            );
+        }
+
+        Ok(Self::new(entries))
+    }
+
+    /// Only keeps the entries which match `filter`, failing if there aren't any left.
+    pub fn filter(self, filter: impl Fn(&Entry) -> bool) -> eyre::Result<Self> {
+        let mut entries = Vec::new();
+        let mut groups = Vec::new();
+
+        // Every group shrinks along with the entries, and goes away entirely if it's emptied out.
+        let ungrouped = [(0..self.entries.len(), 1.0)];
+        let all = if self.groups.is_empty() {
+            &ungrouped[..]
+        } else {
+            &self.groups[..]
+        };
+
+        for (range, weight) in all {
+            let start = entries.len();
+            entries.extend(
+                self.entries[range.clone()]
+                    .iter()
+                    .filter(|x| filter(x))
+                    .cloned(),
+            );
+            if entries.len() > start {
+                groups.push((start..entries.len(), *weight));
+            }
+        }
+
+        if entries.is_empty() {
+            bail!("none of the tracks in the track list are available");
+        }
+
+        Ok(Self {
+            entries,
+            groups: if groups.len() > 1 { groups } else { Vec::new() },
+        })
+    }
+
+    /// Gets all of the entries in the list.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Picks the next entry to play with a [Selector].
+    ///
+    /// In a mix, the group is picked by its weight first, and then the track from within it.
+    pub fn pick(&self, selector: &dyn Selector) -> &Entry {
+        let entries = match WeightedIndex::new(self.groups.iter().map(|(_, weight)| *weight)) {
+            Ok(index) => {
+                &self.entries[self.groups[index.sample(&mut rand::thread_rng())].0.clone()]
+            }
+            Err(_) => &self.entries[..],
+        };
+
+        let candidates: Vec<&Entry> = entries.iter().collect();
+        candidates[selector.pick(&candidates)]
+    }
+}
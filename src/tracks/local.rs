@@ -0,0 +1,84 @@
+//! Tracks that are played straight from the disk, like with `lowfi play ~/Music/lofi`.
+//!
+//! These go into a [List](super::list::List) just like any other tracks, but with
+//! `file://` URLs, which are read by [read] instead of being downloaded.
+
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use eyre::eyre;
+use reqwest::Url;
+
+use super::list::Entry;
+
+/// The extensions of the audio files that can be played, which depends on the codecs that were built in.
+pub const EXTENSIONS: &[&str] = &[
+    "mp3",
+    "ogg",
+    #[cfg(feature = "flac")]
+    "flac",
+    #[cfg(feature = "opus")]
+    "opus",
+];
+
+/// Whether a URL points to a file on the disk.
+pub fn is_local(url: &str) -> bool {
+    url.starts_with("file://")
+}
+
+/// Finds all of the audio files in `dir`, including the ones in any directories inside of it.
+///
+/// The entries are named after their files, and sorted by their path.
+pub fn scan(dir: &Path) -> eyre::Result<Vec<Entry>> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|error| eyre!("unable to read {}: {}", dir.display(), error))?;
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut dirs = vec![dir];
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|error| eyre!("unable to read {}: {}", dir.display(), error))?;
+
+        for path in entries.filter_map(Result::ok).map(|x| x.path()) {
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .and_then(|x| x.to_str())
+                .is_some_and(|x| EXTENSIONS.contains(&x.to_ascii_lowercase().as_str()))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files
+        .into_iter()
+        .filter_map(|path| {
+            let url = Url::from_file_path(&path).ok()?;
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+
+            Some(Entry {
+                url: url.to_string(),
+                name: Some(name),
+            })
+        })
+        .collect())
+}
+
+/// Reads a track from a `file://` URL.
+pub async fn read(url: &str) -> eyre::Result<Bytes> {
+    let path = Url::parse(url)
+        .ok()
+        .and_then(|x| x.to_file_path().ok())
+        .ok_or_else(|| eyre!("invalid file url: {}", url))?;
+
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|error| eyre!("unable to read {}: {}", path.display(), error))?;
+
+    Ok(Bytes::from(data))
+}
@@ -0,0 +1,179 @@
+//! Decodes Ogg Opus tracks, which [rodio] can't do by itself.
+//!
+//! The Ogg pages are read by [symphonia], just like with any other track, but the
+//! packets are decoded by libopus, which has to be installed for this to link.
+
+use std::{io::Cursor, time::Duration};
+
+use bytes::Bytes;
+use eyre::eyre;
+use lazy_static::lazy_static;
+use rodio::{source::SeekError, Source};
+use symphonia::{
+    core::{
+        audio::SampleBuffer,
+        codecs::{CodecRegistry, Decoder, DecoderOptions},
+        errors::Error,
+        formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+        io::MediaSourceStream,
+        units::Time,
+    },
+    default::formats::OggReader,
+};
+use symphonia_adapter_libopus::OpusDecoder;
+
+lazy_static! {
+    /// The codecs that are tried on the tracks in an Ogg file, which is just Opus.
+    static ref CODECS: CodecRegistry = {
+        let mut codecs = CodecRegistry::new();
+        codecs.register_all::<OpusDecoder>();
+        codecs
+    };
+}
+
+/// Whether `data` is an Ogg file that has Opus in it.
+///
+/// The first page of an Ogg Opus file only has the `OpusHead` packet, which starts
+/// right after the page header, as long as there's only one segment in it.
+pub fn is_opus(data: &[u8]) -> bool {
+    data.starts_with(b"OggS") && data.get(28..36) == Some(b"OpusHead")
+}
+
+/// An Opus track that's decoded a packet at a time while it's being played.
+pub struct Opus {
+    /// Reads the packets out of the Ogg pages.
+    reader: OggReader,
+
+    /// Decodes the packets.
+    decoder: Box<dyn Decoder>,
+
+    /// The ID of the track in the Ogg file that's being played.
+    track: u32,
+
+    /// The samples of the last packet, interleaved.
+    samples: Vec<f32>,
+
+    /// How many of `samples` have been played.
+    position: usize,
+
+    /// The channel count, which doesn't change throughout the track.
+    channels: u16,
+
+    /// The sample rate, which is always 48kHz for Opus unless something is very off.
+    rate: u32,
+
+    /// How long the track is, if the Ogg file says.
+    duration: Option<Duration>,
+}
+
+impl Opus {
+    /// Starts decoding the Opus track in `data`.
+    pub fn new(data: Bytes) -> eyre::Result<Self> {
+        let stream = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let reader = OggReader::try_new(stream, &FormatOptions::default())?;
+
+        let track = reader
+            .default_track()
+            .ok_or_else(|| eyre!("the ogg file doesn't have any tracks"))?;
+        let params = &track.codec_params;
+
+        let decoder = CODECS.make(params, &DecoderOptions::default())?;
+        let duration = params
+            .time_base
+            .zip(params.n_frames)
+            .map(|(base, frames)| base.calc_time(frames))
+            .map(|time| Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac));
+
+        Ok(Self {
+            track: track.id,
+            channels: params.channels.map_or(2, |x| x.count() as u16),
+            rate: params.sample_rate.unwrap_or(48000),
+            duration,
+            decoder,
+            reader,
+            samples: Vec::new(),
+            position: 0,
+        })
+    }
+
+    /// Decodes the next packet into `samples`, returning [false] once the track is over.
+    fn decode(&mut self) -> bool {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) if packet.track_id() == self.track => packet,
+                Ok(_) => continue,
+                Err(_) => return false,
+            };
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let mut buffer = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+                    buffer.copy_interleaved_ref(decoded);
+
+                    self.samples.clear();
+                    self.samples.extend_from_slice(buffer.samples());
+                    self.position = 0;
+
+                    if !self.samples.is_empty() {
+                        return true;
+                    }
+                }
+                // A single broken packet is just a tiny gap, so it's not worth stopping over.
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+impl Iterator for Opus {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.samples.len() && !self.decode() {
+            return None;
+        }
+
+        let sample = self.samples[self.position];
+        self.position += 1;
+
+        Some(sample)
+    }
+}
+
+impl Source for Opus {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        let time = Time::new(position.as_secs(), f64::from(position.subsec_nanos()) / 1e9);
+        self.reader
+            .seek(
+                SeekMode::Coarse,
+                SeekTo::Time {
+                    time,
+                    track_id: Some(self.track),
+                },
+            )
+            .map_err(|error| SeekError::Other(Box::new(error)))?;
+
+        self.decoder.reset();
+        self.samples.clear();
+        self.position = 0;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Parsers for M3U & PLS playlists, so that they can be used as track lists.
+//!
+//! The locations in a playlist can either be URLs, or paths to files which are
+//! relative to the playlist itself. Paths are turned into `file://` URLs,
+//! see [local](super::local).
+
+use std::path::Path;
+
+use eyre::{bail, eyre};
+use reqwest::Url;
+
+use super::list::Entry;
+
+/// Whether a file is a playlist, going by its extension.
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| matches!(x.to_ascii_lowercase().as_str(), "m3u" | "m3u8" | "pls"))
+}
+
+/// Parses the playlist at `path`, which has already been read into `text`.
+pub fn parse(text: &str, path: &Path) -> eyre::Result<Vec<Entry>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let pls = path
+        .extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("pls"));
+
+    if pls {
+        self::pls(text, dir)
+    } else {
+        m3u(text, dir)
+    }
+}
+
+/// Parses an M3U playlist, where the name of each track comes from the `#EXTINF` before it.
+fn m3u(text: &str, dir: &Path) -> eyre::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut name = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+
+        // A stream has its segments listed like tracks, which would be a mess to play one by one.
+        if line.starts_with("#EXT-X-") {
+            bail!("this is an HLS stream, so its URL has to be put into a track list instead");
+        }
+
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            name = info
+                .split_once(',')
+                .map(|(_, x)| x.trim().to_owned())
+                .filter(|x| !x.is_empty());
+            continue;
+        } else if line.starts_with('#') {
+            continue;
+        }
+
+        entries.push(Entry {
+            url: resolve(line, dir).map_err(|error| eyre!("{} on line {}", error, i + 1))?,
+            name: name.take(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parses a PLS playlist, which is an INI file with a `FileN` & an optional `TitleN` for every track.
+fn pls(text: &str, dir: &Path) -> eyre::Result<Vec<Entry>> {
+    let mut files = Vec::new();
+    let mut titles = Vec::new();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+        if let Some(n) = key
+            .strip_prefix("file")
+            .and_then(|x| x.parse::<usize>().ok())
+        {
+            files.push((n, resolve(value, dir)?));
+        } else if let Some(n) = key
+            .strip_prefix("title")
+            .and_then(|x| x.parse::<usize>().ok())
+        {
+            titles.push((n, value.to_owned()));
+        }
+    }
+
+    files.sort_by_key(|(n, _)| *n);
+
+    Ok(files
+        .into_iter()
+        .map(|(n, url)| Entry {
+            url,
+            name: titles
+                .iter()
+                .find(|(x, _)| *x == n)
+                .map(|(_, x)| x.clone())
+                .filter(|x| !x.is_empty()),
+        })
+        .collect())
+}
+
+/// Turns a location in a playlist into a URL, where anything that isn't
+/// already a URL is a path relative to the playlist.
+fn resolve(location: &str, dir: &Path) -> eyre::Result<String> {
+    // On Windows, a path like `C:\Music` would otherwise be read as a URL with a scheme of `c`.
+    if let Ok(url) = Url::parse(location) {
+        if matches!(url.scheme(), "http" | "https" | "file") {
+            return Ok(url.to_string());
+        }
+    }
+
+    let path = dir.join(location);
+    Url::from_file_path(path.canonicalize().unwrap_or(path))
+        .map(|x| x.to_string())
+        .map_err(|_| eyre!("invalid location '{}'", location))
+}
@@ -0,0 +1,79 @@
+//! Tracks which have been liked or blocked, which changes how often they're picked.
+//!
+//! These are saved to `ratings.txt` in the data directory, with one
+//! track per line as its URL, a tab, and then either `liked` or `blocked`.
+
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+};
+
+use crate::storage;
+
+/// What the user thinks of a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rating {
+    /// Liked tracks are picked more often, see [crate::config::Shuffle::like_weight].
+    Liked,
+
+    /// Blocked tracks are never picked again.
+    Blocked,
+}
+
+impl Rating {
+    /// Gets how the rating is written in the file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Liked => "liked",
+            Self::Blocked => "blocked",
+        }
+    }
+
+    /// Parses a rating from the file, which is [None] if it's invalid.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "liked" => Some(Self::Liked),
+            "blocked" => Some(Self::Blocked),
+            _ => None,
+        }
+    }
+}
+
+/// Every rated track, by URL.
+#[derive(Default)]
+pub struct Ratings(RwLock<HashMap<String, Rating>>);
+
+impl Ratings {
+    /// Loads the ratings from the data directory, which are empty if they haven't been saved yet.
+    pub async fn load() -> Self {
+        Self(RwLock::new(
+            storage::load_ratings().await.unwrap_or_default(),
+        ))
+    }
+
+    /// Gets the rating of a track, if it has one.
+    pub fn get(&self, url: &str) -> Option<Rating> {
+        let ratings = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        ratings.get(url).copied()
+    }
+
+    /// Rates a track, or takes its rating away if it already had the same one,
+    /// and then saves the ratings. This returns the new rating.
+    pub async fn toggle(&self, url: &str, rating: Rating) -> eyre::Result<Option<Rating>> {
+        let (new, ratings) = {
+            let mut ratings = self.0.write().unwrap_or_else(PoisonError::into_inner);
+            let new = if ratings.get(url) == Some(&rating) {
+                ratings.remove(url);
+                None
+            } else {
+                ratings.insert(url.to_owned(), rating);
+                Some(rating)
+            };
+
+            (new, ratings.clone())
+        };
+
+        storage::save_ratings(&ratings).await?;
+        Ok(new)
+    }
+}
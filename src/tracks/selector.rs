@@ -0,0 +1,197 @@
+//! Decides which track from a [List] gets played next.
+//!
+//! Every strategy is a [Selector], and they can wrap each other, so for example
+//! [NoRepeat] narrows down the choices before handing them to [Weighted].
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+
+use super::{
+    list::{Entry, List},
+    ratings::{Rating, Ratings},
+};
+use crate::config;
+
+/// How much less likely a track is to be picked after each time that it's failed to load.
+const FAILURE_WEIGHT: f32 = 0.1;
+
+/// The tracks that have failed to load during this session, and how many times,
+/// which makes them less likely to be picked again, see [from_config].
+#[derive(Default)]
+pub struct Failures(Mutex<HashMap<String, i32>>);
+
+impl Failures {
+    /// Records that a track failed to load.
+    pub fn record(&self, url: &str) {
+        let mut failures = self.0.lock().unwrap_or_else(|x| x.into_inner());
+        *failures.entry(url.to_owned()).or_default() += 1;
+    }
+
+    /// How many times a track has failed to load.
+    pub fn get(&self, url: &str) -> i32 {
+        let failures = self.0.lock().unwrap_or_else(|x| x.into_inner());
+        failures.get(url).copied().unwrap_or_default()
+    }
+}
+
+/// A strategy for picking tracks.
+pub trait Selector: Send + Sync {
+    /// Picks one of `candidates`, which is never empty, returning its index.
+    fn pick(&self, candidates: &[&Entry]) -> usize;
+}
+
+/// Picks any track, with every one of them being equally likely.
+pub struct Random;
+
+impl Selector for Random {
+    fn pick(&self, candidates: &[&Entry]) -> usize {
+        rand::thread_rng().gen_range(0..candidates.len())
+    }
+}
+
+/// Gets how likely a track is to be picked, relative to the others.
+pub type Weight = Box<dyn Fn(&Entry) -> f32 + Send + Sync>;
+
+/// Makes some tracks more or less likely than others, where a normal track has a weight of `1.0`.
+pub struct Weighted {
+    /// Gets the weight of each track.
+    weight: Weight,
+}
+
+impl Weighted {
+    /// Creates a selector which weighs every track with `weight`.
+    pub fn new(weight: Weight) -> Self {
+        Self { weight }
+    }
+}
+
+impl Selector for Weighted {
+    fn pick(&self, candidates: &[&Entry]) -> usize {
+        let weights = candidates.iter().map(|x| (self.weight)(x).max(0.0));
+
+        // If every weight is zero, then there's nothing to go off of.
+        match WeightedIndex::new(weights) {
+            Ok(index) => index.sample(&mut rand::thread_rng()),
+            Err(_) => Random.pick(candidates),
+        }
+    }
+}
+
+/// Avoids any of the last few tracks that were picked, and
+/// leaves the choice between the rest up to another [Selector].
+pub struct NoRepeat<S> {
+    /// The selector that picks between the tracks which haven't played recently.
+    inner: S,
+
+    /// How many of the most recent tracks to avoid.
+    window: usize,
+
+    /// The URLs of the most recently picked tracks, with the most recent one first.
+    recent: Mutex<VecDeque<String>>,
+}
+
+impl<S: Selector> NoRepeat<S> {
+    /// Wraps `inner`, so that it never sees any of the last `window` tracks.
+    pub fn new(inner: S, window: usize) -> Self {
+        Self {
+            inner,
+            window,
+            recent: Mutex::new(VecDeque::with_capacity(window)),
+        }
+    }
+}
+
+impl<S: Selector> Selector for NoRepeat<S> {
+    fn pick(&self, candidates: &[&Entry]) -> usize {
+        let mut recent = self.recent.lock().unwrap_or_else(|x| x.into_inner());
+
+        // There always has to be at least one track left to pick from.
+        let window = self.window.min(candidates.len().saturating_sub(1));
+        let fresh: Vec<usize> = (0..candidates.len())
+            .filter(|i| !recent.iter().take(window).any(|x| *x == candidates[*i].url))
+            .collect();
+
+        let index = if fresh.is_empty() {
+            self.inner.pick(candidates)
+        } else {
+            let narrowed: Vec<&Entry> = fresh.iter().map(|i| candidates[*i]).collect();
+            fresh[self.inner.pick(&narrowed)]
+        };
+
+        recent.push_front(candidates[index].url.clone());
+        recent.truncate(self.window);
+
+        index
+    }
+}
+
+/// Never picks blocked tracks, see [Rating::Blocked].
+pub struct Unblocked<S> {
+    /// The selector that picks between the tracks which aren't blocked.
+    inner: S,
+
+    /// The ratings, which are checked every time so that blocking a track takes effect straight away.
+    ratings: Arc<Ratings>,
+}
+
+impl<S: Selector> Unblocked<S> {
+    /// Wraps `inner`, so that it never sees any blocked tracks.
+    pub fn new(inner: S, ratings: Arc<Ratings>) -> Self {
+        Self { inner, ratings }
+    }
+}
+
+impl<S: Selector> Selector for Unblocked<S> {
+    fn pick(&self, candidates: &[&Entry]) -> usize {
+        let allowed: Vec<usize> = (0..candidates.len())
+            .filter(|i| self.ratings.get(&candidates[*i].url) != Some(Rating::Blocked))
+            .collect();
+
+        // If absolutely everything is blocked, then something still has to play.
+        if allowed.is_empty() {
+            return self.inner.pick(candidates);
+        }
+
+        let narrowed: Vec<&Entry> = allowed.iter().map(|i| candidates[*i]).collect();
+        allowed[self.inner.pick(&narrowed)]
+    }
+}
+
+/// Builds the [Selector] described by the config.
+///
+/// `favorites` is the list of favorite tracks, which are weighted by `favorite_weight`,
+/// while liked tracks are weighted by `like_weight`. Tracks which have failed to load
+/// are a lot less likely, see [Failures].
+pub fn from_config(
+    config: &config::Shuffle,
+    favorites: Option<&List>,
+    ratings: Arc<Ratings>,
+    failures: Arc<Failures>,
+) -> Box<dyn Selector> {
+    let favorites: HashSet<String> = favorites
+        .map(|list| list.entries().iter().map(|x| x.url.clone()).collect())
+        .unwrap_or_default();
+
+    let (favorite_weight, like_weight) = (config.favorite_weight, config.like_weight);
+    let liked = Arc::clone(&ratings);
+    let weighted = Weighted::new(Box::new(move |entry| {
+        let mut weight = 1.0;
+        if favorites.contains(&entry.url) {
+            weight *= favorite_weight;
+        }
+        if liked.get(&entry.url) == Some(Rating::Liked) {
+            weight *= like_weight;
+        }
+
+        weight * FAILURE_WEIGHT.powi(failures.get(&entry.url))
+    }));
+
+    Box::new(Unblocked::new(
+        NoRepeat::new(weighted, config.no_repeat),
+        ratings,
+    ))
+}
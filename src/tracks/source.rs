@@ -0,0 +1,240 @@
+//! Where tracks come from, which is anything that implements [TrackSource].
+//!
+//! Every source just lists its tracks as [Entry]s, which are then put into a [List]
+//! that the player picks from, so a new kind of source doesn't need any changes
+//! to the player. Several sources can also be [mixed](List::mix) together, each
+//! with its own weight, with `[[sources]]` in the config.
+
+use std::path::PathBuf;
+
+use eyre::{bail, eyre, Context};
+use futures::future::BoxFuture;
+use reqwest::Url;
+
+use super::{
+    download::Network,
+    list::{Entry, List},
+    station,
+};
+use crate::config;
+
+#[cfg(feature = "sources")]
+pub mod archive;
+#[cfg(feature = "sources")]
+pub mod s3;
+#[cfg(feature = "soundcloud")]
+pub mod soundcloud;
+#[cfg(feature = "sources")]
+pub mod subsonic;
+#[cfg(feature = "sources")]
+pub mod webdav;
+#[cfg(feature = "sources")]
+mod xml;
+#[cfg(feature = "sources")]
+pub mod youtube;
+
+/// Somewhere that tracks can come from.
+pub trait TrackSource: Send + Sync {
+    /// A short description of the source, like the path to a directory, which is used in errors.
+    fn describe(&self) -> String;
+
+    /// Gets all of the tracks from the source, which might involve downloading something.
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>>;
+}
+
+/// A station, see [station].
+pub struct Station(pub String);
+
+impl TrackSource for Station {
+    fn describe(&self) -> String {
+        format!("the {} station", self.0)
+    }
+
+    fn entries<'a>(&'a self, _: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move { Ok(station::load(&self.0)?.entries().to_vec()) })
+    }
+}
+
+/// A track list or a playlist on the disk, see [List::load].
+pub struct File(pub PathBuf);
+
+impl TrackSource for File {
+    fn describe(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    fn entries<'a>(&'a self, _: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move { Ok(List::load(&self.0)?.entries().to_vec()) })
+    }
+}
+
+/// A directory of audio files, see [List::scan].
+pub struct Dir(pub PathBuf);
+
+impl TrackSource for Dir {
+    fn describe(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    fn entries<'a>(&'a self, _: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move { Ok(List::scan(&self.0)?.entries().to_vec()) })
+    }
+}
+
+/// A track list that's downloaded from a URL, in the same format as a [File].
+pub struct Remote(pub Url);
+
+impl TrackSource for Remote {
+    fn describe(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            let data = network.download(self.0.as_str(), None).await?;
+            let text = String::from_utf8(data.to_vec()).context("track list isn't valid text")?;
+
+            Ok(List::parse(&text)?.entries().to_vec())
+        })
+    }
+}
+
+/// A single stream, like an HLS radio station, which plays forever whenever it's picked.
+pub struct Stream(pub Entry);
+
+impl TrackSource for Stream {
+    fn describe(&self) -> String {
+        self.0.url.clone()
+    }
+
+    fn entries<'a>(&'a self, _: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move { Ok(vec![self.0.clone()]) })
+    }
+}
+
+/// Works out the source for a path that was given to lowfi, which is either a directory or a file.
+pub fn from_path(path: PathBuf) -> Box<dyn TrackSource> {
+    if path.is_dir() {
+        Box::new(Dir(path))
+    } else {
+        Box::new(File(path))
+    }
+}
+
+/// Builds the source that's described by a `[[sources]]` entry in the config.
+pub fn from_config(source: &config::Source) -> eyre::Result<Box<dyn TrackSource>> {
+    let mut sources: Vec<Box<dyn TrackSource>> = Vec::new();
+
+    if let Some(name) = &source.station {
+        sources.push(Box::new(Station(name.clone())));
+    }
+    if let Some(location) = &source.list {
+        match Url::parse(location) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                sources.push(Box::new(Remote(url)))
+            }
+            _ => sources.push(Box::new(File(PathBuf::from(location)))),
+        }
+    }
+    if let Some(dir) = &source.dir {
+        sources.push(Box::new(Dir(dir.clone())));
+    }
+    #[cfg(feature = "sources")]
+    if let Some(identifier) = &source.archive {
+        sources.push(Box::new(archive::Archive(identifier.clone())));
+    }
+    #[cfg(feature = "soundcloud")]
+    if let Some(url) = &source.soundcloud {
+        sources.push(Box::new(soundcloud::SoundCloud::new(
+            url.clone(),
+            source.client_id.clone(),
+        )?));
+    }
+    #[cfg(feature = "sources")]
+    if let Some(url) = &source.subsonic {
+        sources.push(Box::new(subsonic::Subsonic::new(
+            url,
+            source.user.clone(),
+            source.password.clone(),
+            source.playlist.clone(),
+        )?));
+    }
+    #[cfg(feature = "sources")]
+    if let Some(url) = &source.webdav {
+        sources.push(Box::new(webdav::WebDav::new(
+            url,
+            source.user.clone(),
+            source.password.clone(),
+        )?));
+    }
+    #[cfg(feature = "sources")]
+    if let Some(url) = &source.s3 {
+        sources.push(Box::new(s3::S3::new(
+            url,
+            source.region.clone(),
+            source.access_key.clone(),
+            source.secret_key.clone(),
+        )?));
+    }
+    #[cfg(feature = "sources")]
+    if let Some(url) = &source.youtube {
+        sources.push(Box::new(youtube::YouTube(url.clone())));
+    }
+    if let Some(url) = &source.stream {
+        Url::parse(url).with_context(|| format!("invalid stream url {}", url))?;
+        sources.push(Box::new(Stream(Entry {
+            url: url.clone(),
+            name: source.name.clone(),
+        })));
+    }
+
+    if sources.len() != 1 {
+        bail!("every source needs exactly one of `station`, `list`, `dir`, `archive`, `subsonic`, `webdav`, `s3`, `youtube` or `stream`");
+    }
+
+    Ok(sources.remove(0))
+}
+
+/// Gets where a track actually is, for URLs that only point to it,
+/// like a [YouTube](youtube) video, which is [None] for anything else.
+pub async fn locate(url: &str, network: &Network) -> eyre::Result<Option<String>> {
+    #[cfg(feature = "sources")]
+    if youtube::is_video(url) {
+        return Ok(Some(youtube::resolve(url).await?));
+    }
+
+    #[cfg(feature = "soundcloud")]
+    if soundcloud::is_media(url) {
+        return Ok(Some(soundcloud::resolve(url, network).await?));
+    }
+
+    #[cfg(not(feature = "soundcloud"))]
+    let _ = network;
+    #[cfg(not(feature = "sources"))]
+    let _ = url;
+
+    Ok(None)
+}
+
+/// Gets the tracks from every source, and mixes them together by their weights.
+pub async fn load(
+    sources: &[(Box<dyn TrackSource>, f32)],
+    network: &Network,
+) -> eyre::Result<List> {
+    let mut lists = Vec::with_capacity(sources.len());
+
+    for (source, weight) in sources {
+        if !weight.is_finite() || *weight <= 0.0 {
+            bail!("the weight of {} has to be above 0", source.describe());
+        }
+
+        let list = source
+            .entries(network)
+            .await
+            .and_then(List::from_entries)
+            .map_err(|error| eyre!("unable to load {}: {:#}", source.describe(), error))?;
+        lists.push((list, *weight));
+    }
+
+    List::mix(lists)
+}
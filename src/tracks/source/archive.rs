@@ -0,0 +1,222 @@
+//! Tracks from a collection on the [Internet Archive](https://archive.org), like `[[sources]] archive = "georgeblood"`.
+//!
+//! The items in the collection are found with the search API, a page at a time, and then the
+//! metadata API lists the files in each item. Only one format is used from every item, since
+//! most of them have the same audio in a few different formats, and even then there can be a
+//! few copies of every track at different bitrates, in which case only the biggest one is kept.
+
+use std::collections::HashMap;
+
+use futures::{future::BoxFuture, stream, StreamExt};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize};
+use tracing::{debug, warn};
+
+use super::TrackSource;
+use crate::tracks::{download::Network, list::Entry, local};
+
+/// The base URL of all of the archive's APIs.
+const BASE_URL: &str = "https://archive.org";
+
+/// How many items are asked for in every page of search results.
+const PAGE_SIZE: usize = 500;
+
+/// How many items have their files listed at once.
+const CONCURRENCY: usize = 8;
+
+/// The formats that are looked for in an item, from most to least preferred,
+/// which are only used if lowfi can actually play them, see [local::EXTENSIONS].
+const FORMATS: [&str; 4] = ["mp3", "ogg", "opus", "flac"];
+
+/// A page of search results.
+#[derive(Deserialize)]
+struct Search {
+    /// The results themselves.
+    response: Results,
+}
+
+/// The results of a search.
+#[derive(Deserialize)]
+struct Results {
+    /// How many results there are in total, across every page.
+    #[serde(rename = "numFound")]
+    found: usize,
+
+    /// The results on this page.
+    docs: Vec<Item>,
+}
+
+/// A single item, like an album or a recording.
+#[derive(Deserialize)]
+struct Item {
+    /// The ID of the item, which is part of the URL of all of its files.
+    identifier: String,
+}
+
+/// The metadata of an item, which is only used to list its files.
+#[derive(Deserialize)]
+struct Metadata {
+    /// Every file in the item, which is missing if the item doesn't exist.
+    #[serde(default)]
+    files: Vec<File>,
+}
+
+/// A file in an item.
+#[derive(Deserialize)]
+struct File {
+    /// The name of the file, which may be inside a directory.
+    name: String,
+
+    /// The title of the track, if it's tagged with one.
+    title: Option<String>,
+
+    /// The file that this was converted from, if it's a copy.
+    original: Option<String>,
+
+    /// The size of the file in bytes, which is a string for some reason.
+    size: Option<String>,
+}
+
+impl File {
+    /// The extension of the file, in lowercase.
+    fn extension(&self) -> Option<String> {
+        self.name
+            .rsplit_once('.')
+            .map(|(_, x)| x.to_ascii_lowercase())
+    }
+
+    /// The size of the file, or `0` if it's unknown.
+    fn size(&self) -> u64 {
+        self.size
+            .as_deref()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// All of the audio in a collection on the archive, going by its identifier.
+///
+/// An item's identifier also works, in which case it's just the audio in that item.
+pub struct Archive(pub String);
+
+impl Archive {
+    /// Downloads & parses some JSON from the archive.
+    async fn get<T: DeserializeOwned>(network: &Network, url: Url) -> eyre::Result<T> {
+        let data = network.download(url.as_str(), None).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Finds the identifiers of every item in the collection, as well as the collection itself.
+    async fn items(&self, network: &Network) -> eyre::Result<Vec<String>> {
+        let query = format!("collection:({0}) OR identifier:({0})", self.0);
+        let mut items = Vec::new();
+
+        for page in 1.. {
+            let url = Url::parse_with_params(
+                &format!("{}/advancedsearch.php", BASE_URL),
+                &[
+                    ("q", query.as_str()),
+                    ("fl[]", "identifier"),
+                    ("rows", &PAGE_SIZE.to_string()),
+                    ("page", &page.to_string()),
+                    ("output", "json"),
+                ],
+            )?;
+
+            let search: Search = Self::get(network, url).await?;
+            let done = search.response.docs.len() < PAGE_SIZE;
+            items.extend(search.response.docs.into_iter().map(|x| x.identifier));
+
+            if done || items.len() >= search.response.found {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Lists the tracks in an item, in whichever format is preferred out of the ones it has.
+    async fn tracks(network: &Network, identifier: String) -> eyre::Result<Vec<Entry>> {
+        let url = Url::parse(&format!("{}/metadata/{}", BASE_URL, identifier))?;
+        let metadata: Metadata = Self::get(network, url).await?;
+
+        let Some(format) = FORMATS
+            .into_iter()
+            .filter(|x| local::EXTENSIONS.contains(x))
+            .find(|x| {
+                metadata
+                    .files
+                    .iter()
+                    .any(|file| file.extension().as_deref() == Some(x))
+            })
+        else {
+            debug!("{} doesn't have any audio in it", identifier);
+            return Ok(Vec::new());
+        };
+
+        // Copies of the same track all have the same original, so only the biggest one is kept.
+        let mut files: Vec<File> = Vec::new();
+        let mut originals: HashMap<String, usize> = HashMap::new();
+        for file in metadata.files {
+            if file.extension().as_deref() != Some(format) {
+                continue;
+            }
+
+            let original = file.original.clone().unwrap_or_else(|| file.name.clone());
+            match originals.get(&original) {
+                Some(&i) if files[i].size() >= file.size() => (),
+                Some(&i) => files[i] = file,
+                None => {
+                    originals.insert(original, files.len());
+                    files.push(file);
+                }
+            }
+        }
+
+        let mut tracks = Vec::new();
+        for file in files {
+            // The name can have spaces & slashes in it, which are escaped one segment at a time.
+            let mut url = Url::parse(&format!("{}/download", BASE_URL))?;
+            url.path_segments_mut()
+                .map_err(|_| eyre::eyre!("invalid base url"))?
+                .push(&identifier)
+                .extend(file.name.split('/'));
+
+            tracks.push(Entry {
+                url: url.to_string(),
+                name: file.title.filter(|x| !x.trim().is_empty()),
+            });
+        }
+
+        Ok(tracks)
+    }
+}
+
+impl TrackSource for Archive {
+    fn describe(&self) -> String {
+        format!("the {} collection on archive.org", self.0)
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            let items = self.items(network).await?;
+            debug!("found {} items in {}", items.len(), self.0);
+
+            // A single broken item isn't worth losing the rest of the collection over.
+            let tracks: Vec<Vec<Entry>> = stream::iter(items)
+                .map(|item| async move {
+                    Self::tracks(network, item.clone())
+                        .await
+                        .unwrap_or_else(|error| {
+                            warn!("unable to list the files in {}: {:#}", item, error);
+                            Vec::new()
+                        })
+                })
+                .buffered(CONCURRENCY)
+                .collect()
+                .await;
+
+            Ok(tracks.into_iter().flatten().collect())
+        })
+    }
+}
@@ -0,0 +1,305 @@
+//! The audio files in an S3 bucket, or anything that's compatible with S3,
+//! like `[[sources]] s3 = "https://s3.eu-west-1.amazonaws.com/bucket/lofi/"`.
+//!
+//! The URL is always path-style, with the bucket first & then an optional prefix
+//! that the files have to start with. Public buckets work without any credentials,
+//! while private ones sign every request with AWS Signature Version 4.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use eyre::{bail, eyre};
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{RequestBuilder, Url};
+use sha2::{Digest, Sha256};
+
+use super::{xml, TrackSource};
+use crate::tracks::{
+    download::{Authorize, Network},
+    list::Entry,
+    local,
+};
+
+/// The characters which are escaped when signing, which is everything but the unreserved ones.
+const ESCAPED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// The region that's used if there isn't one in the config or the URL.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// The hash of the body of every request, which S3 lets be skipped.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// The environment variables that the credentials are read from, if they aren't in the config.
+const ACCESS_KEY_VAR: &str = "AWS_ACCESS_KEY_ID";
+const SECRET_KEY_VAR: &str = "AWS_SECRET_ACCESS_KEY";
+const SESSION_TOKEN_VAR: &str = "AWS_SESSION_TOKEN";
+
+/// Escapes some text for a URL or a signature, optionally leaving slashes alone.
+fn escape(text: &str, slashes: bool) -> String {
+    if slashes {
+        text.split('/')
+            .map(|x| utf8_percent_encode(x, ESCAPED).to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    } else {
+        utf8_percent_encode(text, ESCAPED).to_string()
+    }
+}
+
+/// Signs a message with a key, using HMAC-SHA256.
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signs requests with AWS Signature Version 4.
+struct Signer {
+    /// The ID of the access key.
+    access_key: String,
+
+    /// The secret part of the access key.
+    secret_key: String,
+
+    /// The token of a temporary session, if the key is part of one.
+    session_token: Option<String>,
+
+    /// The region of the bucket, like `eu-west-1`.
+    region: String,
+}
+
+impl Authorize for Signer {
+    fn authorize(&self, request: RequestBuilder, url: &str) -> RequestBuilder {
+        let Ok(url) = Url::parse(url) else {
+            return request;
+        };
+
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let time = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+
+        // Everything in the URL has to be escaped in exactly the same way as S3 does it.
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        let mut query: Vec<String> = url
+            .query_pairs()
+            .map(|(key, value)| format!("{}={}", escape(&key, false), escape(&value, false)))
+            .collect();
+        query.sort();
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_owned()),
+            ("x-amz-date", time.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+
+        let signed = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical = format!(
+            "GET\n{}\n{}\n{}\n{}\n{}",
+            escape(&path, true),
+            query.join("&"),
+            headers
+                .iter()
+                .map(|(name, value)| format!("{}:{}\n", name, value))
+                .collect::<String>(),
+            signed,
+            UNSIGNED_PAYLOAD
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let message = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            time,
+            scope,
+            Sha256::digest(canonical.as_bytes())
+        );
+
+        let key = ["s3", "aws4_request"].iter().fold(
+            hmac(
+                &hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date),
+                &self.region,
+            ),
+            |key, x| hmac(&key, x),
+        );
+        let signature: String = hmac(&key, &message)
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect();
+
+        let mut request = request.header(
+            "Authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed, signature
+            ),
+        );
+
+        // The host header is added by the client itself.
+        for (name, value) in headers.into_iter().skip(1) {
+            request = request.header(name, value);
+        }
+
+        request
+    }
+}
+
+/// The files in a bucket, which start with a prefix.
+pub struct S3 {
+    /// The URL of the bucket, like `https://s3.amazonaws.com/bucket`, without a slash on the end.
+    bucket: String,
+
+    /// What the name of every file has to start with.
+    prefix: String,
+
+    /// The signer, if the bucket isn't public.
+    signer: Option<Arc<Signer>>,
+}
+
+impl S3 {
+    /// Creates the source, where the credentials are either the ones
+    /// that are given or from the environment, and the bucket is public if there aren't any.
+    ///
+    /// The region is worked out from the URL if it isn't given, like `eu-west-1`
+    /// from `s3.eu-west-1.amazonaws.com`.
+    pub fn new(
+        url: &str,
+        region: Option<String>,
+        access_key: Option<String>,
+        secret_key: Option<String>,
+    ) -> eyre::Result<Self> {
+        let parsed = Url::parse(url).map_err(|_| eyre!("invalid s3 url {}", url))?;
+        let path = percent_decode_str(parsed.path().trim_start_matches('/')).decode_utf8_lossy();
+        let (bucket, prefix) = path.split_once('/').unwrap_or((&path, ""));
+        if bucket.is_empty() {
+            bail!("the s3 url needs the bucket in it, like https://s3.amazonaws.com/bucket");
+        }
+
+        let origin = parsed.origin().ascii_serialization();
+        let region = region.unwrap_or_else(|| {
+            parsed
+                .host_str()
+                .and_then(|x| x.strip_prefix("s3."))
+                .and_then(|x| x.strip_suffix(".amazonaws.com"))
+                .unwrap_or(DEFAULT_REGION)
+                .to_owned()
+        });
+
+        let access_key = access_key.or_else(|| std::env::var(ACCESS_KEY_VAR).ok());
+        let secret_key = secret_key.or_else(|| std::env::var(SECRET_KEY_VAR).ok());
+        let signer = match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) => Some(Arc::new(Signer {
+                access_key,
+                secret_key,
+                session_token: std::env::var(SESSION_TOKEN_VAR).ok(),
+                region,
+            })),
+            (None, None) => None,
+            _ => bail!("s3 needs both an `access_key` & a `secret_key`, or neither"),
+        };
+
+        let bucket = format!("{}/{}", origin, escape(bucket, false));
+        Ok(Self {
+            bucket,
+            prefix: prefix.to_owned(),
+            signer,
+        })
+    }
+
+    /// Gets a page of the files in the bucket, along with the token for the next page, if there is one.
+    async fn page(
+        &self,
+        network: &Network,
+        token: Option<&str>,
+    ) -> eyre::Result<(Vec<String>, Option<String>)> {
+        let mut url = Url::parse(&self.bucket)?;
+        url.query_pairs_mut()
+            .append_pair("list-type", "2")
+            .append_pair("prefix", &self.prefix);
+        if let Some(token) = token {
+            url.query_pairs_mut()
+                .append_pair("continuation-token", token);
+        }
+
+        let mut request = network.client().get(url.clone());
+        if let Some(signer) = &self.signer {
+            request = signer.authorize(request, url.as_str());
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        let text = response.text().await?;
+
+        let keys = xml::elements(&text, "Key")
+            .into_iter()
+            .map(xml::unescape)
+            .collect();
+        let next = match xml::element(&text, "IsTruncated") {
+            Some("true") => xml::element(&text, "NextContinuationToken").map(xml::unescape),
+            _ => None,
+        };
+
+        Ok((keys, next))
+    }
+}
+
+impl TrackSource for S3 {
+    fn describe(&self) -> String {
+        format!("{}/{}", self.bucket, self.prefix)
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            if let Some(signer) = &self.signer {
+                network.authorize(
+                    format!("{}/", self.bucket),
+                    Arc::clone(signer) as Arc<dyn Authorize>,
+                );
+            }
+
+            let mut keys = Vec::new();
+            let mut token = None;
+
+            loop {
+                let (mut page, next) = self.page(network, token.as_deref()).await?;
+                keys.append(&mut page);
+
+                match next {
+                    Some(next) => token = Some(next),
+                    None => break,
+                }
+            }
+
+            Ok(keys
+                .into_iter()
+                .filter_map(|key| {
+                    let file = key.rsplit('/').next()?;
+                    let (stem, extension) = file.rsplit_once('.')?;
+                    if !local::EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) {
+                        return None;
+                    }
+
+                    Some(Entry {
+                        name: Some(stem.to_owned()),
+                        url: format!("{}/{}", self.bucket, escape(&key, true)),
+                    })
+                })
+                .collect())
+        })
+    }
+}
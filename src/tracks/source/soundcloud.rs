@@ -0,0 +1,219 @@
+//! Tracks from a SoundCloud playlist, like `[[sources]] soundcloud = "https://soundcloud.com/user/sets/lofi"`.
+//!
+//! SoundCloud only lets apps use its API with a client ID, which has to be in the config or
+//! in `SOUNDCLOUD_CLIENT_ID`. The tracks are found through the API, but the URL of the audio
+//! itself only works for a little while, so each entry is the API's URL for getting it instead,
+//! which is swapped for the real one right before it's downloaded, see [resolve].
+
+use std::collections::HashMap;
+
+use eyre::{bail, eyre};
+use futures::future::BoxFuture;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize};
+use tracing::debug;
+
+use super::TrackSource;
+use crate::tracks::{download::Network, list::Entry};
+
+/// The base URL of the API.
+const API: &str = "https://api-v2.soundcloud.com";
+
+/// The environment variable that the client ID is read from, if it isn't in the config.
+const CLIENT_ID_VAR: &str = "SOUNDCLOUD_CLIENT_ID";
+
+/// The most tracks that can be asked for at once.
+const BATCH_SIZE: usize = 50;
+
+/// Something that a URL resolves to.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Resolved {
+    /// A playlist, which might only have the IDs of most of its tracks.
+    Playlist {
+        /// The tracks in the playlist.
+        tracks: Vec<Track>,
+    },
+
+    /// A single track.
+    Track(Track),
+
+    /// Anything else, like a user.
+    #[serde(other)]
+    Other,
+}
+
+/// A track, where everything but the ID is missing if it's only a stub.
+#[derive(Deserialize)]
+struct Track {
+    /// The ID of the track.
+    id: u64,
+
+    /// The title of the track.
+    title: Option<String>,
+
+    /// The different versions of the audio.
+    media: Option<Media>,
+}
+
+/// The audio of a track.
+#[derive(Deserialize)]
+struct Media {
+    /// The different versions of the audio.
+    transcodings: Vec<Transcoding>,
+}
+
+/// A version of the audio.
+#[derive(Deserialize)]
+struct Transcoding {
+    /// The API URL for getting the actual audio URL.
+    url: String,
+
+    /// How the audio is delivered.
+    format: Format,
+}
+
+/// How a [Transcoding] is delivered.
+#[derive(Deserialize)]
+struct Format {
+    /// Either `progressive` for a single file, or `hls`.
+    protocol: String,
+
+    /// The type of the audio, like `audio/mpeg`.
+    mime_type: String,
+}
+
+/// Where the audio of a track actually is.
+#[derive(Deserialize)]
+struct Location {
+    /// The URL of the audio, which expires after a while.
+    url: String,
+}
+
+/// Downloads & parses some JSON from the API.
+async fn get<T: DeserializeOwned>(network: &Network, url: &Url) -> eyre::Result<T> {
+    let data = network.download(url.as_str(), None).await?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Whether a URL is one that has to be [resolved](resolve) before it's downloaded.
+pub fn is_media(url: &str) -> bool {
+    url.starts_with(&format!("{}/media/", API))
+}
+
+/// Gets the actual URL of a track's audio, from the URL that's in its [Entry].
+pub async fn resolve(url: &str, network: &Network) -> eyre::Result<String> {
+    let location: Location = get(network, &Url::parse(url)?).await?;
+    Ok(location.url)
+}
+
+/// The tracks of a playlist, or just a single track.
+pub struct SoundCloud {
+    /// The URL of the playlist on SoundCloud, like `https://soundcloud.com/user/sets/lofi`.
+    url: String,
+
+    /// The client ID that's sent with every request.
+    client_id: String,
+}
+
+impl SoundCloud {
+    /// Creates the source, with a client ID that's either `client_id` or from the environment.
+    pub fn new(url: String, client_id: Option<String>) -> eyre::Result<Self> {
+        let client_id = client_id
+            .or_else(|| std::env::var(CLIENT_ID_VAR).ok())
+            .ok_or_else(|| {
+                eyre!(
+                    "soundcloud needs a `client_id` in the source, or {}",
+                    CLIENT_ID_VAR
+                )
+            })?;
+
+        Ok(Self { url, client_id })
+    }
+
+    /// Builds an API URL, with the client ID in it.
+    fn api(&self, path: &str, params: &[(&str, &str)]) -> eyre::Result<Url> {
+        let mut url = Url::parse_with_params(&format!("{}{}", API, path), params)?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client_id);
+
+        Ok(url)
+    }
+
+    /// Fills in all of the stubs in `tracks`, keeping them in the same order.
+    async fn complete(&self, network: &Network, tracks: Vec<Track>) -> eyre::Result<Vec<Track>> {
+        let stubs: Vec<String> = tracks
+            .iter()
+            .filter(|x| x.media.is_none())
+            .map(|x| x.id.to_string())
+            .collect();
+
+        let mut full: HashMap<u64, Track> = HashMap::new();
+        for batch in stubs.chunks(BATCH_SIZE) {
+            let url = self.api("/tracks", &[("ids", &batch.join(","))])?;
+            let batch: Vec<Track> = get(network, &url).await?;
+            full.extend(batch.into_iter().map(|x| (x.id, x)));
+        }
+
+        Ok(tracks
+            .into_iter()
+            .filter_map(|x| match x.media {
+                Some(_) => Some(x),
+                None => full.remove(&x.id),
+            })
+            .collect())
+    }
+
+    /// Turns a track into an entry, which is [None] if it can't be played,
+    /// like if it's only available as a stream.
+    fn entry(&self, track: Track) -> Option<Entry> {
+        let transcoding =
+            track.media?.transcodings.into_iter().find(|x| {
+                x.format.protocol == "progressive" && x.format.mime_type == "audio/mpeg"
+            })?;
+
+        let mut url = Url::parse(&transcoding.url).ok()?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client_id);
+
+        Some(Entry {
+            url: url.to_string(),
+            name: track.title.filter(|x| !x.trim().is_empty()),
+        })
+    }
+}
+
+impl TrackSource for SoundCloud {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            let url = self.api("/resolve", &[("url", &self.url)])?;
+            let tracks = match get(network, &url).await? {
+                Resolved::Playlist { tracks } => tracks,
+                Resolved::Track(track) => vec![track],
+                Resolved::Other => bail!("this isn't a playlist or a track"),
+            };
+
+            let total = tracks.len();
+            let entries: Vec<Entry> = self
+                .complete(network, tracks)
+                .await?
+                .into_iter()
+                .filter_map(|x| self.entry(x))
+                .collect();
+
+            if entries.len() < total {
+                debug!(
+                    "{} of the tracks in {} can't be played",
+                    total - entries.len(),
+                    self.url
+                );
+            }
+
+            Ok(entries)
+        })
+    }
+}
@@ -0,0 +1,226 @@
+//! Tracks from a Subsonic server, or anything that speaks its API, like Navidrome.
+//!
+//! The password is never actually sent, since every request is signed with an md5 hash
+//! of it along with a salt. The salt comes from the server & user rather than being random,
+//! so that the URLs of the tracks never change, which means that they can be cached, and
+//! that favorites still work next time.
+
+use eyre::{bail, eyre};
+use futures::future::BoxFuture;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use super::TrackSource;
+use crate::tracks::{download::Network, list::Entry, local};
+
+/// The version of the API that lowfi uses, which is the one that added token authentication.
+const VERSION: &str = "1.13.0";
+
+/// The name that lowfi goes by on the server.
+const CLIENT: &str = "lowfi";
+
+/// The environment variable that the password is read from, if it isn't in the config.
+const PASSWORD_VAR: &str = "SUBSONIC_PASSWORD";
+
+/// How many songs are asked for when there's no playlist, which is the most that's allowed.
+const RANDOM_SONGS: &str = "500";
+
+/// The format that songs are transcoded to, if lowfi can't play them as they are.
+const TRANSCODE_FORMAT: &str = "mp3";
+
+/// The response to `getPlaylists`.
+#[derive(Deserialize)]
+struct Playlists {
+    /// Every playlist, which is missing if there aren't any.
+    #[serde(default)]
+    playlist: Vec<Playlist>,
+}
+
+/// A playlist, without its songs.
+#[derive(Deserialize)]
+struct Playlist {
+    /// The ID of the playlist.
+    id: String,
+
+    /// The name of the playlist.
+    name: String,
+}
+
+/// Some songs, which is what both `getPlaylist` & `getRandomSongs` respond with.
+#[derive(Deserialize)]
+struct Songs {
+    /// The songs, which are called `entry` in a playlist & `song` otherwise.
+    #[serde(default, alias = "song")]
+    entry: Vec<Song>,
+}
+
+/// A single song.
+#[derive(Deserialize)]
+struct Song {
+    /// The ID of the song, which is what it's streamed with.
+    id: String,
+
+    /// The title of the song.
+    title: Option<String>,
+
+    /// The extension of the original file, like `flac`.
+    suffix: Option<String>,
+}
+
+/// A playlist on a Subsonic server, or a random selection of its songs.
+pub struct Subsonic {
+    /// The base URL of the server, like `https://music.example.com`.
+    url: Url,
+
+    /// The name of the user to log in as.
+    user: String,
+
+    /// The md5 hash of the password & `salt`.
+    token: String,
+
+    /// The salt that `token` was made with.
+    salt: String,
+
+    /// The name or ID of the playlist to play, which is random songs if it's [None].
+    playlist: Option<String>,
+}
+
+impl Subsonic {
+    /// Creates the source, with a password that's either `password` or from the environment.
+    pub fn new(
+        url: &str,
+        user: Option<String>,
+        password: Option<String>,
+        playlist: Option<String>,
+    ) -> eyre::Result<Self> {
+        let mut url = Url::parse(url).map_err(|_| eyre!("invalid subsonic url {}", url))?;
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+
+        let user = user.ok_or_else(|| eyre!("subsonic needs a `user` in the source"))?;
+        let password = password
+            .or_else(|| std::env::var(PASSWORD_VAR).ok())
+            .ok_or_else(|| {
+                eyre!(
+                    "subsonic needs a `password` in the source, or {}",
+                    PASSWORD_VAR
+                )
+            })?;
+
+        let salt = format!("{:x}", md5::compute(format!("{}{}", user, url)))[..12].to_owned();
+        let token = format!("{:x}", md5::compute(format!("{}{}", password, salt)));
+
+        Ok(Self {
+            url,
+            user,
+            token,
+            salt,
+            playlist,
+        })
+    }
+
+    /// Builds the URL of an API method, which is signed & has `params` in it.
+    fn method(&self, method: &str, params: &[(&str, &str)]) -> eyre::Result<Url> {
+        let mut url = self.url.join(&format!("rest/{}", method))?;
+        url.query_pairs_mut()
+            .append_pair("u", &self.user)
+            .append_pair("t", &self.token)
+            .append_pair("s", &self.salt)
+            .append_pair("v", VERSION)
+            .append_pair("c", CLIENT)
+            .append_pair("f", "json")
+            .extend_pairs(params);
+
+        Ok(url)
+    }
+
+    /// Calls an API method, and gets the `key` part of the response.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        network: &Network,
+        method: &str,
+        params: &[(&str, &str)],
+        key: &str,
+    ) -> eyre::Result<T> {
+        let url = self.method(method, params)?;
+        let data = network.download(url.as_str(), None).await?;
+
+        let mut body: Value = serde_json::from_slice(&data)?;
+        let response = body
+            .get_mut("subsonic-response")
+            .ok_or_else(|| eyre!("this doesn't look like a subsonic server"))?;
+
+        if response["status"] != "ok" {
+            match response["error"]["message"].as_str() {
+                Some(message) => bail!("{}", message),
+                None => bail!("{} failed", method),
+            }
+        }
+
+        Ok(serde_json::from_value(response[key].take())?)
+    }
+
+    /// Finds the ID of the playlist, which can either be its name or its ID.
+    async fn playlist(&self, network: &Network, playlist: &str) -> eyre::Result<String> {
+        let playlists: Playlists = self.call(network, "getPlaylists", &[], "playlists").await?;
+
+        playlists
+            .playlist
+            .into_iter()
+            .find(|x| x.name == playlist || x.id == playlist)
+            .map(|x| x.id)
+            .ok_or_else(|| eyre!("there isn't a playlist called {}", playlist))
+    }
+
+    /// Turns a song into an entry, which is transcoded if lowfi can't play it.
+    fn entry(&self, song: Song) -> eyre::Result<Entry> {
+        let playable = song
+            .suffix
+            .as_deref()
+            .is_some_and(|x| local::EXTENSIONS.contains(&x.to_ascii_lowercase().as_str()));
+
+        let mut params = vec![("id", song.id.as_str())];
+        if !playable {
+            params.push(("format", TRANSCODE_FORMAT));
+        }
+
+        Ok(Entry {
+            url: self.method("stream", &params)?.to_string(),
+            name: song.title.filter(|x| !x.trim().is_empty()),
+        })
+    }
+}
+
+impl TrackSource for Subsonic {
+    fn describe(&self) -> String {
+        match &self.playlist {
+            Some(playlist) => format!("the {} playlist on {}", playlist, self.url),
+            None => self.url.to_string(),
+        }
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            let songs: Songs = match &self.playlist {
+                Some(playlist) => {
+                    let id = self.playlist(network, playlist).await?;
+                    self.call(network, "getPlaylist", &[("id", &id)], "playlist")
+                        .await?
+                }
+                None => {
+                    self.call(
+                        network,
+                        "getRandomSongs",
+                        &[("size", RANDOM_SONGS)],
+                        "randomSongs",
+                    )
+                    .await?
+                }
+            };
+
+            songs.entry.into_iter().map(|x| self.entry(x)).collect()
+        })
+    }
+}
@@ -0,0 +1,158 @@
+//! The audio files in a directory on a WebDAV share, like `[[sources]] webdav = "https://cloud.example.com/dav/music"`.
+//!
+//! Directories are listed with `PROPFIND`, one level at a time since plenty of servers
+//! don't allow listing everything at once, and any directories inside are listed too.
+
+use std::{collections::HashSet, sync::Arc};
+
+use eyre::{bail, eyre};
+use futures::future::BoxFuture;
+use percent_encoding::percent_decode_str;
+use reqwest::{Method, RequestBuilder, Url};
+
+use super::{xml, TrackSource};
+use crate::tracks::{
+    download::{Authorize, Network},
+    list::Entry,
+    local,
+};
+
+/// The body of every `PROPFIND`, which only asks for whether something is a directory.
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<propfind xmlns="DAV:"><prop><resourcetype/></prop></propfind>"#;
+
+/// The most directories that are listed, in case of something like a symlink loop.
+const MAX_DIRS: usize = 1000;
+
+/// Basic authentication, which is what WebDAV shares pretty much always use.
+struct Basic {
+    /// The name of the user.
+    user: String,
+
+    /// The user's password.
+    password: Option<String>,
+}
+
+impl Authorize for Basic {
+    fn authorize(&self, request: RequestBuilder, _: &str) -> RequestBuilder {
+        request.basic_auth(&self.user, self.password.as_ref())
+    }
+}
+
+/// A directory on a WebDAV share.
+pub struct WebDav {
+    /// The URL of the directory, which always ends in a slash.
+    url: Url,
+
+    /// The credentials for the share, if it needs them.
+    auth: Option<Arc<Basic>>,
+}
+
+impl WebDav {
+    /// Creates the source, which logs in as `user` if there is one.
+    ///
+    /// Every track that's downloaded from the share is authenticated too.
+    pub fn new(url: &str, user: Option<String>, password: Option<String>) -> eyre::Result<Self> {
+        let mut url = Url::parse(url).map_err(|_| eyre!("invalid webdav url {}", url))?;
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+
+        let auth = user.map(|user| Arc::new(Basic { user, password }));
+        Ok(Self { url, auth })
+    }
+
+    /// Lists a single directory, returning the URLs of the files & directories inside of it.
+    async fn list(&self, network: &Network, dir: &Url) -> eyre::Result<(Vec<Url>, Vec<Url>)> {
+        let mut request = network
+            .client()
+            .request(Method::from_bytes(b"PROPFIND")?, dir.clone())
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND);
+        if let Some(auth) = &self.auth {
+            request = auth.authorize(request, dir.as_str());
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        let text = response.text().await?;
+
+        let (mut files, mut dirs) = (Vec::new(), Vec::new());
+        for response in xml::elements(&text, "response") {
+            let Some(href) = xml::element(response, "href") else {
+                continue;
+            };
+
+            // The directory itself is in the listing too, though maybe without the slash on the end.
+            let url = dir.join(&xml::unescape(href.trim()))?;
+            if url.path().trim_end_matches('/') == dir.path().trim_end_matches('/') {
+                continue;
+            }
+
+            if xml::element(response, "collection").is_some() {
+                dirs.push(url);
+            } else {
+                files.push(url);
+            }
+        }
+
+        Ok((files, dirs))
+    }
+}
+
+impl TrackSource for WebDav {
+    fn describe(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn entries<'a>(&'a self, network: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            // The whole server is authorized, since servers don't always escape paths the same way.
+            if let Some(auth) = &self.auth {
+                let origin = format!("{}/", self.url.origin().ascii_serialization());
+                network.authorize(origin, Arc::clone(auth) as Arc<dyn Authorize>);
+            }
+
+            let mut files = Vec::new();
+            let mut dirs = vec![self.url.clone()];
+            let mut seen = HashSet::new();
+
+            while let Some(dir) = dirs.pop() {
+                if !seen.insert(dir.path().trim_end_matches('/').to_owned()) {
+                    continue;
+                }
+                if seen.len() > MAX_DIRS {
+                    bail!("there are more than {} directories", MAX_DIRS);
+                }
+
+                let (mut found, inside) = self.list(network, &dir).await?;
+                files.append(&mut found);
+                dirs.extend(
+                    inside
+                        .into_iter()
+                        .filter(|x| x.path().starts_with(self.url.path())),
+                );
+            }
+
+            files.sort_by(|a, b| a.path().cmp(b.path()));
+
+            Ok(files
+                .into_iter()
+                .filter_map(|url| {
+                    let file = percent_decode_str(url.path_segments()?.next_back()?)
+                        .decode_utf8_lossy()
+                        .into_owned();
+                    let (stem, extension) = file.rsplit_once('.')?;
+                    if !local::EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) {
+                        return None;
+                    }
+
+                    Some(Entry {
+                        name: Some(stem.to_owned()),
+                        url: url.to_string(),
+                    })
+                })
+                .collect())
+        })
+    }
+}
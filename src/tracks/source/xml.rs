@@ -0,0 +1,60 @@
+//! Just enough XML parsing for the listings from [WebDAV](super::webdav) & [S3](super::s3).
+//!
+//! This only looks at elements by their name, ignoring any namespace prefix,
+//! since the prefixes that servers use are all over the place.
+
+/// Finds every element called `name`, like `href` for `<d:href>`, returning what's inside of each.
+///
+/// Elements with the same name inside of each other aren't supported, but nothing needs them.
+pub fn elements<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+
+        let tag = &rest[..end];
+        let full = tag
+            .split(|x: char| x.is_whitespace() || x == '/')
+            .next()
+            .unwrap_or_default();
+        let local = full.rsplit(':').next().unwrap_or(full);
+
+        if full.is_empty() || local != name {
+            continue;
+        }
+
+        rest = &rest[end + 1..];
+        if tag.ends_with('/') {
+            found.push("");
+            continue;
+        }
+
+        let close = format!("</{}>", full);
+        let Some(inner) = rest.find(&close) else {
+            break;
+        };
+
+        found.push(&rest[..inner]);
+        rest = &rest[inner + close.len()..];
+    }
+
+    found
+}
+
+/// Gets what's inside of the first element called `name`, see [elements].
+pub fn element<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    elements(text, name).into_iter().next()
+}
+
+/// Turns the escapes in some text back into the characters that they stand for.
+pub fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
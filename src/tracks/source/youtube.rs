@@ -0,0 +1,198 @@
+//! Tracks from a YouTube playlist or channel, like `[[sources]] youtube = "https://www.youtube.com/playlist?list=..."`.
+//!
+//! Everything goes through [yt-dlp](https://github.com/yt-dlp/yt-dlp), which has to be installed
+//! separately. Each entry is just the URL of the video, since the URL of the audio itself only
+//! works for a few hours, so that's swapped for the real one right before it's downloaded,
+//! see [resolve]. The real URLs are kept around until they expire, so that retrying a download
+//! doesn't mean running yt-dlp all over again.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    process::Stdio,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use eyre::{bail, eyre};
+use futures::future::BoxFuture;
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::{process::Command, time::timeout};
+use tracing::debug;
+
+use super::TrackSource;
+use crate::tracks::{download::Network, list::Entry};
+
+/// The name of the yt-dlp binary, which has to be somewhere in the `PATH`.
+const BINARY: &str = "yt-dlp";
+
+/// The formats that yt-dlp is asked for, in order of preference, which are all ones that lowfi can decode.
+const FORMAT: &str = "bestaudio[ext=m4a]/bestaudio[ext=mp3]/best[ext=mp4]";
+
+/// The longest that yt-dlp gets to find the audio of a single video.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The longest that yt-dlp gets to list a whole playlist.
+const LIST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a resolved URL is kept if it doesn't say when it expires.
+const DEFAULT_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// How long before a resolved URL expires that it's thrown away, so that it doesn't expire mid-download.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// The audio URLs that have already been resolved, along with when they stop working.
+static RESOLVED: Mutex<BTreeMap<String, (String, Instant)>> = Mutex::new(BTreeMap::new());
+
+/// A video in the output of `--flat-playlist`.
+#[derive(Deserialize)]
+struct Video {
+    /// The ID of the video.
+    id: String,
+
+    /// The title of the video.
+    title: Option<String>,
+
+    /// What kind of thing this is, which is `Youtube` for a video in a playlist.
+    ie_key: Option<String>,
+
+    /// The same as `ie_key`, but for a single video rather than a playlist.
+    extractor_key: Option<String>,
+}
+
+/// Runs yt-dlp with some arguments, and gets what it printed.
+async fn run(args: &[&str], limit: Duration) -> eyre::Result<String> {
+    let child = Command::new(BINARY)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => eyre!(
+                "{} isn't installed, see https://github.com/yt-dlp/yt-dlp",
+                BINARY
+            ),
+            _ => eyre!("unable to run {}: {}", BINARY, error),
+        })?;
+
+    let output = timeout(limit, child.wait_with_output())
+        .await
+        .map_err(|_| eyre!("{} took too long", BINARY))??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.lines().rev().find(|x| !x.trim().is_empty()) {
+            Some(line) => bail!("{}", line.trim().trim_start_matches("ERROR: ")),
+            None => bail!("{} failed with {}", BINARY, output.status),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Works out when an audio URL expires, which YouTube puts in the URL itself.
+fn expiry(url: &str) -> Instant {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let left = Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "expire")
+                .and_then(|(_, value)| value.parse::<u64>().ok())
+        })
+        .map_or(DEFAULT_EXPIRY, |expire| {
+            Duration::from_secs(expire).saturating_sub(now)
+        });
+
+    Instant::now() + left.saturating_sub(EXPIRY_MARGIN)
+}
+
+/// Whether a URL is a YouTube video, which has to be [resolved](resolve) before it's downloaded.
+pub fn is_video(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+
+    match url.host_str() {
+        Some("youtu.be") => true,
+        Some("www.youtube.com" | "youtube.com" | "music.youtube.com" | "m.youtube.com") => {
+            url.path() == "/watch"
+        }
+        _ => false,
+    }
+}
+
+/// Gets the actual URL of a video's audio, using one that's already been resolved if it still works.
+pub async fn resolve(url: &str) -> eyre::Result<String> {
+    {
+        let mut resolved = RESOLVED.lock().unwrap_or_else(|x| x.into_inner());
+        resolved.retain(|_, (_, expires)| *expires > Instant::now());
+        if let Some((location, _)) = resolved.get(url) {
+            return Ok(location.clone());
+        }
+    }
+
+    debug!("resolving {} with {}", url, BINARY);
+    let output = run(
+        &["--no-warnings", "--no-playlist", "-f", FORMAT, "-g", url],
+        RESOLVE_TIMEOUT,
+    )
+    .await?;
+
+    let location = output
+        .lines()
+        .map(str::trim)
+        .find(|x| !x.is_empty())
+        .ok_or_else(|| eyre!("{} didn't find any audio", BINARY))?
+        .to_owned();
+
+    RESOLVED
+        .lock()
+        .unwrap_or_else(|x| x.into_inner())
+        .insert(url.to_owned(), (location.clone(), expiry(&location)));
+
+    Ok(location)
+}
+
+/// The videos in a playlist or on a channel, or just a single video.
+pub struct YouTube(pub String);
+
+impl TrackSource for YouTube {
+    fn describe(&self) -> String {
+        self.0.clone()
+    }
+
+    fn entries<'a>(&'a self, _: &'a Network) -> BoxFuture<'a, eyre::Result<Vec<Entry>>> {
+        Box::pin(async move {
+            let output = run(
+                &["--no-warnings", "--flat-playlist", "--dump-json", &self.0],
+                LIST_TIMEOUT,
+            )
+            .await?;
+
+            let mut entries = Vec::new();
+            for line in output.lines().filter(|x| !x.trim().is_empty()) {
+                let video: Video = serde_json::from_str(line)?;
+
+                // Channels also list their tabs, like shorts, which aren't videos.
+                let key = video.ie_key.as_deref().or(video.extractor_key.as_deref());
+                if key != Some("Youtube") {
+                    continue;
+                }
+
+                entries.push(Entry {
+                    url: format!("https://www.youtube.com/watch?v={}", video.id),
+                    name: video.title.filter(|x| !x.trim().is_empty()),
+                });
+            }
+
+            Ok(entries)
+        })
+    }
+}
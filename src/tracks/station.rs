@@ -0,0 +1,60 @@
+//! Stations, which are named track lists that can be switched between while lowfi is running.
+//!
+//! The built-in list is always available as [DEFAULT], and every other station
+//! is a track list file in the stations directory, named after its file.
+
+use std::path::PathBuf;
+
+use eyre::{bail, eyre};
+
+use super::list::List;
+
+/// The name of the station which plays the built-in list.
+pub const DEFAULT: &str = "lofi";
+
+/// The directory that station files are read from, which is `stations` in the config directory.
+pub fn dir() -> eyre::Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| eyre!("unable to find the config directory"))?
+        .join("lowfi")
+        .join("stations"))
+}
+
+/// Gets the names of all of the stations, starting with [DEFAULT] & then sorted alphabetically.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = dir()
+        .and_then(|dir| Ok(std::fs::read_dir(dir)?))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|x| x == "txt"))
+                .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+                .filter(|name| name != DEFAULT)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    names.sort();
+    names.insert(0, DEFAULT.to_owned());
+
+    names
+}
+
+/// Loads the track list of a station.
+pub fn load(name: &str) -> eyre::Result<List> {
+    if name == DEFAULT {
+        return Ok(List::builtin());
+    }
+
+    let path = dir()?.join(format!("{}.txt", name));
+    if !path.exists() {
+        bail!(
+            "unknown station '{}', the stations are: {}",
+            name,
+            names().join(", ")
+        );
+    }
+
+    List::load(&path)
+}
@@ -0,0 +1,97 @@
+//! Reads the metadata tags of tracks, like ID3 frames or Vorbis comments.
+
+use std::io::Cursor;
+
+use bytes::Bytes;
+use lofty::{
+    config::ParseOptions,
+    file::TaggedFileExt,
+    picture::PictureType,
+    probe::Probe,
+    tag::{Accessor, ItemKey, Tag},
+};
+
+/// The tags of a track that lowfi cares about, any of which can be missing.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Tags {
+    /// The artist of the track.
+    pub artist: Option<String>,
+
+    /// The album that the track is from.
+    pub album: Option<String>,
+
+    /// The embedded album art, which is usually a JPEG or PNG.
+    pub artwork: Option<Bytes>,
+
+    /// The ReplayGain of the track as a factor to multiply its samples by,
+    /// which is lowered if it'd make the track's peak clip.
+    pub replay_gain: Option<f32>,
+}
+
+impl Tags {
+    /// Reads the tags from the raw data of a track.
+    ///
+    /// Tags are only a nice to have, so this just returns empty ones if they can't be read.
+    pub fn read(data: &Bytes) -> Self {
+        let options = ParseOptions::new().read_properties(false);
+
+        let tagged = Probe::new(Cursor::new(data))
+            .options(options)
+            .guess_file_type()
+            .ok()
+            .and_then(|probe| probe.read().ok());
+
+        let Some(tag) = tagged
+            .as_ref()
+            .and_then(|x| x.primary_tag().or(x.first_tag()))
+        else {
+            return Self::default();
+        };
+
+        // The front cover is preferred, but any picture is better than none.
+        // Without the `artwork` feature it'd never be drawn, so it isn't kept around.
+        let pictures = tag.pictures();
+        let artwork = pictures
+            .iter()
+            .find(|x| x.pic_type() == PictureType::CoverFront)
+            .or(pictures.first())
+            .filter(|_| cfg!(feature = "artwork"))
+            .map(|x| Bytes::copy_from_slice(x.data()));
+
+        Self {
+            artist: Self::text(tag, Tag::artist),
+            album: Self::text(tag, Tag::album),
+            artwork,
+            replay_gain: Self::replay_gain(tag),
+        }
+    }
+
+    /// Reads the ReplayGain of the track, which is tagged in decibels like `-6.48 dB`.
+    fn replay_gain(tag: &Tag) -> Option<f32> {
+        let number = |key| {
+            tag.get_string(key)?
+                .trim()
+                .trim_end_matches(|x: char| x.is_ascii_alphabetic())
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|x| x.is_finite())
+        };
+
+        let gain = 10f32.powf(number(ItemKey::ReplayGainTrackGain)? / 20.0);
+        Some(match number(ItemKey::ReplayGainTrackPeak) {
+            Some(peak) if peak > 0.0 => gain.min(1.0 / peak),
+            _ => gain,
+        })
+    }
+
+    /// Gets a single text field from a tag, ignoring it if it's blank.
+    fn text<'a, T: AsRef<str>>(
+        tag: &'a Tag,
+        field: impl Fn(&'a Tag) -> Option<T>,
+    ) -> Option<String> {
+        field(tag)
+            .map(|x| x.as_ref().trim().to_owned())
+            .filter(|x| !x.is_empty())
+    }
+}
@@ -0,0 +1,184 @@
+//! A tiny template language for how track names are shown in the UI, like `{title}[ — {artist}]`.
+//!
+//! Fields are written in braces, and can be `title`, `artist`, `album` or `source`, which
+//! is the website that the track came from. Anything in square brackets is left out
+//! if one of the fields inside of it isn't known, and if one outside of brackets
+//! isn't known, then the usual [display name](super::TrackInfo::display_name) is shown instead.
+
+use std::str::FromStr;
+
+use eyre::{bail, eyre};
+use serde::Deserialize;
+
+use super::TrackInfo;
+
+/// Something about a track that can be put into a [Template].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    Source,
+}
+
+impl Field {
+    /// Gets the value of the field for `info`, which is [None] if it isn't known.
+    fn value(self, info: &TrackInfo) -> Option<String> {
+        let value = match self {
+            Self::Title => Some(info.name.clone()),
+            Self::Artist => info.artist.clone(),
+            Self::Album => info.album.clone(),
+            Self::Source => info.source(),
+        };
+
+        value.filter(|x| !x.trim().is_empty())
+    }
+}
+
+impl FromStr for Field {
+    type Err = eyre::Report;
+
+    fn from_str(name: &str) -> eyre::Result<Self> {
+        Ok(match name.trim() {
+            "title" => Self::Title,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "source" => Self::Source,
+            _ => bail!(
+                "unknown field '{{{}}}', which can be title, artist, album or source",
+                name
+            ),
+        })
+    }
+}
+
+/// A single piece of a [Template].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    /// Text that's shown as it is.
+    Text(String),
+
+    /// A [Field] that's filled in.
+    Field(Field),
+
+    /// Parts which are only shown if every field in them is known.
+    Optional(Vec<Part>),
+}
+
+/// Moves the text that's been read so far onto the end of `parts`, if there is any.
+fn flush(parts: &mut Vec<Part>, literal: &mut String) {
+    if !literal.is_empty() {
+        parts.push(Part::Text(std::mem::take(literal)));
+    }
+}
+
+/// Fills in `parts` for `info`, which is [None] if one of the fields isn't known.
+fn render(parts: &[Part], info: &TrackInfo) -> Option<String> {
+    let mut rendered = String::new();
+    for part in parts {
+        match part {
+            Part::Text(text) => rendered.push_str(text),
+            Part::Field(field) => rendered.push_str(&field.value(info)?),
+            Part::Optional(parts) => {
+                if let Some(text) = render(parts, info) {
+                    rendered.push_str(&text);
+                }
+            }
+        }
+    }
+
+    Some(rendered)
+}
+
+/// How a track's name is shown, which is parsed from something like `{title}[ — {artist}]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    /// Formats the name of `info`, falling back to its display name if a field that's needed is missing.
+    pub fn format(&self, info: &TrackInfo) -> String {
+        render(&self.0, info).unwrap_or_else(|| info.display_name())
+    }
+}
+
+impl Default for Template {
+    /// The same as [TrackInfo::display_name], which is `[{artist} – ]{title}`.
+    fn default() -> Self {
+        Self(vec![
+            Part::Optional(vec![
+                Part::Field(Field::Artist),
+                Part::Text(" – ".to_owned()),
+            ]),
+            Part::Field(Field::Title),
+        ])
+    }
+}
+
+impl FromStr for Template {
+    type Err = eyre::Report;
+
+    fn from_str(text: &str) -> eyre::Result<Self> {
+        let mut parts = Vec::new();
+        let mut optional: Option<Vec<Part>> = None;
+        let mut literal = String::new();
+        let mut chars = text.chars();
+
+        while let Some(character) = chars.next() {
+            match character {
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for character in chars.by_ref() {
+                        if character == '}' {
+                            closed = true;
+                            break;
+                        }
+
+                        name.push(character);
+                    }
+
+                    if !closed {
+                        bail!("the format has a '{{' without a '}}'");
+                    }
+
+                    let current = optional.as_mut().unwrap_or(&mut parts);
+                    flush(current, &mut literal);
+                    current.push(Part::Field(name.parse()?));
+                }
+                '[' => {
+                    if optional.is_some() {
+                        bail!("the format can't have brackets inside of brackets");
+                    }
+
+                    flush(&mut parts, &mut literal);
+                    optional = Some(Vec::new());
+                }
+                ']' => {
+                    let mut inner = optional
+                        .take()
+                        .ok_or_else(|| eyre!("the format has a ']' without a '['"))?;
+                    flush(&mut inner, &mut literal);
+                    parts.push(Part::Optional(inner));
+                }
+                '}' => bail!("the format has a '}}' without a '{{'"),
+                _ => literal.push(character),
+            }
+        }
+
+        if optional.is_some() {
+            bail!("the format has a '[' without a ']'");
+        }
+        flush(&mut parts, &mut literal);
+
+        Ok(Self(parts))
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = eyre::Report;
+
+    fn try_from(text: String) -> eyre::Result<Self> {
+        text.parse()
+    }
+}
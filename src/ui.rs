@@ -0,0 +1,1224 @@
+//! The module which manages all user interface, including inputs.
+
+use std::{
+    collections::VecDeque,
+    io::stderr,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, AtomicU8, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use lowfi::{
+    config::{Config, Frontend, Keybinds, Marquee, MIN_WIDTH},
+    player::{
+        keymap::{self, Action, Binding, Keymap},
+        Messages, Player, PlayerState, UiMessage,
+    },
+    tracks::{download::Progress, station},
+};
+
+use artwork::Protocol;
+use boxed::Boxed;
+use crossterm::{
+    cursor::{self, Hide, MoveTo, RestorePosition, Show},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        EventStream, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::StreamExt;
+use json::Json;
+use minimal::Minimal;
+use theme::Theme;
+use tokio::{
+    select,
+    sync::{mpsc::Sender, Notify},
+    time::sleep,
+};
+use tracing::debug;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub mod artwork;
+mod boxed;
+mod json;
+mod minimal;
+pub mod theme;
+
+/// How many seconds a single press of the seek keys skips.
+const SEEK_STEP: i64 = 5;
+
+/// The maximum amount of rows that a pane shows at once.
+const PANE_ROWS: usize = 5;
+
+/// How many rows the visualizer is.
+const VISUALIZER_ROWS: usize = 3;
+
+/// The bars that the visualizer is drawn with, from empty to full.
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The widest that the UI gets when it's fitting itself to the terminal.
+const MAX_WIDTH: usize = 80;
+
+/// How much a single press of the speed keys changes the playback speed.
+const SPEED_STEP: f32 = 0.1;
+
+/// How many decibels a single press of the equalizer keys changes the bass or treble by.
+const EQUALIZER_STEP: i8 = 2;
+
+/// How long a toast stays under the UI after it happened.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// The most toasts that are shown at once, where older ones make way for newer ones.
+const MAX_TOASTS: usize = 3;
+
+/// How often the UI is drawn while nothing is happening, which keeps the
+/// progress bar, the sleep timer & any toasts up to date.
+const TICK: Duration = Duration::from_secs(1);
+
+/// How much a single notch of the scroll wheel changes the volume.
+const SCROLL_STEP: f32 = 0.05;
+
+/// How long "tuning" is shown for when the track changes, before the new name appears.
+const TUNING: Duration = Duration::from_millis(450);
+
+/// How long it takes for the new name to appear once it's done tuning.
+const REVEAL: Duration = Duration::from_millis(300);
+
+/// How long each frame of a transition is shown for.
+const TRANSITION_FRAME: Duration = Duration::from_millis(50);
+
+/// How many digits can be typed into the volume input, since it only goes up to 100.
+const VOLUME_DIGITS: usize = 3;
+
+/// A frontend that the UI is drawn with, which is picked once at startup with [Frontend].
+///
+/// Input is handled the same way no matter which one is used, see [input].
+trait Renderer {
+    /// Draws a single frame of `snapshot`, where anything that isn't in the
+    /// snapshot, like the position within the track, is read from `queue`.
+    async fn draw(
+        &mut self,
+        queue: &Player,
+        snapshot: &PlayerState,
+        state: &State,
+    ) -> eyre::Result<()>;
+}
+
+/// The panes that can be opened below the main box, of which only one is open at a time.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    None,
+    History,
+    Stations,
+    Visualizer,
+    Queue,
+    Volume,
+    Help,
+}
+
+/// A [UiMessage] that's being shown below the UI.
+struct Toast {
+    /// The message itself.
+    message: UiMessage,
+
+    /// When it came in.
+    at: Instant,
+}
+
+/// The animation that's shown in place of the name for a moment after the track changes,
+/// which goes from tuning to revealing the name & is then done until the next one.
+#[derive(Default)]
+struct Transition {
+    /// The name that was last shown.
+    name: Option<String>,
+
+    /// When the animation for `name` started, which is [None] once it's done.
+    started: Option<Instant>,
+}
+
+/// State which only matters to the UI, and is shared between drawing & input.
+#[derive(Default)]
+struct State {
+    /// The [Pane] that's open.
+    pane: AtomicU8,
+
+    /// How far down the history pane has been scrolled, or which station or track is selected.
+    scroll: AtomicUsize,
+
+    /// The stations shown in the station picker, which are read when it's opened.
+    stations: Mutex<Vec<String>>,
+
+    /// The digits that have been typed into the volume input so far.
+    volume: Mutex<String>,
+
+    /// The width that the UI was last drawn with.
+    width: AtomicUsize,
+
+    /// Whether the name went onto a second line when the UI was last drawn,
+    /// which moves the rest of the box down a row, see `wrap_names`.
+    wrapped: AtomicBool,
+
+    /// The toasts that are being shown, from oldest to newest.
+    toasts: Mutex<VecDeque<Toast>>,
+
+    /// The name that was last shown & when it was first shown, so that its
+    /// [marquee] starts from the beginning whenever the name changes.
+    shown: Mutex<Option<(String, Instant)>>,
+
+    /// Whether the name is being scrolled, which means that the UI has to be drawn more often.
+    scrolling: AtomicBool,
+
+    /// The animation for when the track changes, see [State::transition].
+    transition: Mutex<Transition>,
+
+    /// Whether a transition is being shown, which means that the UI has to be drawn more often.
+    transitioning: AtomicBool,
+
+    /// The terminal row that the top border of the UI is on, used to figure
+    /// out what was clicked. This moves up if drawing the UI scrolls the terminal.
+    top: AtomicU16,
+
+    /// Whether the terminal has lost focus, which is only known in terminals that report it.
+    unfocused: AtomicBool,
+
+    /// Used to draw a new frame immediately when something that only the UI
+    /// knows about changes, like which pane is open. Anything to do with
+    /// the player itself comes through [Player::state] instead.
+    redraw: Notify,
+}
+
+impl State {
+    /// Gets the [Pane] that's open.
+    fn pane(&self) -> Pane {
+        match self.pane.load(Ordering::Relaxed) {
+            1 => Pane::History,
+            2 => Pane::Stations,
+            3 => Pane::Visualizer,
+            4 => Pane::Queue,
+            5 => Pane::Volume,
+            6 => Pane::Help,
+            _ => Pane::None,
+        }
+    }
+
+    /// Opens `pane`, or closes it if it's already open.
+    fn toggle(&self, pane: Pane) {
+        let pane = if self.pane() == pane {
+            Pane::None
+        } else {
+            pane
+        };
+        self.pane.store(pane as u8, Ordering::Relaxed);
+        self.scroll.store(0, Ordering::Relaxed);
+    }
+
+    /// Locks the digits that have been typed into the volume input.
+    fn volume(&self) -> std::sync::MutexGuard<'_, String> {
+        self.volume.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Moves the open pane up or down by a row.
+    fn scroll(&self, down: bool) {
+        if down {
+            self.scroll.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let _ = self
+                .scroll
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| x.checked_sub(1));
+        }
+    }
+
+    /// Fits `name` into `width` columns like [fit], except that it's scrolled if it's too long, see [marquee].
+    fn marquee(&self, name: &str, width: usize, config: &Marquee) -> String {
+        let mut shown = self.shown.lock().unwrap_or_else(|x| x.into_inner());
+        let since = match &*shown {
+            Some((x, since)) if x == name => *since,
+            _ => {
+                let now = Instant::now();
+                *shown = Some((name.to_owned(), now));
+                now
+            }
+        };
+
+        let scrolling = name.width() > width;
+        self.scrolling.store(scrolling, Ordering::Relaxed);
+        if scrolling {
+            marquee(name, width, since.elapsed(), config)
+        } else {
+            fit(name, width)
+        }
+    }
+
+    /// Swaps `action` for the frame of the transition that's being shown, if there is one,
+    /// starting a new one whenever the name changes.
+    ///
+    /// Coming from the loading bar goes straight to revealing the name, since
+    /// there's already been something to look at while it was loading.
+    fn transition(&self, action: ActionBar) -> ActionBar {
+        let mut transition = self.transition.lock().unwrap_or_else(|x| x.into_inner());
+        let name = match &action {
+            ActionBar::Playing(x) | ActionBar::Paused(x) => Some(x.clone()),
+            ActionBar::Loading(_) | ActionBar::Offline | ActionBar::Tuning(_) => None,
+        };
+
+        // Loading takes over from any transition that hasn't finished yet.
+        if name.is_none() {
+            transition.started = None;
+        } else if name != transition.name {
+            let now = Instant::now();
+            transition.started = Some(match transition.name {
+                Some(_) => now,
+                None => now.checked_sub(TUNING).unwrap_or(now),
+            });
+        }
+        transition.name = name;
+
+        let elapsed = transition.started.map(|x| x.elapsed());
+        let action = match (elapsed, action) {
+            (Some(elapsed), _) if elapsed < TUNING => {
+                let dots = (elapsed.as_millis() / 150) as usize % 3 + 1;
+                ActionBar::Tuning(dots)
+            }
+            (Some(elapsed), ActionBar::Playing(name)) if elapsed < TUNING + REVEAL => {
+                ActionBar::Playing(reveal(&name, elapsed - TUNING))
+            }
+            (Some(elapsed), ActionBar::Paused(name)) if elapsed < TUNING + REVEAL => {
+                ActionBar::Paused(reveal(&name, elapsed - TUNING))
+            }
+            (_, action) => {
+                transition.started = None;
+                action
+            }
+        };
+
+        self.transitioning
+            .store(transition.started.is_some(), Ordering::Relaxed);
+        action
+    }
+
+    /// Starts showing `message` as a toast, making room for it if there are already too many.
+    fn toast(&self, message: UiMessage) {
+        let mut toasts = self.toasts.lock().unwrap_or_else(|x| x.into_inner());
+        if toasts.len() >= MAX_TOASTS {
+            toasts.pop_front();
+        }
+
+        toasts.push_back(Toast {
+            message,
+            at: Instant::now(),
+        });
+    }
+
+    /// Formats the toasts which haven't run out yet, dropping the ones that have.
+    fn toasts(&self, width: usize, theme: &Theme) -> Vec<String> {
+        let mut toasts = self.toasts.lock().unwrap_or_else(|x| x.into_inner());
+        toasts.retain(|x| x.at.elapsed() < TOAST_DURATION);
+
+        toasts
+            .iter()
+            .map(|toast| {
+                let line = if toast.message.problem {
+                    theme.error(&fit(&format!("! {}", toast.message.message), width))
+                } else {
+                    fit(&toast.message.message, width)
+                };
+
+                format!("  {}", line)
+            })
+            .collect()
+    }
+}
+
+/// Gets the inner width of the UI, which is either set in the config or
+/// fits the terminal, without going under [MIN_WIDTH] or over [MAX_WIDTH].
+fn width(config: &Config) -> usize {
+    config.width.unwrap_or_else(|| {
+        terminal::size().map_or(MIN_WIDTH, |(columns, _)| {
+            // The borders & padding take up 4 columns.
+            usize::from(columns)
+                .saturating_sub(4)
+                .clamp(MIN_WIDTH, MAX_WIDTH)
+        })
+    })
+}
+
+/// The controls shown in the bottom bar, as the key and what it does.
+///
+/// These are also what can be clicked on with the mouse.
+fn controls(keys: &Keybinds) -> [(String, &'static str); 4] {
+    let key = |binding: &Binding| {
+        binding
+            .first()
+            .map_or_else(String::new, ToString::to_string)
+    };
+
+    [
+        (format!("[{}]", key(&keys.skip)), "skip"),
+        (format!("[{}]", key(&keys.pause)), "pause"),
+        (format!("[{}]", key(&keys.quit)), "quit"),
+        (
+            format!("[{}/{}]", key(&keys.volume_up), key(&keys.volume_down)),
+            "vol",
+        ),
+    ]
+}
+
+/// Gets the part of `name` that's shown `elapsed` into revealing it, which appears from left to right.
+fn reveal(name: &str, elapsed: Duration) -> String {
+    let shown = elapsed.as_secs_f32() / REVEAL.as_secs_f32();
+    let count = name.graphemes(true).count();
+
+    name.graphemes(true)
+        .take((shown * count as f32).ceil() as usize)
+        .collect()
+}
+
+/// Gets the part of `text` that's shown `elapsed` into scrolling it within `width` columns.
+///
+/// The text stays still at the start for a moment, scrolls along until its end is showing,
+/// stays still there too & then scrolls back, over & over.
+fn marquee(text: &str, width: usize, elapsed: Duration, config: &Marquee) -> String {
+    let span = text.width().saturating_sub(width) as f32;
+    let moving = span / config.speed;
+    let period = 2.0 * (config.pause + moving);
+    let phase = elapsed.as_secs_f32() % period;
+
+    let offset = if phase < config.pause {
+        0.0
+    } else if phase < config.pause + moving {
+        (phase - config.pause) * config.speed
+    } else if phase < 2.0 * config.pause + moving {
+        span
+    } else {
+        span - (phase - 2.0 * config.pause - moving) * config.speed
+    };
+    let offset = offset.clamp(0.0, span) as usize;
+
+    // Anything that starts before the offset is skipped, even if it's wider than a column.
+    let mut start = 0;
+    let visible: String = text
+        .graphemes(true)
+        .filter(|grapheme| {
+            let skipped = start < offset;
+            start += grapheme.width();
+            !skipped
+        })
+        .collect();
+
+    fit_exact(&visible, width)
+}
+
+/// Cuts `text` off at `width` columns without adding "...", padding it if it's any shorter.
+fn fit_exact(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used > width {
+            break;
+        }
+
+        fitted.push_str(grapheme);
+    }
+
+    let padding = width.saturating_sub(fitted.width());
+    format!("{}{}", fitted, " ".repeat(padding))
+}
+
+/// Splits `text` into a first line that's at most `width` columns wide & whatever's left over,
+/// which is done at the last space that fits if there is one, so that words aren't cut in half.
+fn split(text: &str, width: usize) -> (&str, &str) {
+    let mut end = 0;
+    let mut used = 0;
+    let mut space = None;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            break;
+        }
+
+        if grapheme == " " && i > 0 {
+            space = Some(i);
+        }
+        end = i + grapheme.len();
+    }
+
+    if end == text.len() {
+        return (text, "");
+    }
+
+    let end = space.unwrap_or(end);
+    (text[..end].trim_end(), text[end..].trim_start())
+}
+
+/// Truncates `text` so that it takes up at most `width` columns in the terminal,
+/// ending it with `...` if anything had to be cut off.
+///
+/// This works on graphemes rather than bytes, so it's fine with CJK characters & emoji.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_owned();
+    }
+
+    let mut truncated = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used + 3 > width {
+            break;
+        }
+
+        truncated.push_str(grapheme);
+    }
+
+    format!("{}...", truncated)
+}
+
+/// Truncates or pads `text` so that it takes up exactly `width` columns in the terminal.
+fn fit(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    let padding = width.saturating_sub(text.width());
+
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// Draws a pane, which is a second box that goes below the main one.
+fn pane(
+    title: &str,
+    rows: impl Iterator<Item = String>,
+    width: usize,
+    theme: &Theme,
+) -> Vec<String> {
+    let mut lines = vec![theme.top(title, width)];
+    lines.extend(rows.map(|row| theme.row(&fit(&row, width))));
+    lines.push(theme.bottom(&[], width));
+
+    lines
+}
+
+/// Formats the history pane.
+async fn history(queue: &Player, state: &State, width: usize, theme: &Theme) -> Vec<String> {
+    let history = queue.history.read().await;
+
+    let scroll = state
+        .scroll
+        .load(Ordering::Relaxed)
+        .min(history.len().saturating_sub(PANE_ROWS));
+    state.scroll.store(scroll, Ordering::Relaxed);
+
+    let title = format!("history ({})", history.len());
+    if history.is_empty() {
+        let rows = std::iter::once("nothing has been played yet".to_owned());
+        return pane(&title, rows, width, theme);
+    }
+
+    let rows = history
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(PANE_ROWS)
+        .map(|(i, track)| format!("{:>2}. {}", i + 1, track.display_name()));
+
+    pane(&title, rows, width, theme)
+}
+
+/// Formats the station picker, where the selected station is marked
+/// with an arrow and the one that's playing is marked as such.
+fn stations(queue: &Player, state: &State, width: usize, theme: &Theme) -> Vec<String> {
+    let stations = state.stations.lock().unwrap_or_else(|x| x.into_inner());
+    let current = queue.station.load_full();
+
+    let selected = state
+        .scroll
+        .load(Ordering::Relaxed)
+        .min(stations.len().saturating_sub(1));
+    state.scroll.store(selected, Ordering::Relaxed);
+
+    // The list only starts scrolling once the selection goes past the bottom.
+    let first = selected.saturating_sub(PANE_ROWS - 1);
+    let rows = stations
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(PANE_ROWS)
+        .map(|(i, name)| {
+            let marker = if i == selected { ">" } else { " " };
+            let playing = current.as_deref() == Some(name);
+            let suffix = if playing { " (playing)" } else { "" };
+
+            format!("{} {}{}", marker, name, suffix)
+        });
+
+    pane("stations", rows, width, theme)
+}
+
+/// Formats the queue, which is the track that's lined up next, followed by the
+/// buffered tracks after it. The selected track is marked with an arrow.
+async fn queued(queue: &Player, state: &State, width: usize, theme: &Theme) -> Vec<String> {
+    let tracks = queue.queue().await;
+
+    let selected = state
+        .scroll
+        .load(Ordering::Relaxed)
+        .min(tracks.len().saturating_sub(1));
+    state.scroll.store(selected, Ordering::Relaxed);
+
+    // The next track is already in the sink, so it can't be moved or removed anymore.
+    let next = queue
+        .upcoming
+        .load()
+        .as_ref()
+        .map(|x| format!("  {} (next)", x.display_name()));
+
+    let rows: Vec<String> = next
+        .into_iter()
+        .chain(tracks.iter().enumerate().map(|(i, track)| {
+            let marker = if i == selected { ">" } else { " " };
+            format!("{} {}", marker, track)
+        }))
+        .collect();
+
+    let title = format!("queue ({})", rows.len());
+    if rows.is_empty() {
+        let rows = std::iter::once("nothing has been downloaded yet".to_owned());
+        return pane(&title, rows, width, theme);
+    }
+
+    pane(&title, rows.into_iter(), width, theme)
+}
+
+/// Formats the volume input, which shows what's been typed so far with a cursor after it.
+fn volume_input(state: &State, width: usize, theme: &Theme) -> Vec<String> {
+    let typed = state.volume().clone();
+    let row = if typed.is_empty() {
+        "_ type a percentage, then enter".to_owned()
+    } else {
+        format!("{}%_", typed)
+    };
+
+    pane("volume", std::iter::once(row), width, theme)
+}
+
+/// Whether the visualizer is paused to save power, see [crate::config::Power].
+fn resting(queue: &Player, config: &Config) -> bool {
+    queue.saving.load(Ordering::Relaxed) && !config.power.visualizer
+}
+
+/// Formats the visualizer, which is a spectrum with a bar for every column.
+fn visualizer(queue: &Player, config: &Config, width: usize, theme: &Theme) -> Vec<String> {
+    let resting = resting(queue, config);
+    let levels = if queue.sink.is_paused() || resting {
+        vec![0.0; width]
+    } else {
+        queue.tap.spectrum(width)
+    };
+
+    // Each row can show 8 different heights, going from the top row down.
+    let rows = (0..VISUALIZER_ROWS).rev().map(|row| {
+        levels
+            .iter()
+            .map(|level| {
+                let eighths = (level * (VISUALIZER_ROWS * 8) as f32).round() as usize;
+                BARS[eighths.saturating_sub(row * 8).min(8)]
+            })
+            .collect::<String>()
+    });
+
+    let title = if resting {
+        "visualizer, paused to save battery"
+    } else {
+        "visualizer"
+    };
+    pane(title, rows, width, theme)
+}
+
+/// Formats the help overlay, which lists every keybind, including any that were
+/// changed in the config, followed by a summary of the config itself.
+fn help(queue: &Player, config: &Config, width: usize, theme: &Theme) -> Vec<String> {
+    let bindings = keymap::help(&config.keybinds);
+
+    // The keybinds are split into two columns, so that the pane doesn't get too tall.
+    let column = width / 2;
+    let cell = |binding: Option<&(String, &str)>| {
+        binding.map_or_else(String::new, |(keys, action)| {
+            fit(&format!("{}  {}", keys, action), column)
+        })
+    };
+    let half = bindings.len().div_ceil(2);
+    let mut rows: Vec<String> = (0..half)
+        .map(|i| format!("{}{}", cell(bindings.get(i)), cell(bindings.get(half + i))))
+        .collect();
+
+    let path = Config::path()
+        .ok()
+        .filter(|x| x.exists())
+        .map_or_else(|| "the defaults".to_owned(), |x| x.display().to_string());
+    let station = queue.station.load_full();
+    let switch = |on: bool| if on { "on" } else { "off" };
+
+    rows.push(String::new());
+    rows.push(format!("config   {}", path));
+    rows.push(format!(
+        "station  {}",
+        station.as_deref().map_or("a custom list", String::as_str)
+    ));
+    rows.push(format!("device   {}", queue.output.device()));
+    rows.push(format!(
+        "fade {}ms, normalize {}, pitch correction {}",
+        config.fade,
+        switch(config.normalize),
+        switch(config.pitch_correction)
+    ));
+
+    pane(
+        "help, press any key to close",
+        rows.into_iter(),
+        width,
+        theme,
+    )
+}
+
+/// This represents the main "action" bars state, with the name of the track if there is one.
+enum ActionBar {
+    Paused(String),
+    Playing(String),
+    Loading(Option<Progress>),
+
+    /// Waiting for the network to come back before loading, see [lowfi::player::connectivity].
+    Offline,
+
+    /// The track just changed, with how many dots are after "tuning", see [State::transition].
+    Tuning(usize),
+}
+
+impl ActionBar {
+    /// Gets the word at the start of the action bar, along with the name that follows it, if there is one.
+    ///
+    /// If `repeat` is on, then the word is followed by a loop symbol.
+    fn parts(&self, repeat: bool) -> (String, Option<String>) {
+        let (word, subject) = match self {
+            Self::Playing(x) => ("playing".to_owned(), Some(x.clone())),
+            Self::Paused(x) => ("paused".to_owned(), Some(x.clone())),
+            Self::Loading(None) => ("loading".to_owned(), None),
+            Self::Loading(Some(progress)) => (format!("loading {}", progress), None),
+            Self::Offline => ("offline – waiting for network".to_owned(), None),
+            Self::Tuning(dots) => (format!("tuning{}", ".".repeat(*dots)), None),
+        };
+        let word = if repeat {
+            format!("{} ↻", word)
+        } else {
+            word
+        };
+
+        (word, subject)
+    }
+
+    /// Formats the action bar to be displayed, taking up exactly `width` columns.
+    fn format(&self, width: usize, repeat: bool, theme: &Theme) -> String {
+        let (word, subject) = self.parts(repeat);
+
+        subject.map_or_else(
+            || fit(&word, width),
+            |subject| {
+                let subject = fit(&subject, width.saturating_sub(word.width() + 1));
+                format!("{} {}", word, theme.name(&subject))
+            },
+        )
+    }
+
+    /// Formats the action bar like [ActionBar::format], except that a name which doesn't
+    /// fit is scrolled back & forth, see [State::marquee].
+    fn marquee(
+        &self,
+        width: usize,
+        repeat: bool,
+        theme: &Theme,
+        state: &State,
+        config: &Marquee,
+    ) -> String {
+        let (word, subject) = self.parts(repeat);
+
+        match subject {
+            Some(subject) => {
+                let subject =
+                    state.marquee(&subject, width.saturating_sub(word.width() + 1), config);
+                format!("{} {}", word, theme.name(&subject))
+            }
+            None => {
+                state.scrolling.store(false, Ordering::Relaxed);
+                fit(&word, width)
+            }
+        }
+    }
+
+    /// Formats the action bar like [ActionBar::format], except that a name which doesn't
+    /// fit goes onto a second line that's `next` columns wide, lined up with the first.
+    ///
+    /// The second line is [None] if the name fits on the first one anyway.
+    fn wrap(
+        &self,
+        width: usize,
+        next: usize,
+        repeat: bool,
+        theme: &Theme,
+    ) -> (String, Option<String>) {
+        let (word, subject) = self.parts(repeat);
+        let indent = word.width() + 1;
+
+        let Some(subject) = subject.filter(|x| x.width() > width.saturating_sub(indent)) else {
+            return (self.format(width, repeat, theme), None);
+        };
+
+        let (first, rest) = split(&subject, width.saturating_sub(indent));
+        let first = format!(
+            "{} {}",
+            word,
+            theme.name(&fit(first, width.saturating_sub(indent)))
+        );
+        let rest = fit(rest, next.saturating_sub(indent));
+
+        (
+            first,
+            Some(format!("{}{}", " ".repeat(indent), theme.name(&rest))),
+        )
+    }
+}
+
+/// The code for the interface itself, which only draws a new frame when the [PlayerState]
+/// changes or every [TICK], apart from the visualizer which is animated at `config.fps`
+/// as long as something is playing & the terminal is focused. While saving power,
+/// that's lowered to `config.power.fps`, if the visualizer isn't paused entirely.
+async fn interface(
+    mut renderer: impl Renderer,
+    queue: Arc<Player>,
+    config: Arc<Config>,
+    state: Arc<State>,
+) -> eyre::Result<()> {
+    // How long to wait in between frames of the visualizer.
+    let frame_delta = Duration::from_secs_f32(1.0 / config.fps);
+    let saving_delta = Duration::from_secs_f32(1.0 / config.fps.min(config.power.fps));
+    let mut updates = queue.state.subscribe();
+    let mut progress = queue.progress.subscribe();
+    let mut messages = queue.messages.subscribe();
+
+    // How long to wait before moving a scrolling name along by a column.
+    let step = Duration::from_secs_f32(1.0 / config.marquee.speed);
+
+    loop {
+        let snapshot = updates.borrow_and_update().clone();
+        renderer.draw(&queue, &snapshot, &state).await?;
+
+        // The visualizer would just be drawing the same frame over & over while
+        // paused, and there's no point animating it if nobody's looking.
+        let animated = state.pane() == Pane::Visualizer
+            && !snapshot.paused
+            && !state.unfocused.load(Ordering::Relaxed)
+            && !resting(&queue, &config);
+        let mut delay = match animated {
+            true if queue.saving.load(Ordering::Relaxed) => saving_delta,
+            true => frame_delta,
+            false => TICK,
+        };
+        if state.scrolling.load(Ordering::Relaxed) && !state.unfocused.load(Ordering::Relaxed) {
+            delay = delay.min(step);
+        }
+        if state.transitioning.load(Ordering::Relaxed) {
+            delay = delay.min(TRANSITION_FRAME);
+        }
+
+        // Wait for the next tick, unless something changed that should be shown immediately.
+        select! {
+            _ = sleep(delay) => {},
+            _ = updates.changed() => {},
+            _ = progress.changed() => {},
+            _ = state.redraw.notified() => {},
+            Ok(message) = messages.recv() => state.toast(message),
+        }
+    }
+}
+
+/// Runs the interface with whichever [Renderer] the config asks for.
+async fn frontend(queue: Arc<Player>, config: Arc<Config>, state: Arc<State>) -> eyre::Result<()> {
+    match config.frontend {
+        Frontend::Boxed => interface(Boxed::new(Arc::clone(&config)), queue, config, state).await,
+        Frontend::Minimal => interface(Minimal::new(&config), queue, config, state).await,
+        Frontend::Json => interface(Json::default(), queue, config, state).await,
+    }
+}
+
+/// Handles a single mouse event, returning false if the user clicked quit.
+async fn mouse(
+    queue: &Player,
+    sender: &Sender<Messages>,
+    config: &Config,
+    state: &State,
+    event: MouseEvent,
+) -> eyre::Result<bool> {
+    let open = matches!(state.pane(), Pane::History | Pane::Stations | Pane::Queue);
+
+    match event.kind {
+        // Just like the arrow keys, the scroll wheel scrolls the open pane if there is one.
+        MouseEventKind::ScrollUp if open => {
+            state.scroll(false);
+            state.redraw.notify_one();
+        }
+        MouseEventKind::ScrollDown if open => {
+            state.scroll(true);
+            state.redraw.notify_one();
+        }
+        MouseEventKind::ScrollUp => {
+            sender.send(Messages::ChangeVolume(SCROLL_STEP)).await?;
+        }
+        MouseEventKind::ScrollDown => {
+            sender.send(Messages::ChangeVolume(-SCROLL_STEP)).await?;
+        }
+        // Only the boxed frontend has anything to click on.
+        MouseEventKind::Down(MouseButton::Left) if config.frontend == Frontend::Boxed => {
+            // A wrapped name pushes the rest of the box down by a row.
+            let shift = u16::from(state.wrapped.load(Ordering::Relaxed));
+            let row = event
+                .row
+                .checked_sub(state.top.load(Ordering::Relaxed) + shift);
+            let column = usize::from(event.column);
+
+            match row {
+                // The progress bar, which starts after "│  [".
+                Some(2) => {
+                    let progress_width = state.width.load(Ordering::Relaxed).saturating_sub(16);
+                    let Some(offset) = column.checked_sub(4) else {
+                        return Ok(true);
+                    };
+
+                    let Some(duration) = queue.current.load().as_ref().and_then(|x| x.duration)
+                    else {
+                        return Ok(true);
+                    };
+
+                    if offset < progress_width {
+                        let target =
+                            duration.as_secs_f32() * (offset as f32 + 0.5) / progress_width as f32;
+                        let target = Duration::from_secs_f32(target);
+                        sender.send(Messages::SeekTo(target)).await?;
+                    }
+                }
+                // The bottom bar with all of the controls, which starts after "│ ".
+                Some(3) => {
+                    let mut start = 2;
+                    for (i, (key, action)) in controls(&config.keybinds).iter().enumerate() {
+                        let end = start + key.width() + 1 + action.len();
+
+                        if (start..end).contains(&column) {
+                            match i {
+                                0 if queue.current.load().is_some() => {
+                                    sender.send(Messages::Next).await?;
+                                }
+                                1 => sender.send(Messages::PlayPauseToggle).await?,
+                                2 => return Ok(false),
+                                _ => (),
+                            }
+                        }
+
+                        start = end + 3;
+                    }
+                }
+                _ => (),
+            }
+        }
+        _ => (),
+    }
+
+    Ok(true)
+}
+
+/// Handles all of the input from the user, until they quit.
+async fn input(
+    queue: Arc<Player>,
+    sender: Sender<Messages>,
+    config: Arc<Config>,
+    state: Arc<State>,
+) -> eyre::Result<()> {
+    let keymap = Keymap::new(&config.keybinds);
+    let mut events = EventStream::new();
+
+    while let Some(event) = events.next().await {
+        let event = match event? {
+            // Windows also reports when keys are let go of, which would otherwise trigger everything twice.
+            event::Event::Key(event) if event.kind == KeyEventKind::Release => continue,
+            event::Event::Key(event) => event,
+            event::Event::Mouse(event) => {
+                if !mouse(&queue, &sender, &config, &state, event).await? {
+                    break;
+                }
+
+                continue;
+            }
+            event::Event::Resize(..) => {
+                state.redraw.notify_one();
+                continue;
+            }
+            focus @ (event::Event::FocusGained | event::Event::FocusLost) => {
+                let unfocused = focus == event::Event::FocusLost;
+                state.unfocused.store(unfocused, Ordering::Relaxed);
+                state.redraw.notify_one();
+                continue;
+            }
+            _ => continue,
+        };
+
+        // Any key closes the help overlay, apart from Ctrl+C.
+        let pane = state.pane();
+        if pane == Pane::Help {
+            if event.code == KeyCode::Char('c') && event.modifiers == KeyModifiers::CONTROL {
+                break;
+            }
+
+            state.toggle(Pane::Help);
+            state.redraw.notify_one();
+            continue;
+        }
+
+        // While the volume input is open, every key goes to it, apart from Ctrl+C.
+        if pane == Pane::Volume {
+            match event.code {
+                KeyCode::Char('c') if event.modifiers == KeyModifiers::CONTROL => break,
+                KeyCode::Char(digit @ '0'..='9') => {
+                    let mut typed = state.volume();
+                    if typed.len() < VOLUME_DIGITS {
+                        typed.push(digit);
+                    }
+                }
+                KeyCode::Backspace => {
+                    state.volume().pop();
+                }
+                KeyCode::Enter => {
+                    let typed = state.volume().parse::<u8>().ok();
+                    state.toggle(Pane::Volume);
+                    if let Some(volume) = typed {
+                        let volume = f32::from(volume.min(100)) / 100.0;
+                        sender.send(Messages::SetVolume(volume)).await?;
+                    }
+                }
+                KeyCode::Esc => state.toggle(Pane::Volume),
+                _ => (),
+            }
+
+            state.redraw.notify_one();
+            continue;
+        }
+
+        // While a pane is open, the arrow keys scroll it instead.
+        if pane != Pane::None {
+            let down = event.code == KeyCode::Down;
+            match event.code {
+                // In the queue, holding shift moves the selected track instead.
+                KeyCode::Up | KeyCode::Down
+                    if pane == Pane::Queue && event.modifiers.contains(KeyModifiers::SHIFT) =>
+                {
+                    let from = state.scroll.load(Ordering::Relaxed);
+                    let to = if down {
+                        from + 1
+                    } else {
+                        from.saturating_sub(1)
+                    };
+
+                    state.scroll.store(to, Ordering::Relaxed);
+                    sender.send(Messages::MoveQueued(from, to)).await?;
+                    continue;
+                }
+                KeyCode::Up | KeyCode::Down if pane != Pane::Visualizer => {
+                    state.scroll(down);
+                    state.redraw.notify_one();
+                    continue;
+                }
+                KeyCode::Delete | KeyCode::Backspace if pane == Pane::Queue => {
+                    let selected = state.scroll.load(Ordering::Relaxed);
+                    sender.send(Messages::RemoveQueued(selected)).await?;
+                    continue;
+                }
+                KeyCode::Enter if pane == Pane::Stations => {
+                    let selected = state.scroll.load(Ordering::Relaxed);
+                    let station = state
+                        .stations
+                        .lock()
+                        .unwrap_or_else(|x| x.into_inner())
+                        .get(selected)
+                        .cloned();
+
+                    state.toggle(Pane::Stations);
+                    state.redraw.notify_one();
+                    if let Some(station) = station {
+                        sender.send(Messages::ChangeStation(station)).await?;
+                    }
+
+                    continue;
+                }
+                KeyCode::Esc => {
+                    state.toggle(pane);
+                    state.redraw.notify_one();
+                    continue;
+                }
+                _ => (),
+            }
+        }
+
+        // Ctrl+C always quits, no matter what the keybinds are.
+        if event.code == KeyCode::Char('c') && event.modifiers == KeyModifiers::CONTROL {
+            break;
+        }
+
+        let Some(action) = keymap.get(event) else {
+            continue;
+        };
+        debug!("pressed {:?}", action);
+
+        let message = match action {
+            Action::Quit => break,
+            Action::Skip if queue.current.load().is_none() => continue,
+            Action::Skip => Messages::Next,
+            Action::Pause => Messages::PlayPauseToggle,
+            Action::Favorite => Messages::Favorite,
+            Action::Previous => Messages::Previous,
+            Action::Like => Messages::Like,
+            Action::Block => Messages::Block,
+            Action::Repeat => Messages::ToggleRepeat,
+            Action::Device => Messages::CycleDevice,
+            Action::Timer => Messages::CycleTimer,
+            Action::SeekForward => Messages::Seek(SEEK_STEP),
+            Action::SeekBackward => Messages::Seek(-SEEK_STEP),
+            Action::VolumeUp => Messages::ChangeVolume(0.1),
+            Action::VolumeDown => Messages::ChangeVolume(-0.1),
+            Action::VolumeUpFine => Messages::ChangeVolume(0.01),
+            Action::VolumeDownFine => Messages::ChangeVolume(-0.01),
+            Action::SpeedUp => Messages::ChangeSpeed(SPEED_STEP),
+            Action::SpeedDown => Messages::ChangeSpeed(-SPEED_STEP),
+            Action::BassUp => Messages::ChangeBass(EQUALIZER_STEP),
+            Action::BassDown => Messages::ChangeBass(-EQUALIZER_STEP),
+            Action::TrebleUp => Messages::ChangeTreble(EQUALIZER_STEP),
+            Action::TrebleDown => Messages::ChangeTreble(-EQUALIZER_STEP),
+            Action::History => {
+                state.toggle(Pane::History);
+                state.redraw.notify_one();
+                continue;
+            }
+            Action::Visualizer => {
+                state.toggle(Pane::Visualizer);
+                state.redraw.notify_one();
+                continue;
+            }
+            Action::Queue => {
+                state.toggle(Pane::Queue);
+                state.redraw.notify_one();
+                continue;
+            }
+            Action::Help => {
+                state.toggle(Pane::Help);
+                state.redraw.notify_one();
+                continue;
+            }
+            Action::Mute => Messages::ToggleMute,
+            Action::Copy => Messages::Copy,
+            Action::Open => Messages::Open,
+            Action::Download => Messages::Download,
+            Action::Ambience => Messages::CycleAmbience,
+            Action::AmbienceSelect => Messages::SelectAmbience,
+            Action::SetVolume => {
+                state.volume().clear();
+                state.toggle(Pane::Volume);
+                state.redraw.notify_one();
+                continue;
+            }
+            Action::Stations => {
+                state.toggle(Pane::Stations);
+
+                // The picker starts out on the station that's playing.
+                if state.pane() == Pane::Stations {
+                    let stations = station::names();
+                    let current = queue.station.load_full();
+                    let selected = stations
+                        .iter()
+                        .position(|x| current.as_deref() == Some(x))
+                        .unwrap_or(0);
+
+                    state.scroll.store(selected, Ordering::Relaxed);
+                    *state.stations.lock().unwrap_or_else(|x| x.into_inner()) = stations;
+                }
+
+                state.redraw.notify_one();
+                continue;
+            }
+        };
+
+        sender.send(message).await?;
+    }
+
+    Ok(())
+}
+
+/// Initializes the UI, and then keeps drawing it & taking input from the user.
+///
+/// This returns once the user quits, but it can also be cancelled if the audio
+/// server quits first. Either way, [stop] should be called afterwards.
+///
+/// `config.alternate` controls whether to use [EnterAlternateScreen] in order to hide
+/// previous terminal history.
+pub async fn start(
+    queue: Arc<Player>,
+    sender: Sender<Messages>,
+    config: Arc<Config>,
+) -> eyre::Result<()> {
+    crossterm::execute!(
+        stderr(),
+        RestorePosition,
+        Clear(ClearType::FromCursorDown),
+        Hide,
+        EnableFocusChange
+    )?;
+
+    if config.alternate {
+        crossterm::execute!(stderr(), EnterAlternateScreen, MoveTo(0, 0))?;
+    }
+
+    if config.mouse {
+        crossterm::execute!(stderr(), EnableMouseCapture)?;
+    }
+
+    // This has to be read before the input starts being handled, since it's also read from stdin.
+    let state = Arc::new(State::default());
+    let top = cursor::position().map_or(0, |(_, row)| row);
+    state.top.store(top, Ordering::Relaxed);
+
+    select! {
+        result = frontend(Arc::clone(&queue), Arc::clone(&config), Arc::clone(&state)) => result,
+        result = input(queue, sender, config, state) => result,
+    }
+}
+
+/// Restores the terminal back to how it was before the UI was started.
+pub fn stop(config: &Config) -> eyre::Result<()> {
+    if config.alternate {
+        crossterm::execute!(stderr(), LeaveAlternateScreen)?;
+    }
+
+    if config.mouse {
+        crossterm::execute!(stderr(), DisableMouseCapture)?;
+    }
+
+    if let Some(protocol) = config.artwork.then(Protocol::detect).flatten() {
+        crossterm::execute!(stderr(), Print(protocol.clear()))?;
+    }
+
+    crossterm::execute!(
+        stderr(),
+        Clear(ClearType::FromCursorDown),
+        Show,
+        DisableFocusChange
+    )?;
+    terminal::disable_raw_mode()?;
+
+    Ok(())
+}
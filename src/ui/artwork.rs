@@ -0,0 +1,260 @@
+//! Draws the album art of the current track beside the UI, in terminals with graphics support.
+//!
+//! There's no reliable way of asking a terminal what it supports without fighting
+//! the input handling over stdin, so the protocol is guessed from the environment.
+//! If it can't be, or if lowfi is running inside of tmux, then no art is drawn.
+//! The art is also never drawn without the `artwork` feature, which is what decodes it.
+
+use std::env;
+#[cfg(feature = "artwork")]
+use std::io::Cursor;
+
+#[cfg(feature = "artwork")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "artwork")]
+use crossterm::terminal;
+#[cfg(feature = "artwork")]
+use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbaImage};
+
+/// How many rows the art takes up, which is the top border & the three lines of the UI.
+pub const ROWS: u16 = 4;
+
+/// How many columns the art takes up, which is about square since cells are roughly twice as tall as they're wide.
+pub const COLUMNS: u16 = ROWS * 2;
+
+#[cfg(feature = "artwork")]
+/// The size that's assumed for a cell in pixels, if the terminal doesn't say.
+const FALLBACK_CELL: (u32, u32) = (10, 20);
+
+#[cfg(feature = "artwork")]
+/// The largest that the art is sent as, in pixels, since the terminal scales it anyway.
+const MAX_SIZE: u32 = 256;
+
+/// The ID that the art is given in kitty, so that it can be deleted again later.
+const KITTY_ID: u32 = 0x6c6f;
+
+#[cfg(feature = "artwork")]
+/// How many bytes of base64 kitty accepts in a single escape code.
+const KITTY_CHUNK: usize = 4096;
+
+/// The different graphics protocols that terminals use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The [kitty graphics protocol](https://sw.kovidgoyal.net/kitty/graphics-protocol/),
+    /// also used by ghostty.
+    Kitty,
+
+    /// [iTerm2's inline images](https://iterm2.com/documentation-images.html), also used by WezTerm.
+    Iterm,
+
+    /// The old DEC sixel format, which is supported by a lot of terminals like foot & mlterm.
+    Sixel,
+}
+
+impl Protocol {
+    /// Guesses which protocol the terminal supports, if any.
+    pub fn detect() -> Option<Self> {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+
+        if cfg!(not(feature = "artwork")) {
+            return None;
+        }
+
+        // tmux needs every escape code to be wrapped, and even then tends to mangle images.
+        if env::var_os("TMUX").is_some() {
+            return None;
+        }
+
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+        {
+            Some(Self::Kitty)
+        } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+            Some(Self::Iterm)
+        } else if term.contains("sixel")
+            || ["foot", "mlterm", "contour", "yaft"]
+                .iter()
+                .any(|x| term.starts_with(x))
+        {
+            Some(Self::Sixel)
+        } else {
+            None
+        }
+    }
+
+    /// Encodes album art into the escape codes which draw it at the cursor,
+    /// scaled to fit into [COLUMNS] by [ROWS] cells.
+    #[cfg(feature = "artwork")]
+    pub fn encode(self, artwork: &[u8]) -> eyre::Result<String> {
+        let image = image::load_from_memory(artwork)?;
+
+        Ok(match self {
+            Self::Kitty => {
+                let data = STANDARD.encode(png(&image)?);
+                let chunks: Vec<&str> = data
+                    .as_bytes()
+                    .chunks(KITTY_CHUNK)
+                    .map(|x| std::str::from_utf8(x).unwrap_or_default())
+                    .collect();
+
+                // Only the first chunk has the actual settings, and `C=1` stops the cursor from moving.
+                let mut encoded = String::new();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(i + 1 < chunks.len());
+                    if i == 0 {
+                        encoded.push_str(&format!(
+                            "\x1b_Ga=T,f=100,i={},c={},r={},C=1,q=2,m={};{}\x1b\\",
+                            KITTY_ID, COLUMNS, ROWS, more, chunk
+                        ));
+                    } else {
+                        encoded.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                    }
+                }
+
+                encoded
+            }
+            Self::Iterm => {
+                let data = png(&image)?;
+                format!(
+                    "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+                    data.len(),
+                    COLUMNS,
+                    ROWS,
+                    STANDARD.encode(&data)
+                )
+            }
+            Self::Sixel => {
+                // Unlike the others, sixels are drawn pixel for pixel.
+                let (width, height) = terminal::window_size()
+                    .ok()
+                    .filter(|x| x.width > 0 && x.height > 0 && x.columns > 0 && x.rows > 0)
+                    .map_or(FALLBACK_CELL, |x| {
+                        (u32::from(x.width / x.columns), u32::from(x.height / x.rows))
+                    });
+
+                let image = image.resize(
+                    width * u32::from(COLUMNS),
+                    height * u32::from(ROWS),
+                    FilterType::Triangle,
+                );
+
+                sixel(&image.to_rgba8())
+            }
+        })
+    }
+
+    /// Always fails, since lowfi was built without anything to decode the art with.
+    #[cfg(not(feature = "artwork"))]
+    pub fn encode(self, _: &[u8]) -> eyre::Result<String> {
+        eyre::bail!("lowfi was built without the `artwork` feature")
+    }
+
+    /// Gets the escape codes that remove the art again, since
+    /// in kitty, clearing the text doesn't get rid of images.
+    pub fn clear(self) -> String {
+        match self {
+            Self::Kitty => format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", KITTY_ID),
+            Self::Iterm | Self::Sixel => String::new(),
+        }
+    }
+}
+
+#[cfg(feature = "artwork")]
+/// Shrinks an image down to [MAX_SIZE] & encodes it as a PNG.
+fn png(image: &DynamicImage) -> eyre::Result<Vec<u8>> {
+    let image = image.resize(MAX_SIZE, MAX_SIZE, FilterType::Triangle);
+
+    let mut data = Vec::new();
+    image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)?;
+
+    Ok(data)
+}
+
+#[cfg(feature = "artwork")]
+/// Encodes an image as sixels, with every pixel rounded to a 6x6x6 color cube.
+///
+/// That's a lot less than the 256 colors that most terminals allow,
+/// but it's plenty for a thumbnail & doesn't need any real quantization.
+fn sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |x: u8| (u16::from(x) * 5 + 127) / 255;
+
+    let colors: Vec<u8> = image
+        .pixels()
+        .map(|x| {
+            let [r, g, b, a] = x.0;
+            if a < 128 {
+                0
+            } else {
+                (level(r) * 36 + level(g) * 6 + level(b)) as u8
+            }
+        })
+        .collect();
+
+    // The `1` means that pixels which aren't drawn are left alone.
+    let mut encoded = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, (i / 6) % 6, i % 6);
+        encoded.push_str(&format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20));
+    }
+
+    // Sixels go in bands of six rows, and each color in a band is drawn over the same line.
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+
+        let mut used = [false; 216];
+        for y in rows.clone() {
+            for x in 0..width {
+                used[usize::from(colors[(y * width + x) as usize])] = true;
+            }
+        }
+
+        let mut first = true;
+        for (color, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+            let line: Vec<u8> = (0..width)
+                .map(|x| {
+                    rows.clone()
+                        .filter(|y| usize::from(colors[(y * width + x) as usize]) == color)
+                        .fold(0u8, |bits, y| bits | 1 << (y - top))
+                })
+                .collect();
+
+            // `$` goes back to the start of the band for the next color.
+            if !first {
+                encoded.push('$');
+            }
+            first = false;
+
+            encoded.push_str(&format!("#{}", color));
+            push_run_length(&mut encoded, &line);
+        }
+
+        encoded.push('-');
+    }
+
+    encoded.push_str("\x1b\\");
+    encoded
+}
+
+#[cfg(feature = "artwork")]
+/// Adds a line of sixels, where repeats are shortened to `!<count><sixel>`.
+fn push_run_length(encoded: &mut String, line: &[u8]) {
+    let mut i = 0;
+    while i < line.len() {
+        let bits = line[i];
+        let count = line[i..].iter().take_while(|x| **x == bits).count();
+        let sixel = char::from(63 + bits);
+
+        if count > 3 {
+            encoded.push_str(&format!("!{}{}", count, sixel));
+        } else {
+            (0..count).for_each(|_| encoded.push(sixel));
+        }
+
+        i += count;
+    }
+}
@@ -0,0 +1,343 @@
+//! The usual layout of the UI, which is a box with the track, the progress bar & the controls,
+//! along with any panes below it & the album art beside it.
+
+use std::{
+    io::{stderr, Write},
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::{
+        MoveToColumn, MoveToNextLine, MoveToPreviousLine, MoveUp, RestorePosition, SavePosition,
+    },
+    style::{Print, Stylize},
+    terminal::{self, BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate},
+};
+use tokio::task;
+use tracing::warn;
+use unicode_width::UnicodeWidthStr;
+
+use super::{
+    artwork::{self, Protocol},
+    controls, fit, help, history, queued, resting, stations,
+    theme::Theme,
+    visualizer, volume_input, width, ActionBar, Pane, Renderer, State,
+};
+use lowfi::{
+    config::Config,
+    format_duration,
+    player::{Player, PlayerState},
+};
+
+/// Draws the boxed layout, keeping track of the album art so that it isn't drawn every frame.
+pub struct Boxed {
+    /// The config, which has the width & the keybinds that are shown in the controls.
+    config: Arc<Config>,
+
+    /// The look of the box.
+    theme: Theme,
+
+    /// The protocol that the album art is drawn with, which is [None] if it's not drawn at all.
+    protocol: Option<Protocol>,
+
+    /// The track & width that the art was last drawn for. Since images are quite
+    /// big, it's only drawn again when one of those changes, rather than every frame.
+    drawn: Option<(String, usize)>,
+
+    /// The lines of the last frame, so that only the ones which changed are drawn again.
+    frame: Vec<String>,
+
+    /// The width that the last frame was drawn with.
+    width: usize,
+}
+
+impl Boxed {
+    /// Creates the layout, figuring out whether the terminal can show album art.
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            // The album art is only drawn if the terminal seems to support it.
+            protocol: config.artwork.then(Protocol::detect).flatten(),
+            drawn: None,
+            frame: Vec::new(),
+            width: 0,
+            theme: Theme::new(&config.theme),
+            config,
+        }
+    }
+
+    /// Writes out the lines of a frame that are different from the last one, which is all
+    /// put together in memory first & sent as a single synchronized update, so that
+    /// the terminal never shows a half drawn frame. The cursor ends up back at the top.
+    ///
+    /// `main` is how many of the lines are the main box, which the art is beside.
+    fn flush(&mut self, lines: Vec<String>, height: u16, main: usize) -> eyre::Result<()> {
+        let mut buffer = Vec::new();
+        crossterm::queue!(buffer, BeginSynchronizedUpdate, MoveToColumn(0))?;
+
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                crossterm::queue!(buffer, Print("\r\n"))?;
+            }
+
+            if self.frame.get(i) == Some(line) {
+                continue;
+            }
+
+            crossterm::queue!(buffer, Print(line))?;
+
+            // The art is beside the main box, which is always the same width anyway.
+            if self.protocol.is_none() || i >= main {
+                crossterm::queue!(buffer, Clear(ClearType::UntilNewLine))?;
+            }
+        }
+
+        // Those rows were there last frame, so moving into them won't scroll the terminal.
+        if self.frame.len() > lines.len() {
+            crossterm::queue!(
+                buffer,
+                MoveToNextLine(1),
+                Clear(ClearType::FromCursorDown),
+                MoveToPreviousLine(1)
+            )?;
+        }
+
+        crossterm::queue!(
+            buffer,
+            MoveToColumn(0),
+            MoveUp(height),
+            EndSynchronizedUpdate
+        )?;
+
+        let mut stderr = stderr().lock();
+        stderr.write_all(&buffer)?;
+        stderr.flush()?;
+
+        self.frame = lines;
+
+        Ok(())
+    }
+}
+
+impl Renderer for Boxed {
+    async fn draw(
+        &mut self,
+        queue: &Player,
+        snapshot: &PlayerState,
+        state: &State,
+    ) -> eyre::Result<()> {
+        let (config, theme) = (&*self.config, &self.theme);
+
+        // The total width of the UI, which is checked every frame in case the terminal was resized.
+        let width = width(config);
+        state.width.store(width, Ordering::Relaxed);
+
+        // The width of the progress bar, not including the borders (`[` and `]`) or padding.
+        let progress_width = width - 16;
+
+        let loading = if snapshot.disconnected {
+            ActionBar::Offline
+        } else {
+            ActionBar::Loading(*queue.progress.borrow())
+        };
+        let action = snapshot.current.as_ref().map_or(loading, |x| {
+            let name = config.format.format(x);
+            if snapshot.paused {
+                ActionBar::Paused(name)
+            } else {
+                ActionBar::Playing(name)
+            }
+        });
+        let action = if config.transitions {
+            state.transition(action)
+        } else {
+            action
+        };
+
+        // The speed is only shown when it isn't normal.
+        let speed = snapshot.speed;
+        let level = if snapshot.muted {
+            "muted".to_owned()
+        } else {
+            format!("{}%", snapshot.volume)
+        };
+        let volume = if speed == 1.0 {
+            format!(" Volume: {} ", level)
+        } else {
+            format!(" {}x  Volume: {} ", speed, level)
+        };
+        let (name, overflow) = if config.wrap_names {
+            action.wrap(width - volume.len(), width, snapshot.repeat, theme)
+        } else if config.marquee.enabled {
+            let name = action.marquee(
+                width - volume.len(),
+                snapshot.repeat,
+                theme,
+                state,
+                &config.marquee,
+            );
+            (name, None)
+        } else {
+            (
+                action.format(width - volume.len(), snapshot.repeat, theme),
+                None,
+            )
+        };
+        let main = format!("{}{}", name, volume);
+        state.wrapped.store(overflow.is_some(), Ordering::Relaxed);
+
+        let mut duration = Duration::new(0, 0);
+        let elapsed = queue.elapsed();
+
+        let mut fraction = 0.0;
+        if let Some(current) = snapshot.current.as_ref() {
+            if let Some(x) = current.duration {
+                duration = x;
+                fraction = elapsed.as_secs_f32() / duration.as_secs_f32();
+            }
+        };
+
+        let progress = format!(
+            " [{}] {}/{} ",
+            theme.progress(fraction, progress_width),
+            format_duration(&elapsed),
+            format_duration(&duration),
+        );
+        // With the default keybinds the bar is exactly `MIN_WIDTH` characters wide,
+        // but longer ones like `ctrl+n` might have to be cut off.
+        let controls = controls(&config.keybinds);
+        let plain = controls
+            .clone()
+            .map(|(key, action)| format!("{} {}", key, action));
+        let plain = plain.join("   ");
+
+        let bar = if plain.width() > width {
+            fit(&plain, width)
+        } else {
+            let bar =
+                controls.map(|(key, action)| format!("{} {}", theme.accent(&key).bold(), action));
+            format!("{}{}", bar.join("   "), " ".repeat(width - plain.width()))
+        };
+
+        // Formats the menu properly, with the rest of the name on its own row if it was wrapped.
+        let menu: Vec<String> = [Some(main), overflow, Some(progress), Some(bar)]
+            .into_iter()
+            .flatten()
+            .map(|x| theme.row(&x.reset().to_string()))
+            .collect();
+
+        // How many rows the main box takes up, including its borders.
+        let main = menu.len() + 2;
+
+        // The bottom border also shows whether the track is liked, the equalizer,
+        // the sleep timer, the pomodoro countdown & whether the next track is ready to go.
+        let mut labels = Vec::new();
+        if snapshot.liked {
+            labels.push("liked".to_owned());
+        }
+        let (bass, treble) = snapshot.equalizer;
+        if bass != 0 || treble != 0 {
+            labels.push(format!("eq {:+}/{:+}", bass, treble));
+        }
+        if let Some(end) = snapshot.timer {
+            let remaining = end.saturating_duration_since(Instant::now());
+            labels.push(format!("sleep {}", format_duration(&remaining)));
+        }
+        if let Some(pomodoro) = snapshot.pomodoro {
+            let (phase, remaining) = pomodoro.phase();
+            labels.push(format!("{} {}", phase.name(), format_duration(&remaining)));
+        }
+        if snapshot.upcoming {
+            labels.push("next buffered".to_owned());
+        }
+        if snapshot.disconnected && snapshot.current.is_some() {
+            labels.push("offline".to_owned());
+        }
+
+        let bottom = theme.bottom(&labels, width);
+
+        // Any panes are put below the main box, after any recent toasts.
+        // The samples are only collected while the visualizer is open & animated.
+        queue
+            .tap
+            .set_active(state.pane() == Pane::Visualizer && !resting(queue, config));
+        let mut panes = match state.pane() {
+            Pane::History => history(queue, state, width, theme).await,
+            Pane::Stations => stations(queue, state, width, theme),
+            Pane::Visualizer => visualizer(queue, config, width, theme),
+            Pane::Queue => queued(queue, state, width, theme).await,
+            Pane::Volume => volume_input(state, width, theme),
+            Pane::Help => help(queue, config, width, theme),
+            Pane::None => Vec::new(),
+        };
+        panes.splice(0..0, state.toasts(width, theme));
+        let mut lines = vec![theme.top("", width)];
+        lines.extend(menu);
+        lines.push(bottom);
+        lines.extend(panes);
+        let height = lines.len() as u16 - 1;
+
+        // The art goes to the right of the UI, as long as there's enough room for it.
+        let current = &snapshot.current;
+        let room = terminal::size().is_ok_and(|(columns, _)| {
+            usize::from(columns) >= width + 5 + usize::from(artwork::COLUMNS)
+        });
+        let wanted = match (self.protocol, &current) {
+            (Some(_), Some(current)) if room && current.artwork.is_some() => {
+                Some((current.url.clone(), width))
+            }
+            _ => None,
+        };
+        let redraw = self.protocol.is_some() && wanted != self.drawn;
+
+        // If the UI doesn't fit below the cursor, then the terminal scrolls it up.
+        if let Ok((_, rows)) = terminal::size() {
+            let _ = state
+                .top
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| {
+                    Some(x.min(rows.saturating_sub(height + 1)))
+                });
+        }
+
+        // Everything has to be drawn again if the art is, or if the width changed, since
+        // the terminal might've moved the old frame around.
+        if redraw || self.width != width {
+            crossterm::execute!(stderr(), MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+            self.frame.clear();
+            self.width = width;
+        }
+
+        self.flush(lines, height, main)?;
+
+        if let Some(protocol) = self.protocol.filter(|_| redraw) {
+            crossterm::execute!(stderr(), Print(protocol.clear()))?;
+
+            // Art which can't be decoded is just left out.
+            let art = current
+                .as_ref()
+                .and_then(|x| x.artwork.as_ref())
+                .filter(|_| wanted.is_some())
+                .and_then(|x| match task::block_in_place(|| protocol.encode(x)) {
+                    Ok(art) => Some(art),
+                    Err(error) => {
+                        warn!("unable to draw the album art: {:#}", error);
+                        None
+                    }
+                });
+
+            if let Some(art) = art {
+                crossterm::execute!(
+                    stderr(),
+                    SavePosition,
+                    MoveToColumn(width as u16 + 5),
+                    Print(art),
+                    RestorePosition
+                )?;
+            }
+
+            self.drawn = wanted;
+        }
+
+        Ok(())
+    }
+}
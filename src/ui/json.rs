@@ -0,0 +1,67 @@
+//! The JSON frontend, which doesn't draw anything, and instead prints a line of JSON to
+//! stdout whenever something changes, so that lowfi can be wrapped by other programs.
+//!
+//! Every line has the same fields as `lowfi status --json`, along with a few more.
+
+use std::io::{stdout, Write};
+
+use serde_json::json;
+
+use super::{Renderer, State};
+use lowfi::player::{Player, PlayerState};
+
+/// Prints the state as JSON, keeping the last line so that it isn't printed twice in a row.
+#[derive(Default)]
+pub struct Json {
+    /// The line that was printed last.
+    last: String,
+}
+
+impl Renderer for Json {
+    async fn draw(
+        &mut self,
+        queue: &Player,
+        snapshot: &PlayerState,
+        _: &State,
+    ) -> eyre::Result<()> {
+        let current = snapshot.current.as_ref();
+        let state = match current {
+            None if snapshot.disconnected => "offline",
+            None => "loading",
+            Some(_) if snapshot.paused => "paused",
+            Some(_) => "playing",
+        };
+
+        let line = json!({
+            "state": state,
+            "track": current.map(|x| &x.name),
+            "artist": current.and_then(|x| x.artist.as_ref()),
+            "album": current.and_then(|x| x.album.as_ref()),
+            "url": current.map(|x| &x.url),
+            "elapsed": current.map(|_| queue.elapsed().as_secs()),
+            "duration": current.and_then(|x| x.duration).map(|x| x.as_secs()),
+            "volume": snapshot.volume,
+            "muted": snapshot.muted,
+            "speed": snapshot.speed,
+            "repeat": snapshot.repeat,
+            "liked": snapshot.liked,
+            "station": snapshot.station.as_deref(),
+            "progress": queue.progress.borrow().and_then(|x| x.percentage()),
+            "pomodoro": snapshot.pomodoro.map(|x| {
+                let (phase, remaining) = x.phase();
+                json!({ "phase": phase.name(), "remaining": remaining.as_secs() })
+            }),
+        })
+        .to_string();
+
+        if line != self.last {
+            let mut stdout = stdout().lock();
+            writeln!(stdout, "{}", line)?;
+            stdout.flush()?;
+
+            self.last = line;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! The minimal layout of the UI, which is just a single line like
+//! `▶ track name 01:23/02:45  vol 80%`, for tmux panes & narrow terminals.
+
+use std::{io::stderr, time::Duration};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
+use unicode_width::UnicodeWidthStr;
+
+use super::{fit, theme::Theme, Pane, Renderer, State, MIN_WIDTH};
+use lowfi::{
+    config::{Config, Marquee},
+    format_duration,
+    player::{Player, PlayerState},
+    tracks::template::Template,
+};
+
+/// Draws the minimal layout, which fills up the whole line unless there's a width set in the config.
+pub struct Minimal {
+    /// The width from the config, if there is one.
+    width: Option<usize>,
+
+    /// The look of the line, which only uses the accent color.
+    theme: Theme,
+
+    /// How the name of the track is shown.
+    format: Template,
+
+    /// How names that don't fit are scrolled, if they are.
+    marquee: Option<Marquee>,
+}
+
+impl Minimal {
+    /// Creates the layout.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            width: config.width,
+            theme: Theme::new(&config.theme),
+            format: config.format.clone(),
+            marquee: config.marquee.enabled.then(|| config.marquee.clone()),
+        }
+    }
+}
+
+impl Renderer for Minimal {
+    async fn draw(
+        &mut self,
+        queue: &Player,
+        snapshot: &PlayerState,
+        state: &State,
+    ) -> eyre::Result<()> {
+        let theme = &self.theme;
+        let width = self.width.unwrap_or_else(|| {
+            terminal::size().map_or(MIN_WIDTH, |(columns, _)| usize::from(columns))
+        });
+
+        let current = &snapshot.current;
+        let mut symbol = match current {
+            None => "…",
+            Some(_) if snapshot.paused => "‖",
+            Some(_) => "▶",
+        }
+        .to_owned();
+        if snapshot.repeat {
+            symbol.push('↻');
+        }
+
+        // There's no room for the volume input's pane, so it's typed in place of the volume.
+        let volume = if state.pane() == Pane::Volume {
+            format!("vol {}_", state.volume())
+        } else if snapshot.muted {
+            "muted".to_owned()
+        } else {
+            format!("vol {}%", snapshot.volume)
+        };
+        let mut status = match current {
+            Some(current) => format!(
+                " {}/{}  {}",
+                format_duration(&queue.elapsed()),
+                format_duration(&current.duration.unwrap_or(Duration::ZERO)),
+                volume
+            ),
+            None => format!("  {}", volume),
+        };
+        if let Some(pomodoro) = snapshot.pomodoro {
+            let (phase, remaining) = pomodoro.phase();
+            status.push_str(&format!(
+                "  {} {}",
+                phase.name(),
+                format_duration(&remaining)
+            ));
+        }
+
+        // The name gets whatever room is left, and is the first thing to be cut off.
+        let name = current.as_ref().map_or_else(
+            || match *queue.progress.borrow() {
+                _ if snapshot.disconnected => "offline – waiting for network".to_owned(),
+                Some(progress) => format!("loading {}", progress),
+                None => "loading".to_owned(),
+            },
+            |x| self.format.format(x),
+        );
+        let room = width.saturating_sub(symbol.width() + 1 + status.width());
+        let name = match &self.marquee {
+            Some(marquee) => state.marquee(&name, room, marquee),
+            None => fit(&name, room),
+        };
+        let line = format!("{} {}{}", theme.accent(&symbol), name, status);
+
+        crossterm::execute!(
+            stderr(),
+            MoveToColumn(0),
+            Print(line),
+            Clear(ClearType::UntilNewLine),
+            MoveToColumn(0)
+        )?;
+
+        Ok(())
+    }
+}
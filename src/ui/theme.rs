@@ -0,0 +1,323 @@
+//! The look of the UI, which is the characters that the boxes & the progress bar are drawn with,
+//! along with the colors. Every part of the UI is drawn through a [Theme], so that none
+//! of those are hardcoded anywhere else.
+//!
+//! The colors are worked out for what the terminal can show, so that a color like `#ff8000`
+//! is turned into the closest one of 256, or of the basic 16, in terminals without true color.
+
+use std::env;
+
+use crossterm::style::{Color, Stylize};
+use unicode_width::UnicodeWidthStr;
+
+use lowfi::config::{self, Borders, ProgressStyle};
+
+/// The 16 basic colors, along with roughly what they look like in most terminals.
+const BASIC: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::DarkRed, [128, 0, 0]),
+    (Color::DarkGreen, [0, 128, 0]),
+    (Color::DarkYellow, [128, 128, 0]),
+    (Color::DarkBlue, [0, 0, 128]),
+    (Color::DarkMagenta, [128, 0, 128]),
+    (Color::DarkCyan, [0, 128, 128]),
+    (Color::Grey, [192, 192, 192]),
+    (Color::DarkGrey, [128, 128, 128]),
+    (Color::Red, [255, 0, 0]),
+    (Color::Green, [0, 255, 0]),
+    (Color::Yellow, [255, 255, 0]),
+    (Color::Blue, [0, 0, 255]),
+    (Color::Magenta, [255, 0, 255]),
+    (Color::Cyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+];
+
+/// The levels of each channel in the 6x6x6 cube of the 256 color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Depth {
+    None,
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl Depth {
+    /// Works out how many colors the terminal can show from the environment.
+    fn detect() -> Self {
+        // See https://no-color.org, where an empty `NO_COLOR` doesn't count.
+        if env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty()) {
+            return Self::None;
+        }
+
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || env::var_os("WT_SESSION").is_some()
+        {
+            Self::TrueColor
+        } else if term == "dumb" {
+            Self::None
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Basic
+        }
+    }
+
+    /// Turns `color` into the closest one that can be shown, which is [None] if none can.
+    fn degrade(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (Self::None, _) => None,
+            (Self::TrueColor, _) => Some(color),
+            (Self::Ansi256, Color::Rgb { r, g, b }) => Some(Color::AnsiValue(ansi256([r, g, b]))),
+            (Self::Basic, Color::Rgb { r, g, b }) => Some(basic([r, g, b])),
+            (Self::Basic, Color::AnsiValue(value)) => Some(match value {
+                0..=15 => BASIC[usize::from(value)].0,
+                _ => basic(rgb(value)),
+            }),
+            _ => Some(color),
+        }
+    }
+}
+
+/// Gets roughly what a color from the 256 color palette looks like.
+fn rgb(value: u8) -> [u8; 3] {
+    match value {
+        0..=15 => BASIC[usize::from(value)].1,
+        16..=231 => {
+            let index = value - 16;
+            [index / 36, index / 6 % 6, index % 6].map(|x| CUBE[usize::from(x)])
+        }
+        _ => [8 + (value - 232) * 10; 3],
+    }
+}
+
+/// How different two colors look, which is good enough for picking the closest one.
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| u32::from(a.abs_diff(b)).pow(2))
+        .sum()
+}
+
+/// Gets the closest color to `color` in the 256 color palette, leaving out the basic 16,
+/// since those look different from terminal to terminal.
+fn ansi256(color: [u8; 3]) -> u8 {
+    (16..=255)
+        .min_by_key(|x| distance(rgb(*x), color))
+        .unwrap_or(16)
+}
+
+/// Gets the closest of the 16 basic colors to `color`.
+fn basic(color: [u8; 3]) -> Color {
+    BASIC
+        .iter()
+        .min_by_key(|(_, x)| distance(*x, color))
+        .map_or(Color::White, |(x, _)| *x)
+}
+
+/// The characters that a box is drawn with.
+struct Border {
+    horizontal: char,
+    vertical: char,
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+}
+
+impl Border {
+    /// Gets the characters for one of the border styles in the config.
+    fn of(borders: Borders) -> Self {
+        let corners = match borders {
+            Borders::Square => ['┌', '┐', '└', '┘'],
+            Borders::Rounded => ['╭', '╮', '╰', '╯'],
+            Borders::Ascii => ['+'; 4],
+        };
+        let [top_left, top_right, bottom_left, bottom_right] = corners;
+        let (horizontal, vertical) = match borders {
+            Borders::Square | Borders::Rounded => ('─', '│'),
+            Borders::Ascii => ('-', '|'),
+        };
+
+        Self {
+            horizontal,
+            vertical,
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+}
+
+/// The look of the UI, which is worked out from the config once when the UI starts.
+pub struct Theme {
+    /// The characters that the boxes are drawn with.
+    border: Border,
+
+    /// The character of the progress bar for the part that's been played.
+    filled: char,
+
+    /// The character of the progress bar for the part that hasn't been played yet.
+    empty: char,
+
+    /// How the progress bar is drawn.
+    style: ProgressStyle,
+
+    /// The color of the borders, which is the accent color unless
+    /// there's one set, see [config::Theme::border_color].
+    border_color: Option<Color>,
+
+    /// The color that important bits are highlighted with, see [config::Theme::accent].
+    accent: Option<Color>,
+
+    /// The color of notices about problems, see [config::Theme::error].
+    error: Option<Color>,
+}
+
+impl Theme {
+    /// Creates the theme from the config, leaving out the colors if they've been turned
+    /// off, and making them fit what the terminal can show, see [Depth].
+    pub fn new(config: &config::Theme) -> Self {
+        let depth = if config.color {
+            Depth::detect()
+        } else {
+            Depth::None
+        };
+        let color = |x: Option<Color>| x.and_then(|x| depth.degrade(x));
+
+        // The config makes sure that there's exactly two of these.
+        let mut progress = config.progress.chars();
+
+        Self {
+            border: Border::of(config.borders),
+            filled: progress.next().unwrap_or('/'),
+            empty: progress.next().unwrap_or(' '),
+            style: config.progress_style,
+            border_color: color(config.border_color.or(config.accent)),
+            accent: color(config.accent),
+            error: color(config.error),
+        }
+    }
+
+    /// Colors `text`, unless there's no color for it.
+    fn paint(text: &str, color: Option<Color>) -> String {
+        color.map_or_else(|| text.to_owned(), |x| text.with(x).to_string())
+    }
+
+    /// Highlights `text` with the accent color.
+    pub fn accent(&self, text: &str) -> String {
+        Self::paint(text, self.accent)
+    }
+
+    /// Highlights the name of a track, which is bold & in the accent color.
+    pub fn name(&self, text: &str) -> String {
+        Self::paint(&text.bold().to_string(), self.accent)
+    }
+
+    /// Colors `text` as a problem.
+    pub fn error(&self, text: &str) -> String {
+        Self::paint(text, self.error)
+    }
+
+    /// Draws the top border of a box that's `width` columns wide on the inside,
+    /// with a `title` on the left if it isn't empty.
+    pub fn top(&self, title: &str, width: usize) -> String {
+        let title = if title.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", title)
+        };
+
+        let line = format!(
+            "{}{}{}{}{}",
+            self.border.top_left,
+            self.border.horizontal,
+            title,
+            self.horizontal((width + 1).saturating_sub(title.width())),
+            self.border.top_right
+        );
+
+        Self::paint(&line, self.border_color)
+    }
+
+    /// Draws a single row of a box, where `text` should already be exactly as wide as the inside.
+    pub fn row(&self, text: &str) -> String {
+        let vertical = Self::paint(&self.border.vertical.to_string(), self.border_color);
+        format!("{} {} {}", vertical, text, vertical)
+    }
+
+    /// Draws the bottom border of a box that's `width` columns wide on the inside,
+    /// with any `labels` on the right.
+    pub fn bottom(&self, labels: &[String], width: usize) -> String {
+        let labels: String = labels
+            .iter()
+            .map(|x| format!(" {} {}", x, self.border.horizontal))
+            .collect();
+
+        let line = format!(
+            "{}{}{}{}",
+            self.border.bottom_left,
+            self.horizontal((width + 2).saturating_sub(labels.width())),
+            labels,
+            self.border.bottom_right
+        );
+
+        Self::paint(&line, self.border_color)
+    }
+
+    /// Draws the inside of the progress bar, which is `width` columns wide,
+    /// where `fraction` is how much of the track has been played, from 0 to 1.
+    pub fn progress(&self, fraction: f32, width: usize) -> String {
+        let fraction = if fraction.is_finite() {
+            fraction.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        // The full character, the ones for a partly filled column from least to most, & the empty one.
+        let (full, partial, empty): (char, &[char], char) = match self.style {
+            ProgressStyle::Characters => (self.filled, &[], self.empty),
+            ProgressStyle::Blocks => ('█', &['▏', '▎', '▍', '▌', '▋', '▊', '▉'], ' '),
+            ProgressStyle::Braille => ('⣿', &['⡀', '⡄', '⡆', '⡇', '⣇', '⣧', '⣷'], ' '),
+            ProgressStyle::Gradient => ('█', &['▒', '▓'], '░'),
+            ProgressStyle::Percentage => {
+                let text = format!("{}%", (fraction * 100.0).round());
+                let padding = width.saturating_sub(text.width());
+                let left = padding / 2;
+
+                return format!(
+                    "{}{}{}",
+                    " ".repeat(left),
+                    self.accent(&text),
+                    " ".repeat(padding - left)
+                );
+            }
+        };
+
+        let columns = fraction * width as f32;
+        let (filled, step) = if partial.is_empty() {
+            (columns.round() as usize, None)
+        } else {
+            // How far into the next column it is, where 0 is empty.
+            let step = (columns.fract() * (partial.len() + 1) as f32) as usize;
+            (columns as usize, step.checked_sub(1).map(|x| partial[x]))
+        };
+        let filled = filled.min(width);
+
+        let mut played: String = std::iter::repeat_n(full, filled).collect();
+        played.extend(step.filter(|_| filled < width));
+        let left: String = std::iter::repeat_n(empty, width - played.chars().count()).collect();
+
+        format!("{}{}", self.accent(&played), left)
+    }
+
+    /// Gets a horizontal line that's `width` columns long.
+    fn horizontal(&self, width: usize) -> String {
+        std::iter::repeat_n(self.border.horizontal, width).collect()
+    }
+}